@@ -11,7 +11,7 @@ use crate::{
         eliminate_nonsolitary_terminals::eliminate_nonsolitary_terminals,
         eliminate_unit_rules::eliminate_unit_rules,
     },
-    Cfg, Term, Var,
+    Cfg, Rule, Symbol, Term, Var,
 };
 
 #[derive(Debug, Clone)]
@@ -19,17 +19,85 @@ pub struct Cnf<T: Term> {
     pub(crate) nullable: Vec<bool>,
     pub(crate) rules: Vec<CnfRule<T>>,
     pub(crate) vars_map: Vec<Var>,
+    pub(crate) binarized: Binarized<T>,
 }
 
 impl<T: Term> Cnf<T> {
     pub fn of(cfg: Cfg<T>) -> Self {
+        let binarized = Binarized::of(cfg);
+        let weak_cnf = eliminate_epsilon_rules(binarized.cfg.clone());
+        let nullable = weak_cnf.nullable.clone();
+        let (rules, vars_map) = eliminate_unit_rules(weak_cnf);
+
+        Cnf {
+            nullable,
+            rules,
+            vars_map,
+            binarized,
+        }
+    }
+}
+
+/// The grammar obtained from the original one by replacing nonsolitary terminals with `<GEN_TERM>`
+/// variables and splitting long rules with `<GEN_AUX>` variables.
+///
+/// Both transformations only append variables and rules, so the first `n_vars` variables are
+/// exactly the ones of the original grammar, and their rules keep their original indices. This is
+/// what allows parse trees to be translated back into the original grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct Binarized<T: Term> {
+    pub(crate) cfg: Cfg<T>,
+    pub(crate) n_vars: usize,
+}
+
+impl<T: Term> Binarized<T> {
+    pub(crate) fn of(cfg: Cfg<T>) -> Self {
+        let n_vars = cfg.n_vars();
         let cfg = eliminate_nonsolitary_terminals(cfg);
         let cfg = eliminate_long_rules(cfg);
-        let weak_cnf = eliminate_epsilon_rules(cfg);
-        let cnf = eliminate_unit_rules(weak_cnf);
+        Binarized { cfg, n_vars }
+    }
+
+    /// Returns the rules of the original grammar, by putting the bodies of the rules of the
+    /// `<GEN_TERM>` and `<GEN_AUX>` variables back in place of them, which have a rule each.
+    pub(crate) fn original_rules(&self) -> Vec<Rule<T>> {
+        let mut generated_rules = vec![None; self.cfg.n_vars()];
+        for rule in self.cfg.rules.iter() {
+            if rule.head.0 >= self.n_vars {
+                generated_rules[rule.head.0] = Some(rule);
+            }
+        }
+
+        self.cfg
+            .rules
+            .iter()
+            .filter(|rule| rule.head.0 < self.n_vars)
+            .map(|rule| {
+                let mut body = Vec::new();
+                for symbol in rule.body.iter() {
+                    push_expanded(symbol, &generated_rules, &mut body);
+                }
+                Rule::new(rule.head, body)
+            })
+            .collect()
+    }
+}
 
-        cnf
+/// Pushes the symbol onto the body, or the symbols it stands for if it is a generated variable.
+fn push_expanded<T: Term>(
+    symbol: &Symbol<T>,
+    generated_rules: &[Option<&Rule<T>>],
+    body: &mut Vec<Symbol<T>>,
+) {
+    if let Symbol::Var(var) = symbol {
+        if let Some(rule) = generated_rules[var.0] {
+            for symbol in rule.body.iter() {
+                push_expanded(symbol, generated_rules, body);
+            }
+            return;
+        }
     }
+    body.push(symbol.clone());
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -11,7 +11,10 @@ pub fn eliminate_epsilon_rules<T: Term>(cfg: Cfg<T>) -> WeakCnf<T> {
 
     for rule in cfg.rules {
         let Rule { head, mut body } = rule;
-        if body.len() == 1 {
+        if body.is_empty() {
+            // The empty word is accounted for by `nullable` instead.
+            continue;
+        } else if body.len() == 1 {
             match body.pop().unwrap() {
                 Symbol::Term(term) => {
                     rules.push(WeakCnfRule::Terminal(head, term));
@@ -35,7 +38,7 @@ pub fn eliminate_epsilon_rules<T: Term>(cfg: Cfg<T>) -> WeakCnf<T> {
                 rules.push(WeakCnfRule::Unit(head, first));
             }
         } else {
-            panic!("Expected body to have length 1 or 2");
+            panic!("Expected body to have length at most 2");
         }
     }
 
@@ -3,9 +3,11 @@ use std::{cmp::min, collections::VecDeque};
 use crate::{Term, Var};
 
 use super::weak_cnf::{WeakCnf, WeakCnfRule};
-use super::{Cnf, CnfRule};
+use super::CnfRule;
 
-pub fn eliminate_unit_rules<T: Term>(weak_cnf: WeakCnf<T>) -> Cnf<T> {
+/// Returns the rules of the resulting grammar along with a map from the variables of the weak CNF
+/// to the variables of the resulting grammar.
+pub fn eliminate_unit_rules<T: Term>(weak_cnf: WeakCnf<T>) -> (Vec<CnfRule<T>>, Vec<Var>) {
     let mut graph: Vec<Vec<usize>> = vec![vec![]; weak_cnf.nullable.len()];
 
     for rule in &weak_cnf.rules {
@@ -33,17 +35,13 @@ pub fn eliminate_unit_rules<T: Term>(weak_cnf: WeakCnf<T>) -> Cnf<T> {
         nonunit_rules_at_each_scc,
     );
 
-    Cnf {
-        nullable: weak_cnf.nullable,
-        rules,
-        vars_map,
-    }
+    (rules, vars_map)
 }
 
 fn get_reversed_quotient_graph(
-    graph: &Vec<Vec<usize>>,
+    graph: &[Vec<usize>],
     n_sccs: usize,
-    vars_map: &Vec<Var>,
+    vars_map: &[Var],
 ) -> Vec<Vec<usize>> {
     let mut reversed_quotient_graph: Vec<Vec<usize>> = vec![vec![]; n_sccs];
 
@@ -59,7 +57,7 @@ fn get_reversed_quotient_graph(
 fn get_nonunit_rules_at_each_scc<T: Term>(
     weak_cnf_rules: Vec<WeakCnfRule<T>>,
     n_sccs: usize,
-    vars_map: &Vec<Var>,
+    vars_map: &[Var],
 ) -> Vec<Vec<CnfRule<T>>> {
     let mut nonunit_rules: Vec<Vec<CnfRule<T>>> = vec![vec![]; n_sccs];
 
@@ -87,19 +85,21 @@ fn replace_unit_rules_at_each_scc<T: Term>(
     mut reversed_quotient_graph: Vec<Vec<usize>>,
     mut nonunit_rules: Vec<Vec<CnfRule<T>>>,
 ) -> Vec<CnfRule<T>> {
-    // Degree = Indegree in reversed quotient graph
+    // Degree = Indegree in reversed quotient graph. Unit rules inside an SCC show up as self loops,
+    // which must not count towards the degree, or the SCC would never be processed.
     let mut degree = vec![0; n_sccs];
-    for (_, adj_list) in reversed_quotient_graph.iter_mut().enumerate() {
+    for (u, adj_list) in reversed_quotient_graph.iter_mut().enumerate() {
+        adj_list.sort();
+        adj_list.dedup();
+        adj_list.retain(|&v| v != u);
         for v in adj_list.iter() {
             degree[*v] += 1;
         }
-        adj_list.sort();
-        adj_list.dedup();
     }
 
     let mut queue = VecDeque::<usize>::new();
-    for u in 0..n_sccs {
-        if degree[u] == 0 {
+    for (u, degree) in degree.iter().enumerate() {
+        if *degree == 0 {
             queue.push_back(u);
         }
     }
@@ -130,7 +130,7 @@ fn replace_unit_rules_at_each_scc<T: Term>(
     let mut all_nonunit_rules = Vec::<CnfRule<T>>::new();
 
     for nonunit_rules in nonunit_rules.into_iter() {
-        all_nonunit_rules.extend(nonunit_rules.into_iter());
+        all_nonunit_rules.extend(nonunit_rules);
     }
 
     all_nonunit_rules
@@ -3,24 +3,53 @@ use crate::{
     Term,
 };
 
+pub type SemanticRule<T, M> = fn(Vec<InterpretedSymbol<T, M>>) -> M;
+
 pub struct Interpreter<T: Term, M> {
-    rules: Vec<fn(Vec<InterpretedSymbol<T, M>>) -> M>,
+    rules: Vec<SemanticRule<T, M>>,
 }
 
 impl<T: Term, M> Interpreter<T, M> {
-    pub fn new(rules: Vec<fn(Vec<InterpretedSymbol<T, M>>) -> M>) -> Self {
+    pub fn new(rules: Vec<SemanticRule<T, M>>) -> Self {
         Self { rules }
     }
 
+    /// Applies the semantic rules to the parse tree from the bottom up.
+    ///
+    /// **Panics** if some part of the word was skipped over as a [`ParsedSymbol::Error`], for
+    /// which [`Interpreter::interpret_with_errors`] is to be used instead.
     pub fn interpret(&self, parse_tree: ParseTree<T>) -> M {
+        self.interpret_with_errors(parse_tree, &|_| {
+            panic!("Expected a parse tree without errors to interpret")
+        })
+    }
+
+    /// Applies the semantic rules to a parse tree which may have errors in it, such as the ones
+    /// returned by [`EarleyParser::parse_with_recovery`](crate::parse::EarleyParser::parse_with_recovery).
+    /// The semantic rule cannot be applied to a rule some of whose symbols were skipped over as a
+    /// [`ParsedSymbol::Error`], so the meaning of its parse tree is given by `on_error` instead.
+    pub fn interpret_with_errors(
+        &self,
+        parse_tree: ParseTree<T>,
+        on_error: &impl Fn(ParseTree<T>) -> M,
+    ) -> M {
+        if parse_tree
+            .children
+            .iter()
+            .any(|child| matches!(child, ParsedSymbol::Error(_)))
+        {
+            return on_error(parse_tree);
+        }
+
         let interpreted_symbols = parse_tree
             .children
             .into_iter()
             .map(|child| match child {
                 ParsedSymbol::Term(term) => InterpretedSymbol::Term(term),
                 ParsedSymbol::Var(parse_tree) => {
-                    InterpretedSymbol::Var(self.interpret(*parse_tree))
+                    InterpretedSymbol::Var(self.interpret_with_errors(*parse_tree, on_error))
                 }
+                ParsedSymbol::Error(_) => unreachable!("Expected errors to be handled above"),
             })
             .collect();
         (self.rules[parse_tree.rule_idx])(interpreted_symbols)
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, hash::Hash, rc::Rc};
+use std::{collections::BTreeSet, fmt::Debug, hash::Hash, rc::Rc};
 
 use rand::{rngs::ThreadRng, seq::IteratorRandom};
 
@@ -36,6 +36,152 @@ impl<T: Term> Cfg<T> {
         self.rules.iter().filter(move |rule| rule.head == var)
     }
 
+    /// For every variable, finds a rule that derives the empty word using only variables for which
+    /// such a rule was found before. Following these rules therefore never loops.
+    pub(crate) fn nullable_rules(&self) -> Vec<Option<usize>> {
+        let mut nullable_rules = vec![None; self.n_vars()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rule_idx, rule) in self.rules.iter().enumerate() {
+                if nullable_rules[rule.head.0].is_some() {
+                    continue;
+                }
+                let is_body_nullable = rule.body.iter().all(|symbol| match symbol {
+                    Symbol::Var(var) => nullable_rules[var.0].is_some(),
+                    Symbol::Term(_) => false,
+                });
+                if is_body_nullable {
+                    nullable_rules[rule.head.0] = Some(rule_idx);
+                    changed = true;
+                }
+            }
+        }
+
+        nullable_rules
+    }
+
+    /// For every variable, finds whether it derives any word at all.
+    pub(crate) fn productive_vars(&self) -> Vec<bool> {
+        let mut productive = vec![false; self.n_vars()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in self.rules.iter() {
+                if productive[rule.head.0] {
+                    continue;
+                }
+                let is_body_productive = rule.body.iter().all(|symbol| match symbol {
+                    Symbol::Var(var) => productive[var.0],
+                    Symbol::Term(_) => true,
+                });
+                if is_body_productive {
+                    productive[rule.head.0] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        productive
+    }
+
+    /// For every variable, finds its rules which derive some word, i.e., the ones with only
+    /// variables deriving some word in their bodies.
+    pub(crate) fn productive_rules_by_var(&self) -> Vec<Vec<usize>> {
+        let productive = self.productive_vars();
+        let mut rules_by_var = vec![vec![]; self.n_vars()];
+        for (rule_idx, rule) in self.rules.iter().enumerate() {
+            let is_productive = rule.body.iter().all(|symbol| match symbol {
+                Symbol::Var(var) => productive[var.0],
+                Symbol::Term(_) => true,
+            });
+            if is_productive {
+                rules_by_var[rule.head.0].push(rule_idx);
+            }
+        }
+        rules_by_var
+    }
+
+    /// For every variable, finds the terminals which begin some word derived by the variable.
+    pub(crate) fn first_sets(&self) -> Vec<BTreeSet<T>> {
+        let nullable = self.nullable_rules();
+        let mut first_sets = vec![BTreeSet::new(); self.n_vars()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in self.rules.iter() {
+                let first = self.first_of(&rule.body, &first_sets, &nullable).0;
+                let first_set = &mut first_sets[rule.head.0];
+                let len = first_set.len();
+                first_set.extend(first);
+                changed |= first_set.len() != len;
+            }
+        }
+
+        first_sets
+    }
+
+    /// For every variable, finds the terminals which follow the variable in some sentential form
+    /// derived from the start variable, with `None` standing for the end of the word.
+    pub(crate) fn follow_sets(&self, first_sets: &[BTreeSet<T>]) -> Vec<BTreeSet<Option<T>>> {
+        let nullable = self.nullable_rules();
+        let mut follow_sets = vec![BTreeSet::new(); self.n_vars()];
+        follow_sets[self.start_var.0].insert(None);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in self.rules.iter() {
+                for (i, symbol) in rule.body.iter().enumerate() {
+                    let Symbol::Var(var) = symbol else {
+                        continue;
+                    };
+                    let (first, is_rest_nullable) =
+                        self.first_of(&rule.body[i + 1..], first_sets, &nullable);
+                    let mut follow: Vec<_> = first.into_iter().map(Some).collect();
+                    if is_rest_nullable {
+                        follow.extend(follow_sets[rule.head.0].iter().cloned());
+                    }
+                    let follow_set = &mut follow_sets[var.0];
+                    let len = follow_set.len();
+                    follow_set.extend(follow);
+                    changed |= follow_set.len() != len;
+                }
+            }
+        }
+
+        follow_sets
+    }
+
+    /// Returns the terminals which begin some word derived by the given symbols, and whether the
+    /// symbols derive the empty word.
+    fn first_of(
+        &self,
+        symbols: &[Symbol<T>],
+        first_sets: &[BTreeSet<T>],
+        nullable: &[Option<usize>],
+    ) -> (BTreeSet<T>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            match symbol {
+                Symbol::Term(term) => {
+                    first.insert(term.clone());
+                    return (first, false);
+                }
+                Symbol::Var(var) => {
+                    first.extend(first_sets[var.0].iter().cloned());
+                    if nullable[var.0].is_none() {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
     /// Generates a random word in the language defined by the given variable.
     ///
     /// **Warning**: The distribution of words is not guaranteed. It is not even
@@ -55,7 +201,7 @@ impl<T: Term> Cfg<T> {
                     word.push(term.to_owned());
                 }
                 Symbol::Var(var) => {
-                    word.extend(self.random_word_impl(*var, rng).into_iter());
+                    word.extend(self.random_word_impl(*var, rng));
                 }
             }
         }
@@ -110,7 +256,7 @@ impl<T: Term> Rule<T> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Symbol<T: Term> {
     Var(Var),
     Term(T),
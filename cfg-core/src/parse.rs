@@ -1,10 +1,35 @@
+mod correction;
+mod count;
 mod cyk;
 mod earley;
+mod error;
+mod forest;
+mod gll;
+mod glr;
+mod left_corner;
+mod ll1;
+mod lr;
+mod lr_parser;
+mod semiring;
+mod unger;
+mod valiant;
 
 use std::{fmt::Display, rc::Rc};
 
+pub use correction::*;
+pub use count::*;
 pub use cyk::*;
 pub use earley::*;
+pub use error::*;
+pub use forest::*;
+pub use gll::*;
+pub use glr::*;
+pub use left_corner::*;
+pub use ll1::*;
+pub use lr_parser::*;
+pub use semiring::*;
+pub use unger::*;
+pub use valiant::*;
 
 use crate::{Term, Var};
 
@@ -12,13 +37,79 @@ pub trait Parser<W> {
     type TermType: Term;
 
     fn test(&self, word: W) -> bool {
-        self.parse(word).is_some()
+        self.parse(word).is_ok()
     }
 
-    fn parse(&self, word: W) -> Option<ParseTree<Self::TermType>>;
+    /// Returns a parse tree of the word, or where the word goes wrong if it is not in the language.
+    fn parse(&self, word: W) -> Result<ParseTree<Self::TermType>, ParseError<Self::TermType>>;
+
+    /// Parses the word into a shared packed parse forest holding all of its parse trees, or returns
+    /// `None` if the word is not in the language.
+    fn parse_forest(&self, word: W) -> Option<ParseForest<'_, Self::TermType>>;
+
+    /// Returns an iterator over the parse trees of the word, in order of the number of rules
+    /// applied in them, fewest first. Trees in which a variable derives the same part of the word
+    /// as one of its ancestors are left out, so that there are finitely many even with cycles.
+    ///
+    /// The trees are built one at a time from the parse forest of the word.
+    fn parse_all(&self, word: W) -> impl Iterator<Item = ParseTree<Self::TermType>> {
+        self.parse_forest(word)
+            .into_iter()
+            .flat_map(ParseForest::into_parse_trees)
+    }
+
+    /// Returns the number of parse trees of the word, which is zero if the word is not in the
+    /// language and infinite if a variable derives itself in one of them.
+    fn count_parses(&self, word: W) -> ParseCount {
+        self.parse_forest(word)
+            .map_or(ParseCount::Finite(BigUint::from(0u32)), |parse_forest| {
+                parse_forest.count_parse_trees()
+            })
+    }
+
+    /// Returns the `k` parse trees of the word with the lowest costs, along with their costs, given
+    /// the cost of every rule. The cost of a parse tree is the sum of the costs of the rules applied
+    /// in it, which must not be negative.
+    fn parse_k_best(
+        &self,
+        word: W,
+        k: usize,
+        rule_cost: impl Fn(usize) -> f64,
+    ) -> Vec<(ParseTree<Self::TermType>, f64)> {
+        self.parse_forest(word)
+            .map_or_else(Vec::new, |parse_forest| {
+                parse_forest
+                    .into_cheapest_parse_trees(rule_cost)
+                    .take(k)
+                    .collect()
+            })
+    }
+
+    /// Returns a parse tree of the longest prefix of the word in the language, along with the
+    /// position at which the prefix ends, or `None` if no prefix of the word is in the language.
+    ///
+    /// The chart parsers find every prefix in the language in one run over the word. The default
+    /// is a fallback for the parsers which only tell whether the whole word is: the prefixes are
+    /// parsed one by one, longest first, skipping over those longer than where the last one parsed
+    /// goes wrong. This may parse the word as many times as it has terminals.
+    fn parse_prefix(&self, word: W) -> Option<(ParseTree<Self::TermType>, usize)>
+    where
+        W: From<Vec<Self::TermType>> + Into<Vec<Self::TermType>>,
+    {
+        let mut word: Vec<_> = word.into();
+        loop {
+            match self.parse(W::from(word.clone())) {
+                Ok(parse_tree) => return Some((parse_tree, word.len())),
+                Err(parse_error) => {
+                    let end = parse_error.position().min(word.len().checked_sub(1)?);
+                    word.truncate(end);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseTree<T: Term> {
     pub(crate) root_var: Var,
     pub(crate) root_var_name: Rc<str>,
@@ -26,10 +117,46 @@ pub struct ParseTree<T: Term> {
     pub(crate) children: Vec<ParsedSymbol<T>>,
 }
 
-#[derive(Debug, Clone)]
+impl<T: Term> ParseTree<T> {
+    pub fn root_var(&self) -> Var {
+        self.root_var
+    }
+
+    pub fn root_var_name(&self) -> &str {
+        &self.root_var_name
+    }
+
+    /// The index of the rule applied at the root, in the order in which the rules were added to the
+    /// grammar.
+    pub fn rule_idx(&self) -> usize {
+        self.rule_idx
+    }
+
+    pub fn children(&self) -> &[ParsedSymbol<T>] {
+        &self.children
+    }
+
+    /// The number of rules applied in the tree, i.e., the number of its nodes labelled with a
+    /// variable.
+    pub fn n_rules_applied(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(|child| match child {
+                ParsedSymbol::Term(_) | ParsedSymbol::Error(_) => 0,
+                ParsedSymbol::Var(parse_tree) => parse_tree.n_rules_applied(),
+            })
+            .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsedSymbol<T: Term> {
     Term(T),
     Var(Box<ParseTree<T>>),
+    /// The terminals skipped over while recovering from an error, standing in for the symbols of
+    /// the rule body that could not be parsed.
+    Error(Vec<T>),
 }
 
 pub struct ParseTreeFormatter<'a, T: Term> {
@@ -76,7 +203,7 @@ impl<'a, T: Term + Display> Display for ParseTreeFormatter<'a, T> {
 impl<T: Term + Display> ParseTree<T> {
     pub fn fmt_with_options<'a>(&'a self, options: FormatOptions) -> ParseTreeFormatter<'a, T> {
         ParseTreeFormatter {
-            parse_tree: &self,
+            parse_tree: self,
             options,
         }
     }
@@ -99,6 +226,14 @@ impl<T: Term + Display> ParseTree<T> {
                 ParsedSymbol::Var(parse_tree) => {
                     parse_tree.fmt_impl(options, bars, f)?;
                 }
+                ParsedSymbol::Error(terms) => {
+                    fmt_parse_tree_node(&"<ERROR>", options, bars, f)?;
+                    for (j, term) in terms.iter().enumerate() {
+                        bars.push(j + 1 != terms.len());
+                        fmt_parse_tree_node(term, options, bars, f)?;
+                        bars.pop();
+                    }
+                }
             }
             bars.pop();
         }
@@ -109,12 +244,12 @@ impl<T: Term + Display> ParseTree<T> {
 fn fmt_parse_tree_node(
     node: &impl Display,
     options: &FormatOptions,
-    bars: &Vec<bool>,
+    bars: &[bool],
     f: &mut std::fmt::Formatter<'_>,
 ) -> std::fmt::Result {
     if bars.len() > 1 {
-        for i in 0..bars.len() - 1 {
-            if bars[i] {
+        for bar in &bars[..bars.len() - 1] {
+            if *bar {
                 write!(f, "{}", options.style.vertical)?;
             } else {
                 write!(f, " ")?;
@@ -133,8 +268,8 @@ fn fmt_parse_tree_node(
         for _ in 0..options.indendation {
             write!(f, "{}", options.style.horizontal)?;
         }
-        write!(f, "{}", " ")?;
+        write!(f, " ")?;
     }
-    write!(f, "{}\n", node)?;
+    writeln!(f, "{}", node)?;
     Ok(())
 }
@@ -0,0 +1,324 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    rc::Rc,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{Rule, Symbol, Term, Var};
+
+use super::{forest::Cost, ParseTree, ParsedSymbol};
+
+/// An edit of a single terminal of a word, at a position in the word as it was given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit<T: Term> {
+    /// The terminal is inserted before the one at the position, or at the end of the word if the
+    /// position is its length.
+    Insert {
+        position: usize,
+        term: T,
+    },
+    Delete {
+        position: usize,
+        term: T,
+    },
+    Substitute {
+        position: usize,
+        found: T,
+        replacement: T,
+    },
+}
+
+/// The costs of the edits of single terminals, which must not be negative.
+pub trait EditCosts<T> {
+    fn insert(&self, term: &T) -> f64;
+
+    fn delete(&self, term: &T) -> f64;
+
+    fn substitute(&self, found: &T, replacement: &T) -> f64;
+}
+
+/// Costs under which every edit costs 1, so that the cost of a correction is the Levenshtein
+/// distance between the word and the corrected word.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitEditCosts;
+
+impl<T> EditCosts<T> for UnitEditCosts {
+    fn insert(&self, _term: &T) -> f64 {
+        1.0
+    }
+
+    fn delete(&self, _term: &T) -> f64 {
+        1.0
+    }
+
+    fn substitute(&self, _found: &T, _replacement: &T) -> f64 {
+        1.0
+    }
+}
+
+/// A parse tree of the word in the language closest to a given word, along with the edits turning
+/// the given word into it and their total cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction<T: Term> {
+    parse_tree: ParseTree<T>,
+    edits: Vec<Edit<T>>,
+    cost: f64,
+}
+
+impl<T: Term> Correction<T> {
+    pub fn parse_tree(&self) -> &ParseTree<T> {
+        &self.parse_tree
+    }
+
+    pub fn into_parse_tree(self) -> ParseTree<T> {
+        self.parse_tree
+    }
+
+    /// The edits, in order of their positions.
+    pub fn edits(&self) -> &[Edit<T>] {
+        &self.edits
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+}
+
+/// An Earley state, as a rule, the number of symbols of its body parsed, and the span `l..r` of the
+/// word they were parsed from.
+type Item = (usize, usize, usize, usize);
+
+/// How the cheapest derivation of an item was found, from the items found before it.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Predict,
+    Match(usize),
+    Insert(usize),
+    Substitute(usize),
+    Delete(usize),
+    /// The item was found by completing the variable after the dot of the first item with the
+    /// second one.
+    Complete(usize, usize),
+}
+
+/// Finds the cheapest way to derive every Earley state in a covering grammar in the style of Aho &
+/// Peterson (1972), in which every terminal may also be inserted, deleted or substituted for
+/// another, at a cost.
+///
+/// Since costs only grow as items are combined, items are found cheapest first as in Knuth (1977),
+/// so that each of them is final once found and the first complete start rule spanning the word
+/// gives the correction.
+pub(super) struct Corrector<'a, T: Term, C: EditCosts<T>> {
+    start_var: Var,
+    var_names: &'a [Rc<str>],
+    rules: &'a [Rule<T>],
+    rules_by_var: &'a [Vec<usize>],
+    edit_costs: &'a C,
+    word: Vec<T>,
+    items: Vec<(Item, f64, Step)>,
+    found: FxHashMap<Item, usize>,
+    /// The items found which are waiting on a variable at a position of the word.
+    waiting: FxHashMap<(usize, Var), Vec<usize>>,
+    /// The complete items found for a variable at a position of the word.
+    completed: FxHashMap<(usize, Var), Vec<usize>>,
+    predicted: HashSet<(usize, Var)>,
+    candidates: Vec<(Item, Step)>,
+    queue: BinaryHeap<Reverse<(Cost, usize)>>,
+}
+
+impl<'a, T: Term, C: EditCosts<T>> Corrector<'a, T, C> {
+    pub(super) fn new(
+        start_var: Var,
+        var_names: &'a [Rc<str>],
+        rules: &'a [Rule<T>],
+        rules_by_var: &'a [Vec<usize>],
+        edit_costs: &'a C,
+        word: Vec<T>,
+    ) -> Self {
+        Self {
+            start_var,
+            var_names,
+            rules,
+            rules_by_var,
+            edit_costs,
+            word,
+            items: Vec::new(),
+            found: FxHashMap::default(),
+            waiting: FxHashMap::default(),
+            completed: FxHashMap::default(),
+            predicted: HashSet::new(),
+            candidates: Vec::new(),
+            queue: BinaryHeap::new(),
+        }
+    }
+
+    pub(super) fn correct(mut self) -> Option<Correction<T>> {
+        let n = self.word.len();
+        self.predict(0, self.start_var);
+
+        while let Some(Reverse((Cost(cost), candidate))) = self.queue.pop() {
+            let (item, step) = self.candidates[candidate];
+            if self.found.contains_key(&item) {
+                continue;
+            }
+            let idx = self.items.len();
+            self.items.push((item, cost, step));
+            self.found.insert(item, idx);
+
+            let (rule_idx, parsed, l, r) = item;
+            let rule = &self.rules[rule_idx];
+            if r < n {
+                let cost = cost + self.edit_costs.delete(&self.word[r]);
+                self.push((rule_idx, parsed, l, r + 1), cost, Step::Delete(idx));
+            }
+            match rule.body.get(parsed) {
+                None => {
+                    if rule.head == self.start_var && l == 0 && r == n {
+                        return Some(self.correction(idx));
+                    }
+                    self.completed.entry((l, rule.head)).or_default().push(idx);
+                    for waiting_idx in self
+                        .waiting
+                        .get(&(l, rule.head))
+                        .cloned()
+                        .unwrap_or_default()
+                    {
+                        self.complete(waiting_idx, idx);
+                    }
+                }
+                Some(Symbol::Var(var)) => {
+                    let var = *var;
+                    self.waiting.entry((r, var)).or_default().push(idx);
+                    self.predict(r, var);
+                    for completed_idx in self.completed.get(&(r, var)).cloned().unwrap_or_default()
+                    {
+                        self.complete(idx, completed_idx);
+                    }
+                }
+                Some(Symbol::Term(term)) => {
+                    let next = (rule_idx, parsed + 1, l, r);
+                    self.push(next, cost + self.edit_costs.insert(term), Step::Insert(idx));
+                    if let Some(found) = self.word.get(r) {
+                        let next = (rule_idx, parsed + 1, l, r + 1);
+                        if found == term {
+                            self.push(next, cost, Step::Match(idx));
+                        } else {
+                            let cost = cost + self.edit_costs.substitute(found, term);
+                            self.push(next, cost, Step::Substitute(idx));
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn push(&mut self, item: Item, cost: f64, step: Step) {
+        if self.found.contains_key(&item) {
+            return;
+        }
+        self.queue
+            .push(Reverse((Cost(cost), self.candidates.len())));
+        self.candidates.push((item, step));
+    }
+
+    fn predict(&mut self, r: usize, var: Var) {
+        if !self.predicted.insert((r, var)) {
+            return;
+        }
+        for &rule_idx in self.rules_by_var[var.0].iter() {
+            self.push((rule_idx, 0, r, r), 0.0, Step::Predict);
+        }
+    }
+
+    fn complete(&mut self, waiting_idx: usize, completed_idx: usize) {
+        let ((rule_idx, parsed, l, _), waiting_cost, _) = self.items[waiting_idx];
+        let ((_, _, _, r), completed_cost, _) = self.items[completed_idx];
+        self.push(
+            (rule_idx, parsed + 1, l, r),
+            waiting_cost + completed_cost,
+            Step::Complete(waiting_idx, completed_idx),
+        );
+    }
+
+    fn correction(&self, idx: usize) -> Correction<T> {
+        let mut edits = Vec::new();
+        let parse_tree = self.build(idx, &mut edits);
+        Correction {
+            parse_tree,
+            edits,
+            cost: self.items[idx].1,
+        }
+    }
+
+    fn build(&self, idx: usize, edits: &mut Vec<Edit<T>>) -> ParseTree<T> {
+        let ((rule_idx, ..), ..) = self.items[idx];
+        let head = self.rules[rule_idx].head;
+        let mut children = Vec::new();
+        self.build_children(idx, &mut children, edits);
+        ParseTree {
+            root_var: head,
+            root_var_name: self.var_names[head.0].clone(),
+            rule_idx,
+            children,
+        }
+    }
+
+    /// Rebuilds the parsed symbols of the body of the rule of an item, along with the edits made to
+    /// the span of the word they were parsed from.
+    fn build_children(
+        &self,
+        idx: usize,
+        children: &mut Vec<ParsedSymbol<T>>,
+        edits: &mut Vec<Edit<T>>,
+    ) {
+        let ((rule_idx, parsed, ..), _, step) = self.items[idx];
+        let prev_item = |prev_idx: usize| self.items[prev_idx].0;
+        let term = || match &self.rules[rule_idx].body[parsed - 1] {
+            Symbol::Term(term) => term.clone(),
+            Symbol::Var(_) => panic!("Expected a terminal before the dot"),
+        };
+        match step {
+            Step::Predict => {}
+            Step::Match(prev_idx) => {
+                self.build_children(prev_idx, children, edits);
+                children.push(ParsedSymbol::Term(term()));
+            }
+            Step::Insert(prev_idx) => {
+                self.build_children(prev_idx, children, edits);
+                let (.., position) = prev_item(prev_idx);
+                edits.push(Edit::Insert {
+                    position,
+                    term: term(),
+                });
+                children.push(ParsedSymbol::Term(term()));
+            }
+            Step::Substitute(prev_idx) => {
+                self.build_children(prev_idx, children, edits);
+                let (.., position) = prev_item(prev_idx);
+                edits.push(Edit::Substitute {
+                    position,
+                    found: self.word[position].clone(),
+                    replacement: term(),
+                });
+                children.push(ParsedSymbol::Term(term()));
+            }
+            Step::Delete(prev_idx) => {
+                self.build_children(prev_idx, children, edits);
+                let (.., position) = prev_item(prev_idx);
+                edits.push(Edit::Delete {
+                    position,
+                    term: self.word[position].clone(),
+                });
+            }
+            Step::Complete(waiting_idx, completed_idx) => {
+                self.build_children(waiting_idx, children, edits);
+                let parse_tree = self.build(completed_idx, edits);
+                children.push(ParsedSymbol::Var(Box::new(parse_tree)));
+            }
+        }
+    }
+}
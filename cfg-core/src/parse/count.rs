@@ -0,0 +1,30 @@
+use std::fmt::Display;
+
+pub use num_bigint::BigUint;
+
+/// The number of parse trees of a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCount {
+    Finite(BigUint),
+    /// The word has infinitely many parse trees, as some variable derives itself (`A =>+ A`)
+    /// somewhere in them.
+    Infinite,
+}
+
+impl ParseCount {
+    pub fn is_ambiguous(&self) -> bool {
+        match self {
+            ParseCount::Finite(count) => *count > BigUint::from(1u32),
+            ParseCount::Infinite => true,
+        }
+    }
+}
+
+impl Display for ParseCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseCount::Finite(count) => write!(f, "{}", count),
+            ParseCount::Infinite => write!(f, "infinitely many"),
+        }
+    }
+}
@@ -1,28 +1,34 @@
-use std::{
-    collections::HashMap,
-    ops::{Index, IndexMut},
-};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use crate::{
-    cnf::{Cnf, CnfRule},
-    Cfg, Term, Var,
+    cnf::{Binarized, Cnf, CnfRule},
+    Cfg, Rule, Symbol, Term, Var,
 };
 
-use super::{ParseTree, Parser};
+use super::{forest::SpanForestBuilder, ParseError, ParseForest, ParseTree, ParsedSymbol, Parser};
 
-// TODO: Implement the simpler CYK. This will allow recovering parse trees in the original grammar
-// rather than a derived grammar
 #[derive(Debug, Clone)]
 pub struct CykParser<T: Term> {
-    start: Var,
-    nullable: Vec<bool>,
-    terms_to_vars: HashMap<T, Vec<Var>>,
-    binary_rules: Vec<CnfRule<T>>,
+    pub(super) start: Var,
+    pub(super) nullable: Vec<bool>,
+    pub(super) n_words: usize,
+    pub(super) terms_to_vars: HashMap<T, Vec<Var>>,
+    /// For every variable `B`, the set of variables `C` for which there is a rule `A => BC`.
+    right_vars: Vec<Vec<u64>>,
+    /// For every variable `B`, the pairs `(C, heads)` sorted by `C`, where `heads` is the set of
+    /// variables `A` for which there is a rule `A => BC`.
+    pub(super) heads: Vec<Vec<(usize, Vec<u64>)>>,
+    pub(super) vars_map: Vec<Var>,
+    binarized: Binarized<T>,
+    /// The rules of the original grammar, from which parse forests are built.
+    rules: Vec<Rule<T>>,
+    nullable_rules: Vec<Option<usize>>,
+    unit_like_rules: Vec<Vec<usize>>,
 }
 
 impl<T: Term> CykParser<T> {
     pub fn of(cfg: Cfg<T>) -> Self {
-        let start_var = cfg.start_var.clone();
+        let start_var = cfg.start_var;
         Self::of_cnf(Cnf::of(cfg), start_var)
     }
 
@@ -31,16 +37,13 @@ impl<T: Term> CykParser<T> {
             nullable,
             rules,
             vars_map,
+            binarized,
         }: Cnf<T>,
         start: Var,
     ) -> Self {
-        let (terminal_rules, binary_rules) = rules.into_iter().partition(|rule| {
-            if let CnfRule::Terminal(_, _) = rule {
-                true
-            } else {
-                false
-            }
-        });
+        let (terminal_rules, binary_rules): (Vec<_>, Vec<_>) = rules
+            .into_iter()
+            .partition(|rule| matches!(rule, CnfRule::Terminal(_, _)));
 
         let mut terms_to_vars = HashMap::<T, Vec<Var>>::new();
         for terminal_rule in terminal_rules {
@@ -53,11 +56,96 @@ impl<T: Term> CykParser<T> {
                 .or_insert(vec![var]);
         }
 
+        let n_cnf_vars = vars_map.iter().map(|var| var.0 + 1).max().unwrap_or(0);
+        let n_words = n_cnf_vars.div_ceil(64);
+
+        let mut right_vars = vec![vec![0; n_words]; n_cnf_vars];
+        let mut heads_by_pair = vec![BTreeMap::<usize, Vec<u64>>::new(); n_cnf_vars];
+        for binary_rule in binary_rules {
+            let CnfRule::Binary(first, second, third) = binary_rule else {
+                panic!("Expected a binary rule");
+            };
+            insert_bit(&mut right_vars[second.0], third.0);
+            let heads = heads_by_pair[second.0]
+                .entry(third.0)
+                .or_insert_with(|| vec![0; n_words]);
+            insert_bit(heads, first.0);
+        }
+        let heads = heads_by_pair
+            .into_iter()
+            .map(|heads| heads.into_iter().collect())
+            .collect();
+
+        let nullable_rules = binarized.cfg.nullable_rules();
+        let unit_like_rules = get_unit_like_rules(&binarized.cfg, &nullable);
+        let rules = binarized.original_rules();
+
         Self {
-            start: vars_map[start.0],
+            start,
             nullable,
+            n_words,
             terms_to_vars,
-            binary_rules,
+            right_vars,
+            heads,
+            vars_map,
+            binarized,
+            rules,
+            nullable_rules,
+            unit_like_rules,
+        }
+    }
+
+    fn fill_chart(&self, word: &[T]) -> BitChart {
+        let n = word.len();
+
+        let mut chart = BitChart::new(n, self.n_words);
+
+        for (i, term) in word.iter().enumerate() {
+            let Some(vars) = self.terms_to_vars.get(term) else {
+                continue;
+            };
+            for var in vars {
+                insert_bit(chart.cell_mut(i, i), var.0);
+            }
+        }
+
+        let mut cell = vec![0; self.n_words];
+        for len in 2..=n {
+            for i in 0..=n - len {
+                let j = i + len - 1;
+                cell.fill(0);
+                for k in i..j {
+                    self.combine(chart.cell(i, k), chart.cell(k + 1, j), &mut cell);
+                }
+                chart.cell_mut(i, j).copy_from_slice(&cell);
+            }
+        }
+
+        chart
+    }
+
+    /// Adds to `cell` every variable `A` with a rule `A => BC` such that `B` is in `left` and `C` is
+    /// in `right`.
+    fn combine(&self, left: &[u64], right: &[u64], cell: &mut [u64]) {
+        for second in ones(left) {
+            let heads = &self.heads[second];
+            if heads.is_empty() {
+                continue;
+            }
+            let right_vars = &self.right_vars[second];
+            for (w, (right_vars, right)) in right_vars.iter().zip(right).enumerate() {
+                let mut bits = right_vars & right;
+                while bits != 0 {
+                    let third = w * 64 + bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    let idx = heads
+                        .binary_search_by_key(&third, |(third, _)| *third)
+                        .expect("Expected a rule for the pair of variables");
+                    for (cell, heads) in cell.iter_mut().zip(&heads[idx].1) {
+                        *cell |= heads;
+                    }
+                }
+            }
         }
     }
 }
@@ -71,67 +159,502 @@ impl<T: Term> Parser<Vec<T>> for CykParser<T> {
         }
 
         let n = input.len();
-        let m = self.nullable.len();
+        let chart = self.fill_chart(&input);
+
+        contains_bit(chart.cell(0, n - 1), self.vars_map[self.start.0].0)
+    }
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        let chart = self.fill_chart(&word);
+        self.parse_chart(word, chart)
+    }
 
-        let mut dp = Flat3dVec::filled_with(n, n, m, false);
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let chart = self.fill_chart(&word);
+        self.chart_forest(&word, &chart)
+    }
+
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let chart = self.fill_chart(&word);
+        self.chart_prefix(word, chart)
+    }
+}
+
+impl<T: Term> CykParser<T> {
+    /// Rebuilds a parse tree of the word from its filled chart, or finds out where the word goes
+    /// wrong if it is not in the language.
+    pub(super) fn parse_chart(
+        &self,
+        word: Vec<T>,
+        chart: BitChart,
+    ) -> Result<ParseTree<T>, ParseError<T>> {
+        let n = word.len();
+        let mut parse_tree_builder = ParseTreeBuilder {
+            parser: self,
+            word,
+            chart,
+            derivations: HashMap::new(),
+        };
+        if !parse_tree_builder.derives(self.start, 0, n) {
+            return Err(ErrorFinder::new(parse_tree_builder).find());
+        }
+
+        Ok(parse_tree_builder.build(self.start, 0, n))
+    }
+
+    /// Rebuilds a parse tree of the longest prefix of the word in the language from its filled
+    /// chart, in which the cells of the spans starting at the beginning of the word tell which
+    /// prefixes are in the language.
+    pub(super) fn chart_prefix(
+        &self,
+        word: Vec<T>,
+        chart: BitChart,
+    ) -> Option<(ParseTree<T>, usize)> {
+        let mut parse_tree_builder = ParseTreeBuilder {
+            parser: self,
+            word,
+            chart,
+            derivations: HashMap::new(),
+        };
+        let end = (0..=parse_tree_builder.word.len())
+            .rev()
+            .find(|&j| parse_tree_builder.derives(self.start, 0, j))?;
+        Some((parse_tree_builder.build(self.start, 0, end), end))
+    }
+
+    /// Builds the forest of the parse trees of the word in the original grammar from its filled
+    /// chart, or returns `None` if the word is not in the language.
+    pub(super) fn chart_forest(&self, word: &[T], chart: &BitChart) -> Option<ParseForest<'_, T>> {
+        let binarized = &self.binarized;
+        SpanForestBuilder::new(&self.rules, binarized.n_vars, word, |var, i, j| {
+            self.derives(chart, var, i, j)
+        })
+        .build(&binarized.cfg.var_names[..binarized.n_vars], self.start)
+    }
+
+    /// Returns whether the variable of the binarized grammar derives the span `i..j` of the word
+    /// of the chart.
+    fn derives(&self, chart: &BitChart, var: Var, i: usize, j: usize) -> bool {
+        if i == j {
+            self.nullable[var.0]
+        } else {
+            contains_bit(chart.cell(i, j - 1), self.vars_map[var.0].0)
+        }
+    }
+}
+
+/// For every variable, lists the rules which derive the same span as the variable when applied,
+/// i.e., the unit rules with the variable as body and the binary rules in which the variable is
+/// next to a nullable variable.
+fn get_unit_like_rules<T: Term>(cfg: &Cfg<T>, nullable: &[bool]) -> Vec<Vec<usize>> {
+    let mut unit_like_rules = vec![vec![]; cfg.n_vars()];
 
-        for (i, term) in input.into_iter().enumerate() {
-            let Some(vars) = self.terms_to_vars.get(&term) else {
+    for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+        match rule.body.as_slice() {
+            [Symbol::Var(var)] => {
+                unit_like_rules[var.0].push(rule_idx);
+            }
+            [Symbol::Var(first), Symbol::Var(second)] => {
+                if nullable[second.0] {
+                    unit_like_rules[first.0].push(rule_idx);
+                }
+                if nullable[first.0] && first != second {
+                    unit_like_rules[second.0].push(rule_idx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unit_like_rules
+}
+
+/// Rebuilds a parse tree in the original grammar from a filled chart.
+///
+/// The chart only records which variables of the CNF derive which spans, so the rules of the
+/// binarized grammar are matched against it top-down. The `<GEN_TERM>` and `<GEN_AUX>` variables
+/// of the binarized grammar are then inlined into their parents.
+struct ParseTreeBuilder<'a, T: Term> {
+    parser: &'a CykParser<T>,
+    word: Vec<T>,
+    chart: BitChart,
+    derivations: HashMap<(usize, usize), Vec<Option<Derivation>>>,
+}
+
+/// A rule to apply to a variable deriving a span, along with the position at which the span is
+/// split between the two symbols of the rule's body, if there are two.
+#[derive(Debug, Clone, Copy)]
+struct Derivation {
+    rule_idx: usize,
+    split: usize,
+}
+
+impl<T: Term> ParseTreeBuilder<'_, T> {
+    fn derives(&self, var: Var, i: usize, j: usize) -> bool {
+        self.parser.derives(&self.chart, var, i, j)
+    }
+
+    fn build(&mut self, var: Var, i: usize, j: usize) -> ParseTree<T> {
+        let Derivation { rule_idx, split } = self.derivation(var, i, j);
+        let mut children = Vec::new();
+        self.build_children(rule_idx, i, split, j, &mut children);
+        ParseTree {
+            root_var: var,
+            root_var_name: self.parser.binarized.cfg.var_names[var.0].to_owned(),
+            rule_idx,
+            children,
+        }
+    }
+
+    fn build_children(
+        &mut self,
+        rule_idx: usize,
+        i: usize,
+        split: usize,
+        j: usize,
+        children: &mut Vec<ParsedSymbol<T>>,
+    ) {
+        let parser = self.parser;
+        match parser.binarized.cfg.rules[rule_idx].body.as_slice() {
+            [] => {}
+            [symbol] => {
+                self.build_symbol(symbol, i, j, children);
+            }
+            [first, second] => {
+                self.build_symbol(first, i, split, children);
+                self.build_symbol(second, split, j, children);
+            }
+            _ => panic!("Expected body to have length at most 2"),
+        }
+    }
+
+    fn build_symbol(
+        &mut self,
+        symbol: &Symbol<T>,
+        i: usize,
+        j: usize,
+        children: &mut Vec<ParsedSymbol<T>>,
+    ) {
+        match symbol {
+            Symbol::Term(term) => {
+                children.push(ParsedSymbol::Term(term.clone()));
+            }
+            Symbol::Var(var) if var.0 < self.parser.binarized.n_vars => {
+                children.push(ParsedSymbol::Var(Box::new(self.build(*var, i, j))));
+            }
+            Symbol::Var(var) => {
+                let Derivation { rule_idx, split } = self.derivation(*var, i, j);
+                self.build_children(rule_idx, i, split, j, children);
+            }
+        }
+    }
+
+    fn derivation(&mut self, var: Var, i: usize, j: usize) -> Derivation {
+        if i == j {
+            let rule_idx = self.parser.nullable_rules[var.0].expect("Expected a nullable variable");
+            return Derivation { rule_idx, split: i };
+        }
+        if !self.derivations.contains_key(&(i, j)) {
+            let derivations = self.find_derivations(i, j);
+            self.derivations.insert((i, j), derivations);
+        }
+        self.derivations[&(i, j)][var.0].expect("Expected variable to derive the span")
+    }
+
+    /// Finds a derivation for every variable deriving the nonempty span `i..j`.
+    ///
+    /// Variables deriving the span through a terminal rule or a binary rule splitting the span into
+    /// two nonempty parts are found first. The remaining variables are then found by a BFS along
+    /// the unit-like rules, so that repeatedly following the derivations never loops.
+    fn find_derivations(&self, i: usize, j: usize) -> Vec<Option<Derivation>> {
+        let cfg = &self.parser.binarized.cfg;
+        let mut derivations = vec![None; cfg.n_vars()];
+        let mut queue = VecDeque::<Var>::new();
+
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            if derivations[rule.head.0].is_some() {
                 continue;
+            }
+            let split = match rule.body.as_slice() {
+                [Symbol::Term(term)] => (j == i + 1 && self.word[i] == *term).then_some(j),
+                [Symbol::Var(first), Symbol::Var(second)] => {
+                    let (first, second) = (*first, *second);
+                    (i + 1..j).find(|&k| self.derives(first, i, k) && self.derives(second, k, j))
+                }
+                _ => None,
             };
-            for var in vars {
-                dp[(i, i, var.0)] = true;
+            if let Some(split) = split {
+                derivations[rule.head.0] = Some(Derivation { rule_idx, split });
+                queue.push_back(rule.head);
             }
         }
 
-        for len in 2..=n {
-            for i in 0..=n - len {
-                let j = i + len - 1;
-                for rule in self.binary_rules.iter().cloned() {
-                    if let CnfRule::Binary(first, second, third) = rule {
-                        for k in i..j {
-                            dp[(i, j, first.0)] |= dp[(i, k, second.0)] && dp[(k + 1, j, third.0)];
+        while let Some(var) = queue.pop_front() {
+            for rule_idx in self.parser.unit_like_rules[var.0].iter().cloned() {
+                let rule = &cfg.rules[rule_idx];
+                if derivations[rule.head.0].is_some() {
+                    continue;
+                }
+                let split = match rule.body.as_slice() {
+                    [Symbol::Var(first), Symbol::Var(second)]
+                        if *first != var || !self.parser.nullable[second.0] =>
+                    {
+                        i
+                    }
+                    _ => j,
+                };
+                derivations[rule.head.0] = Some(Derivation { rule_idx, split });
+                queue.push_back(rule.head);
+            }
+        }
+
+        derivations
+    }
+}
+
+/// Finds out where a word goes wrong from its filled chart, in the binarized grammar.
+///
+/// A variable is *predicted* at a position if the start variable derives the word up to there
+/// followed by the variable and then variables deriving some word. Going left to right, the
+/// variables predicted at a position follow from the ones predicted before it and the chart: a
+/// rule `A => BC` predicts `B` where `A` is predicted if `C` derives anything, and predicts `C`
+/// where `B` derives the word from the position of `A` on. The word up to `j` is the beginning of
+/// a word in the language exactly when a variable predicted at `j - 1` has the terminal there as a
+/// rule, so the pass stops where no variable does.
+struct ErrorFinder<'a, T: Term> {
+    builder: ParseTreeBuilder<'a, T>,
+    /// For every variable of the binarized grammar, whether it derives any word at all.
+    productive: Vec<bool>,
+    /// For every variable of the binarized grammar, the indices of its rules.
+    rules_by_var: Vec<Vec<usize>>,
+}
+
+impl<'a, T: Term> ErrorFinder<'a, T> {
+    fn new(builder: ParseTreeBuilder<'a, T>) -> Self {
+        let cfg = &builder.parser.binarized.cfg;
+        let productive = cfg.productive_vars();
+        let mut rules_by_var = vec![vec![]; cfg.n_vars()];
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            rules_by_var[rule.head.0].push(rule_idx);
+        }
+        Self {
+            builder,
+            productive,
+            rules_by_var,
+        }
+    }
+
+    /// Finds the variables predicted at every position up to where the word goes wrong, which is
+    /// the last one.
+    fn predicted(&self) -> Vec<Vec<bool>> {
+        let parser = self.builder.parser;
+        let cfg = &parser.binarized.cfg;
+        let word = &self.builder.word;
+
+        let mut predicted: Vec<Vec<bool>> = Vec::new();
+        for j in 0..=word.len() {
+            let mut is_predicted = vec![false; cfg.n_vars()];
+            let mut stack = Vec::new();
+            if j == 0 {
+                stack.push(parser.start);
+            } else {
+                let is_prefix = predicted[j - 1]
+                    .iter()
+                    .enumerate()
+                    .any(|(var, &is_predicted)| {
+                        is_predicted
+                            && self.rules_by_var[var].iter().any(|&rule_idx| {
+                                matches!(
+                                    cfg.rules[rule_idx].body.as_slice(),
+                                    [Symbol::Term(term)] if *term == word[j - 1]
+                                )
+                            })
+                    });
+                if !is_prefix {
+                    break;
+                }
+                for (i, predicted) in predicted.iter().enumerate() {
+                    for var in (0..cfg.n_vars()).filter(|&var| predicted[var]) {
+                        for &rule_idx in self.rules_by_var[var].iter() {
+                            if let [Symbol::Var(first), Symbol::Var(second)] =
+                                cfg.rules[rule_idx].body.as_slice()
+                            {
+                                if self.builder.derives(*first, i, j) {
+                                    stack.push(*second);
+                                }
+                            }
                         }
                     }
                 }
             }
+
+            // Rules whose first symbol derives the empty word or which are unit rules predict
+            // variables at the same position.
+            while let Some(var) = stack.pop() {
+                if std::mem::replace(&mut is_predicted[var.0], true) {
+                    continue;
+                }
+                for &rule_idx in self.rules_by_var[var.0].iter() {
+                    match cfg.rules[rule_idx].body.as_slice() {
+                        [Symbol::Var(var)] => stack.push(*var),
+                        [Symbol::Var(first), Symbol::Var(second)] => {
+                            if self.productive[second.0] {
+                                stack.push(*first);
+                            }
+                            if self.builder.derives(*first, j, j) {
+                                stack.push(*second);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            predicted.push(is_predicted);
         }
+        predicted
+    }
 
-        dp[(0, n - 1, self.start.0)]
+    fn find(self) -> ParseError<T> {
+        let parser = self.builder.parser;
+        let cfg = &parser.binarized.cfg;
+
+        let predicted = self.predicted();
+        let position = predicted.len() - 1;
+
+        // The terminals expected are the ones which the variables predicted there have as rules.
+        let mut expected = BTreeSet::new();
+        for (var, _) in predicted[position].iter().enumerate().filter(|(_, &p)| p) {
+            for &rule_idx in self.rules_by_var[var].iter() {
+                if let [Symbol::Term(term)] = cfg.rules[rule_idx].body.as_slice() {
+                    expected.insert(Some(term.clone()));
+                }
+            }
+        }
+        if self.builder.derives(parser.start, 0, position) {
+            expected.insert(None);
+        }
+
+        ParseError::new(
+            &self.builder.word,
+            position,
+            expected,
+            self.vars(position),
+            &cfg.var_names,
+        )
     }
 
-    fn parse(&self, _word: Vec<T>) -> Option<ParseTree<T>> {
-        todo!()
+    /// Finds the variables having a rule whose body was recognized up to `j` but not to its end,
+    /// going top-down through the variables predicted at every position up to `j`. Along with
+    /// every variable and its position, the start of the span of the original rule it belongs to
+    /// is kept, which differs for the `<GEN_AUX>` variables splitting long rules.
+    fn vars(&self, j: usize) -> Vec<(usize, Var)> {
+        let parser = self.builder.parser;
+        let cfg = &parser.binarized.cfg;
+        if j == 0 {
+            return Vec::new();
+        }
+
+        let mut rule_heads: Vec<Var> = (0..cfg.n_vars()).map(Var).collect();
+        for rule in cfg.rules.iter() {
+            if let [_, Symbol::Var(second)] = rule.body.as_slice() {
+                if second.0 >= parser.binarized.n_vars {
+                    rule_heads[second.0] = rule.head;
+                }
+            }
+        }
+        let original_head = |mut var: Var| {
+            while var.0 >= parser.binarized.n_vars {
+                var = rule_heads[var.0];
+            }
+            var
+        };
+
+        let start = (parser.start, 0, 0);
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut vars = Vec::new();
+        while let Some((var, i, l)) = queue.pop_front() {
+            for &rule_idx in self.rules_by_var[var.0].iter() {
+                let mut next = Vec::new();
+                match cfg.rules[rule_idx].body.as_slice() {
+                    [Symbol::Var(var)] => next.push((*var, i, i)),
+                    [Symbol::Var(first), Symbol::Var(second)] if self.productive[second.0] => {
+                        next.push((*first, i, i));
+                        if l < j && self.builder.derives(*first, i, j) {
+                            vars.push((l, original_head(var)));
+                        }
+                        for k in i..=j {
+                            if self.builder.derives(*first, i, k) {
+                                let l = match second.0 >= parser.binarized.n_vars {
+                                    true => l,
+                                    false => k,
+                                };
+                                next.push((*second, k, l));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                for next in next {
+                    if seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        vars
     }
 }
 
+/// The sets of variables deriving every span `i..=j` of a word, as bitsets of `n_words` words each.
 #[derive(Debug)]
-struct Flat3dVec<T> {
-    n: usize,
-    k: usize,
-    vec: Vec<T>,
+pub(super) struct BitChart {
+    n_words: usize,
+    bits: Vec<u64>,
 }
 
-impl<T: Clone> Flat3dVec<T> {
-    fn filled_with(m: usize, n: usize, k: usize, val: T) -> Self {
+impl BitChart {
+    pub(super) fn new(n: usize, n_words: usize) -> Self {
         Self {
-            n,
-            k,
-            vec: vec![val; m * n * k],
+            n_words,
+            bits: vec![0; n * (n + 1) / 2 * n_words],
         }
     }
-}
 
-impl<T> Index<(usize, usize, usize)> for Flat3dVec<T> {
-    type Output = T;
+    fn offset(&self, i: usize, j: usize) -> usize {
+        (j * (j + 1) / 2 + i) * self.n_words
+    }
 
-    fn index(&self, index: (usize, usize, usize)) -> &Self::Output {
-        &self.vec[index.0 * self.n * self.k + index.1 * self.k + index.2]
+    fn cell(&self, i: usize, j: usize) -> &[u64] {
+        let offset = self.offset(i, j);
+        &self.bits[offset..offset + self.n_words]
     }
-}
 
-impl<T> IndexMut<(usize, usize, usize)> for Flat3dVec<T> {
-    fn index_mut(&mut self, index: (usize, usize, usize)) -> &mut Self::Output {
-        &mut self.vec[index.0 * self.n * self.k + index.1 * self.k + index.2]
+    pub(super) fn cell_mut(&mut self, i: usize, j: usize) -> &mut [u64] {
+        let offset = self.offset(i, j);
+        &mut self.bits[offset..offset + self.n_words]
     }
 }
+
+pub(super) fn insert_bit(bits: &mut [u64], i: usize) {
+    bits[i / 64] |= 1 << (i % 64);
+}
+
+pub(super) fn contains_bit(bits: &[u64], i: usize) -> bool {
+    (bits[i / 64] >> (i % 64)) & 1 == 1
+}
+
+pub(super) fn ones(bits: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    bits.iter().enumerate().flat_map(|(w, bits)| {
+        let mut bits = *bits;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let i = w * 64 + bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Some(i)
+        })
+    })
+}
@@ -1,19 +1,36 @@
-use std::rc::Rc;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ops::Range,
+    rc::Rc,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{Cfg, Rule, Symbol, Term, Var};
 
-use super::{ParseTree, ParsedSymbol, Parser};
+use super::{
+    correction::Corrector, forest::NodeKey, Correction, EditCosts, ForestNode, ForestNodeLabel,
+    PackedNode, ParseError, ParseForest, ParseTree, ParsedSymbol, Parser, Recovery,
+};
 
 pub struct EarleyParser<T: Term> {
     start_var: Var,
     var_names: Vec<Rc<str>>,
     rules: Vec<Rule<T>>,
     rules_by_var: Vec<Vec<usize>>,
+    nullable_rules: Vec<Option<usize>>,
+    /// For every variable, the variables whose rules may be needed to derive it, i.e., the
+    /// variables reachable from it through the first non-nullable symbol of rule bodies.
+    predictions: Vec<Vec<Var>>,
 }
 
 impl<T: Term> EarleyParser<T> {
     pub fn of(cfg: Cfg<T>) -> Self {
-        let mut rules_by_var = vec![vec![]; cfg.n_vars()];
+        let nullable_rules = cfg.nullable_rules();
+        // Rules with variables deriving no word are never predicted, so that every state of the
+        // chart can still be completed, and the input so far is the beginning of a word in the
+        // language as long as the chart has states for it.
+        let rules_by_var = cfg.productive_rules_by_var();
 
         let Cfg {
             start_var,
@@ -21,107 +38,518 @@ impl<T: Term> EarleyParser<T> {
             rules,
         } = cfg;
 
-        for (i, rule) in rules.iter().enumerate() {
-            rules_by_var[rule.head.0].push(i);
-        }
+        let predictions = get_predictions(&rules, &rules_by_var, &nullable_rules);
 
         Self {
             start_var,
             var_names,
             rules,
             rules_by_var,
+            nullable_rules,
+            predictions,
         }
     }
 
-    fn attempt_parse(&self, word: &Vec<T>) -> (Vec<Vec<State>>, Vec<Vec<Parent>>) {
+    fn attempt_parse(&self, word: &[T]) -> Chart {
         let n = word.len();
 
-        let mut states: Vec<Vec<State>> = vec![Vec::new(); n + 1];
-        let mut parents: Vec<Vec<Parent>> = vec![Vec::new(); n + 1];
+        let mut chart = Chart::new(n, self.rules_by_var.len());
+        self.predict(&mut chart, 0, self.start_var);
 
-        for rule_idx in self.rules_by_var[self.start_var.0].iter().cloned() {
-            states[0].push(State {
-                l: 0,
-                rule_idx,
-                parsed: 0,
-            });
-            parents[0].push(Parent::None);
+        for r in 0..=n {
+            self.fill_column(&mut chart, word, r);
         }
 
-        for r in 0..=n {
-            let mut i = 0;
-            while i < states[r].len() {
-                let State {
-                    l,
-                    rule_idx,
-                    parsed,
-                } = states[r][i];
-
-                let head = self.rules[rule_idx].head;
-                let body = &self.rules[rule_idx].body;
-
-                if parsed == body.len() {
-                    let mut j = 0;
-                    while j < states[l].len() {
-                        let state = states[l][j];
-                        let body = &self.rules[state.rule_idx].body;
-                        if state.parsed == body.len() {
-                            j += 1;
-                            continue;
-                        }
-                        let Symbol::Var(var) = &body[state.parsed] else {
-                            j += 1;
-                            continue;
-                        };
-                        if *var == head {
-                            let state = State {
-                                parsed: state.parsed + 1,
-                                ..state
-                            };
-                            if !states[r].contains(&state) {
-                                states[r].push(state);
-                                parents[r].push(Parent::Var(i, j));
-                            }
-                        }
-                        j += 1;
-                    }
+        chart
+    }
+
+    /// Processes the states of a column, adding those they lead to in it and in the next column.
+    fn fill_column(&self, chart: &mut Chart, word: &[T], r: usize) {
+        let mut i = 0;
+        while i < chart.states[r].len() {
+            let State {
+                l,
+                rule_idx,
+                parsed,
+            } = chart.state(r, i);
+
+            let head = self.rules[rule_idx].head;
+            let body = &self.rules[rule_idx].body;
+
+            if parsed == body.len() {
+                // Leo's optimization: when the completion is bound to cascade up a
+                // deterministic reduction path, only the topmost state of the path is added.
+                if let Some(leo_item) = chart.leo_item(l, head) {
+                    let state = leo_item.top;
+                    self.add_state(chart, r, state, Parent::Leo(i));
                     i += 1;
                     continue;
                 }
 
-                match &body[parsed] {
-                    Symbol::Var(var) => {
-                        for rule_idx in self.rules_by_var[var.0].iter().cloned() {
-                            let state = State {
-                                l: r,
-                                rule_idx,
-                                parsed: 0,
-                            };
-                            if !states[r].contains(&state) {
-                                states[r].push(state);
-                                parents[r].push(Parent::None);
-                            }
-                        }
+                if l < r {
+                    let Some(waiting) = chart.waiting[l].get_mut(&head).map(std::mem::take) else {
+                        i += 1;
+                        continue;
+                    };
+                    for j in waiting.iter().cloned() {
+                        let state = chart.state(l, j);
+                        let state = State {
+                            parsed: state.parsed + 1,
+                            ..state
+                        };
+                        self.add_state(chart, r, state, Parent::Var(i, j));
                     }
-                    Symbol::Term(term) => {
-                        if r < n && word[r] == *term {
+                    chart.waiting[l].insert(head, waiting);
+                } else {
+                    // The states waiting on the head may grow while being iterated over.
+                    let mut k = 0;
+                    while let Some(j) = chart.waiting[l].get(&head).and_then(|w| w.get(k)) {
+                        let j = *j;
+                        let state = chart.state(l, j);
+                        let state = State {
+                            parsed: state.parsed + 1,
+                            ..state
+                        };
+                        self.add_state(chart, r, state, Parent::Var(i, j));
+                        k += 1;
+                    }
+                }
+                i += 1;
+                continue;
+            }
+
+            match &body[parsed] {
+                Symbol::Var(var) => {
+                    self.predict(chart, r, *var);
+                    // As in Aycock & Horspool, a nullable variable is skipped over right away,
+                    // since its empty completion may already have been processed in this column.
+                    if self.nullable_rules[var.0].is_some() {
+                        let state = State {
+                            l,
+                            rule_idx,
+                            parsed: parsed + 1,
+                        };
+                        self.add_state(chart, r, state, Parent::Nullable(i));
+                    }
+                }
+                Symbol::Term(term) => {
+                    if word.get(r) == Some(term) {
+                        let state = State {
+                            l,
+                            rule_idx,
+                            parsed: parsed + 1,
+                        };
+                        self.add_state(chart, r + 1, state, Parent::Term(i));
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let leo_items = self.find_leo_items(chart, r);
+        chart.leo_items.push(leo_items);
+        chart.present[r % 2].clear();
+    }
+
+    fn add_state(&self, chart: &mut Chart, r: usize, state: State, parent: Parent) {
+        if !chart.present[r % 2].insert(state) {
+            return;
+        }
+        let i = chart.states[r].len();
+        chart.states[r].push(state);
+        chart.parents[r].push(parent);
+        if let Some(Symbol::Var(var)) = self.rules[state.rule_idx].body.get(state.parsed) {
+            chart.waiting[r].entry(*var).or_default().push(i);
+        }
+    }
+
+    /// Adds the initial states of all rules that may be needed to derive the variable, unless the
+    /// variable was already predicted in this column.
+    fn predict(&self, chart: &mut Chart, r: usize, var: Var) {
+        if chart.last_predicted[var.0] == Some(r) {
+            return;
+        }
+        for predicted_var in self.predictions[var.0].iter().cloned() {
+            if chart.last_predicted[predicted_var.0] == Some(r) {
+                continue;
+            }
+            chart.last_predicted[predicted_var.0] = Some(r);
+            for rule_idx in self.rules_by_var[predicted_var.0].iter().cloned() {
+                let state = State {
+                    l: r,
+                    rule_idx,
+                    parsed: 0,
+                };
+                self.add_state(chart, r, state, Parent::None);
+            }
+        }
+    }
+
+    /// Finds the state of a column in which a rule of the start variable spans the whole word.
+    fn final_state_pos(&self, chart: &Chart, r: usize) -> Option<usize> {
+        chart.column(r).position(|state| {
+            self.rules[state.rule_idx].head == self.start_var
+                && state.l == 0
+                && state.parsed == self.rules[state.rule_idx].body.len()
+        })
+    }
+
+    /// Adds the states of a column advanced past the given terminal to the next column.
+    fn scan(&self, chart: &mut Chart, r: usize, term: &T) {
+        for i in 0..chart.states[r].len() {
+            let state = chart.state(r, i);
+            let next_symbol = self.rules[state.rule_idx].body.get(state.parsed);
+            if matches!(next_symbol, Some(Symbol::Term(next_term)) if next_term == term) {
+                let state = State {
+                    parsed: state.parsed + 1,
+                    ..state
+                };
+                self.add_state(chart, r + 1, state, Parent::Term(i));
+            }
+        }
+    }
+
+    /// Starts a session in which the word is given one terminal at a time, and which can tell after
+    /// every terminal what may come next, without parsing the word all over again.
+    pub fn start_session(&self) -> EarleySession<'_, T> {
+        let mut chart = Chart::new(0, self.rules_by_var.len());
+        self.predict(&mut chart, 0, self.start_var);
+        self.fill_column(&mut chart, &[], 0);
+        EarleySession {
+            parser: self,
+            word: Vec::new(),
+            chart,
+            subtrees: vec![FxHashMap::default()],
+            empty_subtrees: FxHashMap::default(),
+        }
+    }
+
+    /// Finds the parts of the text in the language, as the ranges of positions they span, in order
+    /// of where they start and then of where they end. The parts may overlap, and include empty
+    /// ones if the empty word is in the language.
+    ///
+    /// All of them are found with a single chart, in which the rules of the start variable are
+    /// predicted in every column rather than only the first one.
+    pub fn find_all(&self, text: &[T]) -> Vec<Range<usize>> {
+        let n = text.len();
+
+        let mut chart = Chart::new(n, self.rules_by_var.len());
+        chart.is_searching = true;
+        for r in 0..=n {
+            self.predict(&mut chart, r, self.start_var);
+            self.fill_column(&mut chart, text, r);
+        }
+
+        let mut ranges: Vec<_> = chart
+            .states
+            .iter()
+            .enumerate()
+            .flat_map(|(r, states)| {
+                states
+                    .iter()
+                    .filter(|state| {
+                        let rule = &self.rules[state.rule_idx];
+                        rule.head == self.start_var && state.parsed == rule.body.len()
+                    })
+                    .map(move |state| state.l..r)
+            })
+            .collect();
+        ranges.sort_by_key(|range| (range.start, range.end));
+        ranges.dedup();
+        ranges
+    }
+
+    /// Finds the parts of the text in the language which do not overlap, as with regular
+    /// expressions: the leftmost part is chosen, the longest one if several start there, and the
+    /// search goes on from where it ends, or from the next position if it is empty.
+    pub fn find_leftmost_longest(&self, text: &[T]) -> Vec<Range<usize>> {
+        let mut longest: Vec<Option<usize>> = vec![None; text.len() + 1];
+        for range in self.find_all(text) {
+            longest[range.start] = Some(range.end);
+        }
+
+        let mut ranges = Vec::new();
+        let mut l = 0;
+        while l < longest.len() {
+            match longest[l] {
+                Some(r) => {
+                    ranges.push(l..r);
+                    l = r.max(l + 1);
+                }
+                None => l += 1,
+            }
+        }
+        ranges
+    }
+
+    /// Finds the Leo items of a column once all of its states are known.
+    ///
+    /// There is a Leo item for a variable if exactly one state of the column expects the variable
+    /// next, and the variable is the last symbol of that state's rule. Completing the variable from
+    /// this column then advances only that state, which in turn completes its rule's head, and so
+    /// on, until the top of this deterministic reduction path is reached.
+    fn find_leo_items(&self, chart: &Chart, r: usize) -> FxHashMap<Var, LeoItem> {
+        chart.waiting[r]
+            .iter()
+            .filter_map(|(var, waiting)| {
+                let [penult] = waiting.as_slice() else {
+                    return None;
+                };
+                let state = chart.state(r, *penult);
+                let rule = &self.rules[state.rule_idx];
+                if state.parsed + 1 != rule.body.len() {
+                    return None;
+                }
+                // The path must not skip over a completed start variable at the start of the word,
+                // as that is what determines whether the word is accepted, or anywhere in the word
+                // when searching it.
+                let is_top = state.l == r
+                    || (rule.head == self.start_var && (state.l == 0 || chart.is_searching));
+                let top = match is_top {
+                    true => None,
+                    false => chart.leo_item(state.l, rule.head),
+                };
+                let top = top.map_or(
+                    State {
+                        parsed: state.parsed + 1,
+                        ..state
+                    },
+                    |leo_item| leo_item.top,
+                );
+                Some((
+                    *var,
+                    LeoItem {
+                        penult: *penult,
+                        top,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the states skipped over when the state at `states[r][i]` was added through a Leo
+    /// item, bottom-up, as the columns and indices of their predecessors.
+    fn leo_path(&self, chart: &Chart, r: usize, i: usize) -> Vec<(usize, usize)> {
+        let Parent::Leo(j) = chart.parents[r][i] else {
+            panic!("Expected state to be added through a Leo item");
+        };
+        self.leo_path_from(chart, chart.state(r, j))
+    }
+
+    /// Returns the states skipped over when completing the given state through the Leo item of its
+    /// head, if there is one, bottom-up, as the columns and indices of their predecessors.
+    fn leo_path_from(&self, chart: &Chart, mut state: State) -> Vec<(usize, usize)> {
+        let head = self.rules[state.rule_idx].head;
+        let Some(top) = chart.leo_item(state.l, head).map(|leo_item| leo_item.top) else {
+            return Vec::new();
+        };
+
+        let mut path = Vec::new();
+        while state != top {
+            let head = self.rules[state.rule_idx].head;
+            let leo_item = chart
+                .leo_item(state.l, head)
+                .expect("Expected a Leo item all along the path");
+            path.push((state.l, leo_item.penult));
+            let penult = chart.state(state.l, leo_item.penult);
+            state = State {
+                parsed: penult.parsed + 1,
+                ..penult
+            };
+        }
+        path
+    }
+
+    /// Builds the parse tree of a nullable variable deriving the empty word.
+    fn build_empty(&self, var: Var) -> ParseTree<T> {
+        let rule_idx = self.nullable_rules[var.0].expect("Expected a nullable variable");
+        let children = self.rules[rule_idx]
+            .body
+            .iter()
+            .map(|symbol| {
+                let Symbol::Var(var) = symbol else {
+                    panic!("Expected body of a nullable rule to only have variables as symbols");
+                };
+                ParsedSymbol::Var(Box::new(self.build_empty(*var)))
+            })
+            .collect();
+        ParseTree {
+            root_var: var,
+            root_var_name: self.var_names[var.0].to_owned(),
+            rule_idx,
+            children,
+        }
+    }
+
+    /// Finds out where the word goes wrong from the last column of the chart with states in it.
+    /// Every state of the column expecting a terminal next gives an expected terminal, and every
+    /// state which started before the column and has not reached the end of its rule gives a
+    /// variable being recognized.
+    fn parse_error(&self, word: &[T], chart: &Chart) -> ParseError<T> {
+        let position = (0..=word.len())
+            .rev()
+            .find(|&r| !chart.states[r].is_empty())
+            .unwrap_or(0);
+        self.parse_error_at(word, chart, position)
+    }
+
+    fn parse_error_at(&self, word: &[T], chart: &Chart, position: usize) -> ParseError<T> {
+        let mut expected = BTreeSet::new();
+        let mut vars = Vec::new();
+        for state in chart.column(position) {
+            let rule = &self.rules[state.rule_idx];
+            match rule.body.get(state.parsed) {
+                Some(Symbol::Term(term)) => {
+                    expected.insert(Some(term.clone()));
+                }
+                None if rule.head == self.start_var && state.l == 0 => {
+                    expected.insert(None);
+                }
+                _ => {}
+            }
+            if state.l < position && state.parsed < rule.body.len() {
+                vars.push((state.l, rule.head));
+            }
+        }
+        ParseError::new(word, position, expected, vars, &self.var_names)
+    }
+
+    /// Parses the word, recovering from errors instead of giving up at the first one, so that a
+    /// parse tree is returned even if the word is not in the language. Returns `None` only if the
+    /// language is empty.
+    ///
+    /// When the chart runs out of states, the parser skips ahead to the next synchronizing
+    /// terminal for which some state further back expects it later in its rule, and resumes right
+    /// after it. The part of the word from the state on becomes a [`ParsedSymbol::Error`] standing
+    /// in for the symbols of the rule up to the synchronizing terminal. The states furthest along
+    /// the word are chosen, so that as little as possible is skipped. When no synchronizing
+    /// terminal comes to the rescue, the rest of the word is skipped as the rest of a rule of the
+    /// start variable.
+    pub fn parse_with_recovery(&self, word: Vec<T>, sync_terms: &[T]) -> Option<Recovery<T>> {
+        if self.rules_by_var[self.start_var.0].is_empty() {
+            return None;
+        }
+        let n = word.len();
+
+        let mut chart = Chart::new(n, self.rules_by_var.len());
+        self.predict(&mut chart, 0, self.start_var);
+
+        let mut diagnostics = Vec::new();
+        // The column at which to resume after an error, along with the states to resume with.
+        let mut resumption = None;
+        let mut is_skipping = false;
+        for r in 0..=n {
+            // The first column always has the rules of the start variable in it.
+            if chart.states[r].is_empty() && !is_skipping {
+                diagnostics.push(self.parse_error_at(&word, &chart, r - 1));
+                resumption = self.resynchronize(&chart, &word, r - 1, sync_terms);
+                is_skipping = true;
+            }
+            if let Some((_, states)) = resumption.take_if(|(resume_at, _)| *resume_at == r) {
+                for (state, parent) in states {
+                    self.add_state(&mut chart, r, state, parent);
+                }
+                is_skipping = false;
+            }
+            self.fill_column(&mut chart, &word, r);
+        }
+
+        let final_state_pos = match self.final_state_pos(&chart, n) {
+            Some(final_state_pos) => final_state_pos,
+            None => {
+                if !is_skipping {
+                    diagnostics.push(self.parse_error_at(&word, &chart, n));
+                }
+                let (c, k) = (0..=n)
+                    .rev()
+                    .find_map(|c| {
+                        let k = chart.states[c].iter().position(|state| {
+                            self.rules[state.rule_idx].head == self.start_var && state.l == 0
+                        })?;
+                        Some((c, k))
+                    })
+                    .expect("Expected the rules of the start variable in the first column");
+                let state = State {
+                    parsed: self.rules[chart.states[c][k].rule_idx].body.len(),
+                    ..chart.states[c][k]
+                };
+                chart.states[n].push(state);
+                chart.parents[n].push(Parent::Truncated(c, k));
+                chart.states[n].len() - 1
+            }
+        };
+
+        Some(Recovery {
+            parse_tree: ParseTreeBuilder {
+                parser: self,
+                word: &word,
+                chart: &chart,
+            }
+            .build(n, final_state_pos),
+            diagnostics,
+        })
+    }
+
+    /// Finds the first synchronizing terminal from the position of an error on for which some
+    /// state expects it later in its rule, and returns the column right after it along with the
+    /// states advanced past it from the latest column with such states. States which have parsed
+    /// part of their rule are preferred over predicted ones, which would stand for rules the word
+    /// never got to.
+    fn resynchronize(
+        &self,
+        chart: &Chart,
+        word: &[T],
+        position: usize,
+        sync_terms: &[T],
+    ) -> Option<(usize, Vec<(State, Parent)>)> {
+        for (q, sync_term) in word.iter().enumerate().skip(position) {
+            if !sync_terms.contains(sync_term) {
+                continue;
+            }
+            for is_started in [true, false] {
+                for c in (0..=position).rev() {
+                    let states: Vec<_> = chart.states[c]
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, state)| state.parsed > 0 || !is_started)
+                        .filter_map(|(k, state)| {
+                            let body = &self.rules[state.rule_idx].body;
+                            let j = (state.parsed..body.len()).find(
+                                |&j| matches!(&body[j], Symbol::Term(term) if term == sync_term),
+                            )?;
                             let state = State {
-                                l,
-                                rule_idx,
-                                parsed: parsed + 1,
+                                parsed: j + 1,
+                                ..*state
                             };
-                            if !states[r + 1].contains(&state) {
-                                states[r + 1].push(state);
-                                parents[r + 1].push(Parent::Term(i));
-                            }
-                        }
+                            Some((state, Parent::Recovered(c, k)))
+                        })
+                        .collect();
+                    if !states.is_empty() {
+                        return Some((q + 1, states));
                     }
                 }
-                i += 1;
             }
         }
+        None
+    }
 
-        (states, parents)
+    /// Returns the parse tree of the word in the language which the word can be turned into by
+    /// the cheapest edits of single terminals, along with the edits, or `None` if the language is
+    /// empty. Words in the language are returned as they are, with no edits.
+    pub fn parse_with_corrections(
+        &self,
+        word: Vec<T>,
+        edit_costs: &impl EditCosts<T>,
+    ) -> Option<Correction<T>> {
+        Corrector::new(
+            self.start_var,
+            &self.var_names,
+            &self.rules,
+            &self.rules_by_var,
+            edit_costs,
+            word,
+        )
+        .correct()
     }
 }
 
@@ -130,51 +558,420 @@ impl<T: Term> Parser<Vec<T>> for EarleyParser<T> {
 
     fn test(&self, word: Vec<T>) -> bool {
         let n = word.len();
-        let (states, _) = self.attempt_parse(&word);
+        let chart = self.attempt_parse(&word);
 
-        states[n].iter().any(|state| {
-            self.rules[state.rule_idx].head == self.start_var
-                && state.l == 0
-                && state.parsed == self.rules[state.rule_idx].body.len()
-        })
+        self.final_state_pos(&chart, n).is_some()
     }
 
-    fn parse(&self, word: Vec<T>) -> Option<ParseTree<T>> {
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
         let n = word.len();
-        let (states, parents) = self.attempt_parse(&word);
+        let chart = self.attempt_parse(&word);
 
-        let Some(final_state_pos) = states[n].iter().position(|state| {
-            self.rules[state.rule_idx].head == self.start_var
-                && state.l == 0
-                && state.parsed == self.rules[state.rule_idx].body.len()
-        }) else {
+        let Some(final_state_pos) = self.final_state_pos(&chart, n) else {
+            return Err(self.parse_error(&word, &chart));
+        };
+
+        Ok(ParseTreeBuilder {
+            parser: self,
+            word: &word,
+            chart: &chart,
+        }
+        .build(n, final_state_pos))
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let n = word.len();
+        let chart = self.attempt_parse(&word);
+
+        let mut parse_forest_builder = ParseForestBuilder::new(self, &word, &chart);
+        if !parse_forest_builder.completed[n]
+            .get(&self.start_var)
+            .is_some_and(|starts| starts.contains(&0))
+        {
             return None;
+        }
+        let root = parse_forest_builder.var_node(self.start_var, 0, n);
+
+        Some(ParseForest {
+            var_names: &self.var_names,
+            nodes: parse_forest_builder.nodes,
+            root,
+        })
+    }
+
+    /// Since every column of the chart tells whether the word up to it is in the language, the
+    /// chart is only filled once.
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let chart = self.attempt_parse(&word);
+        let (end, final_state_pos) = (0..=word.len())
+            .rev()
+            .find_map(|r| Some((r, self.final_state_pos(&chart, r)?)))?;
+
+        let parse_tree = ParseTreeBuilder {
+            parser: self,
+            word: &word,
+            chart: &chart,
+        }
+        .build(end, final_state_pos);
+        Some((parse_tree, end))
+    }
+}
+
+/// A word being given to an [`EarleyParser`] one terminal at a time. The chart is filled as far as
+/// the word goes, so that every column is only ever filled once.
+pub struct EarleySession<'a, T: Term> {
+    parser: &'a EarleyParser<T>,
+    word: Vec<T>,
+    chart: Chart,
+    /// For every column, the subtrees built so far of its complete states, by their indices.
+    subtrees: Vec<FxHashMap<usize, Rc<SharedParseTree<T>>>>,
+    /// The subtrees built so far deriving the empty word, by their root variables.
+    empty_subtrees: FxHashMap<Var, Rc<SharedParseTree<T>>>,
+}
+
+impl<T: Term> EarleySession<'_, T> {
+    /// Appends a terminal to the word.
+    pub fn push(&mut self, term: T) {
+        let r = self.word.len();
+        self.chart.add_column();
+        self.subtrees.push(FxHashMap::default());
+        self.parser.scan(&mut self.chart, r, &term);
+        self.word.push(term);
+        self.parser.fill_column(&mut self.chart, &self.word, r + 1);
+    }
+
+    pub fn word(&self) -> &[T] {
+        &self.word
+    }
+
+    /// Whether the word so far is in the language.
+    pub fn is_complete(&self) -> bool {
+        self.parser
+            .final_state_pos(&self.chart, self.word.len())
+            .is_some()
+    }
+
+    /// Whether the word so far is the beginning of some word in the language.
+    pub fn is_viable(&self) -> bool {
+        !self.chart.states[self.word.len()].is_empty()
+    }
+
+    /// The terminals which may come next, i.e., those which the word so far followed by the
+    /// terminal is the beginning of some word in the language for.
+    pub fn expected(&self) -> BTreeSet<T> {
+        self.chart.states[self.word.len()]
+            .iter()
+            .filter_map(
+                |state| match self.parser.rules[state.rule_idx].body.get(state.parsed) {
+                    Some(Symbol::Term(term)) => Some(term.clone()),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    /// Replaces the terminals of the word in the range with the given ones, as in
+    /// [`Vec::splice`].
+    ///
+    /// The columns of the chart up to the start of the range are kept as they are. The columns
+    /// after it are filled again only until one of them has the same incomplete states as the
+    /// matching column before the edit, which is the case once every rule still being parsed in it
+    /// began either before the range or at the column itself. The rest of the chart cannot differ
+    /// from before the edit but for being shifted, so the old columns are reused from there on.
+    /// Their states are not shifted right away: each column only records the shift, so moving them
+    /// takes time proportional to their number rather than to the number of their states.
+    ///
+    /// For a left-recursive list of statements, the columns are filled again up to the end of the
+    /// statement the edit is in. A right-recursive list keeps every statement open until the end
+    /// of the word, so the rest of the word is parsed again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds of the word.
+    pub fn replace(&mut self, range: Range<usize>, terms: Vec<T>) {
+        let Range { start, end } = range;
+        assert!(
+            start <= end && end <= self.word.len(),
+            "Expected the range to be within the word"
+        );
+        let mut tail = self
+            .word
+            .split_off(start)
+            .split_off(end - start)
+            .into_iter();
+        let old_chart = self.chart.split_off(start + 1);
+        let old_subtrees = self.subtrees.split_off(start + 1);
+        for term in terms {
+            self.push(term);
+        }
+
+        let mut o = end;
+        loop {
+            let c = self.word.len();
+            if o > start && c > start {
+                let k = o - start - 1;
+                if self.is_resynchronized(&old_chart, k, o, start) {
+                    self.word.extend(tail);
+                    self.chart.append_shifted(old_chart, k, o);
+                    self.subtrees.extend(old_subtrees.into_iter().skip(k + 1));
+                    return;
+                }
+            }
+            let Some(term) = tail.next() else {
+                return;
+            };
+            self.push(term);
+            o += 1;
+        }
+    }
+
+    /// Whether the `k`th column of an old chart, which was column `o` before an edit starting at
+    /// `start`, has the same incomplete states as the last column.
+    ///
+    /// The complete states of a column are never looked at from the columns after it, so they
+    /// need not match.
+    fn is_resynchronized(&self, old_chart: &Chart, k: usize, o: usize, start: usize) -> bool {
+        let c = self.word.len();
+        let is_incomplete =
+            |state: &State| state.parsed < self.parser.rules[state.rule_idx].body.len();
+        let states: FxHashSet<State> = self.chart.column(c).filter(is_incomplete).collect();
+        let mut n_old_states = 0;
+        for state in old_chart.column(k).filter(is_incomplete) {
+            let l = match state.l {
+                l if l == o => c,
+                l if l <= start => l,
+                _ => return false,
+            };
+            if !states.contains(&State { l, ..state }) {
+                return false;
+            }
+            n_old_states += 1;
+        }
+        n_old_states == states.len()
+    }
+
+    /// Returns a parse tree of the word so far, or where the word goes wrong if it is not in the
+    /// language.
+    ///
+    /// The subtrees built by earlier calls are kept along with the chart, and those whose part of
+    /// the word was not edited since are returned again as they are. Only the subtrees spanning an
+    /// edit are built anew, along with the nodes above them, so that it takes time proportional to
+    /// their number rather than to the size of the tree. As with [`replace`](Self::replace), a
+    /// right-recursive list has every statement after an edit span it, so they are all built anew.
+    pub fn parse(&mut self) -> Result<Rc<SharedParseTree<T>>, ParseError<T>> {
+        let n = self.word.len();
+        let Some(final_state_pos) = self.parser.final_state_pos(&self.chart, n) else {
+            return Err(self.parser.parse_error(&self.word, &self.chart));
         };
+        Ok(SubtreeBuilder {
+            parser: self.parser,
+            word: &self.word,
+            chart: &self.chart,
+            subtrees: &mut self.subtrees,
+            empty_subtrees: &mut self.empty_subtrees,
+        }
+        .subtree(n, final_state_pos))
+    }
+}
+
+/// A parse tree built in an [`EarleySession`], whose subtrees are shared with the parse trees
+/// built before and after it in the session wherever the part of the word they span was not
+/// edited in between, so that [`Rc::ptr_eq`] tells which of them are unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedParseTree<T: Term> {
+    root_var: Var,
+    root_var_name: Rc<str>,
+    rule_idx: usize,
+    children: Vec<SharedParsedSymbol<T>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharedParsedSymbol<T: Term> {
+    Term(T),
+    Var(Rc<SharedParseTree<T>>),
+}
+
+impl<T: Term> SharedParseTree<T> {
+    pub fn root_var(&self) -> Var {
+        self.root_var
+    }
 
-        Some(
-            ParseTreeBuilder {
-                parser: &self,
-                word,
-                states,
-                parents,
+    pub fn root_var_name(&self) -> &str {
+        &self.root_var_name
+    }
+
+    /// The index of the rule applied at the root, in the order in which the rules were added to the
+    /// grammar.
+    pub fn rule_idx(&self) -> usize {
+        self.rule_idx
+    }
+
+    pub fn children(&self) -> &[SharedParsedSymbol<T>] {
+        &self.children
+    }
+
+    /// Copies the tree into a [`ParseTree`], which takes time proportional to its size.
+    pub fn to_parse_tree(&self) -> ParseTree<T> {
+        let children = self
+            .children
+            .iter()
+            .map(|child| match child {
+                SharedParsedSymbol::Term(term) => ParsedSymbol::Term(term.clone()),
+                SharedParsedSymbol::Var(subtree) => {
+                    ParsedSymbol::Var(Box::new(subtree.to_parse_tree()))
+                }
+            })
+            .collect();
+        ParseTree {
+            root_var: self.root_var,
+            root_var_name: self.root_var_name.clone(),
+            rule_idx: self.rule_idx,
+            children,
+        }
+    }
+}
+
+/// Builds the subtrees of a session's chart as [`ParseTreeBuilder`] does parse trees, reusing
+/// those built before whose part of the word was not edited since.
+struct SubtreeBuilder<'a, T: Term> {
+    parser: &'a EarleyParser<T>,
+    word: &'a [T],
+    chart: &'a Chart,
+    subtrees: &'a mut [FxHashMap<usize, Rc<SharedParseTree<T>>>],
+    empty_subtrees: &'a mut FxHashMap<Var, Rc<SharedParseTree<T>>>,
+}
+
+impl<T: Term> SubtreeBuilder<'_, T> {
+    /// Returns the subtree of the complete state at the given column and index.
+    fn subtree(&mut self, r: usize, i: usize) -> Rc<SharedParseTree<T>> {
+        if let Some(subtree) = self.subtrees[r].get(&i) {
+            if self.chart.is_unedited(r, i) {
+                return subtree.clone();
             }
-            .build(n, final_state_pos),
-        )
+        }
+        let subtree = Rc::new(self.build(r, i));
+        self.subtrees[r].insert(i, subtree.clone());
+        subtree
+    }
+
+    /// Returns the subtree deriving the empty word from a nullable variable, which is the same
+    /// wherever the variable is skipped over.
+    fn empty_subtree(&mut self, var: Var) -> Rc<SharedParseTree<T>> {
+        if let Some(subtree) = self.empty_subtrees.get(&var) {
+            return subtree.clone();
+        }
+        let rule_idx = self.parser.nullable_rules[var.0].expect("Expected a nullable variable");
+        let children = self.parser.rules[rule_idx]
+            .body
+            .iter()
+            .map(|symbol| {
+                let Symbol::Var(var) = symbol else {
+                    panic!("Expected body of a nullable rule to only have variables as symbols");
+                };
+                SharedParsedSymbol::Var(self.empty_subtree(*var))
+            })
+            .collect();
+        let subtree = Rc::new(SharedParseTree {
+            root_var: var,
+            root_var_name: self.parser.var_names[var.0].clone(),
+            rule_idx,
+            children,
+        });
+        self.empty_subtrees.insert(var, subtree.clone());
+        subtree
+    }
+
+    fn build(&mut self, r: usize, i: usize) -> SharedParseTree<T> {
+        match self.chart.parents[r][i] {
+            Parent::None => {
+                let rule_idx = self.chart.states[r][i].rule_idx;
+                let root_var = self.parser.rules[rule_idx].head;
+                SharedParseTree {
+                    root_var,
+                    root_var_name: self.parser.var_names[root_var.0].clone(),
+                    rule_idx,
+                    children: Vec::new(),
+                }
+            }
+            Parent::Term(k) => {
+                let k = self.index_of_previous(r - 1, k, r, i);
+                let mut subtree = self.build(r - 1, k);
+                subtree
+                    .children
+                    .push(SharedParsedSymbol::Term(self.word[r - 1].clone()));
+                subtree
+            }
+            Parent::Nullable(k) => {
+                let mut subtree = self.build(r, k);
+                let state = self.chart.states[r][k];
+                let Symbol::Var(var) = self.parser.rules[state.rule_idx].body[state.parsed] else {
+                    panic!("Expected a variable to be skipped over");
+                };
+                let another_subtree = self.empty_subtree(var);
+                subtree
+                    .children
+                    .push(SharedParsedSymbol::Var(another_subtree));
+                subtree
+            }
+            Parent::Var(k, l) => {
+                let c = self.chart.state(r, k).l;
+                let l = self.index_of_previous(c, l, r, i);
+                let mut subtree = self.build(c, l);
+                let another_subtree = self.subtree(r, k);
+                subtree
+                    .children
+                    .push(SharedParsedSymbol::Var(another_subtree));
+                subtree
+            }
+            Parent::Leo(k) => {
+                let mut subtree = self.subtree(r, k);
+                for (l, penult) in self.parser.leo_path(self.chart, r, i) {
+                    let mut another_subtree = self.build(l, penult);
+                    another_subtree
+                        .children
+                        .push(SharedParsedSymbol::Var(subtree));
+                    subtree = Rc::new(another_subtree);
+                }
+                Rc::unwrap_or_clone(subtree)
+            }
+            Parent::Recovered(..) | Parent::Truncated(..) => {
+                panic!("Expected no error recovery in a session")
+            }
+        }
+    }
+
+    /// Returns the index of the state of column `c` which was advanced into the state at
+    /// `states[r][i]`, given the index it had when the parent was found.
+    ///
+    /// The columns after an edit are kept from before it, and their states may point to those of
+    /// the column at which they were resynchronized by their indices in the column it replaced.
+    fn index_of_previous(&self, c: usize, k: usize, r: usize, i: usize) -> usize {
+        let state = self.chart.state(r, i);
+        let previous_state = State {
+            parsed: state.parsed - 1,
+            ..state
+        };
+        if self.chart.states[c].len() > k && self.chart.state(c, k) == previous_state {
+            return k;
+        }
+        self.chart
+            .column(c)
+            .position(|state| state == previous_state)
+            .expect("Expected the state advanced from in its column")
     }
 }
 
 struct ParseTreeBuilder<'a, T: Term> {
     parser: &'a EarleyParser<T>,
-    word: Vec<T>,
-    states: Vec<Vec<State>>,
-    parents: Vec<Vec<Parent>>,
+    word: &'a [T],
+    chart: &'a Chart,
 }
 
 impl<T: Term> ParseTreeBuilder<'_, T> {
     pub fn build(&self, r: usize, i: usize) -> ParseTree<T> {
-        match &self.parents[r][i] {
+        match &self.chart.parents[r][i] {
             Parent::None => {
-                let rule_idx = self.states[r][i].rule_idx;
+                let rule_idx = self.chart.states[r][i].rule_idx;
                 let root_var = self.parser.rules[rule_idx].head;
                 ParseTree {
                     root_var,
@@ -190,19 +987,232 @@ impl<T: Term> ParseTreeBuilder<'_, T> {
                     .push(ParsedSymbol::Term(self.word[r - 1].clone()));
                 parse_tree
             }
+            Parent::Nullable(k) => {
+                let mut parse_tree = self.build(r, *k);
+                let state = self.chart.states[r][*k];
+                let Symbol::Var(var) = self.parser.rules[state.rule_idx].body[state.parsed] else {
+                    panic!("Expected a variable to be skipped over");
+                };
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Var(Box::new(self.parser.build_empty(var))));
+                parse_tree
+            }
             Parent::Var(k, l) => {
-                let mut parse_tree = self.build(self.states[r][*k].l, *l);
+                let mut parse_tree = self.build(self.chart.states[r][*k].l, *l);
                 let another_parse_tree = self.build(r, *k);
                 parse_tree
                     .children
                     .push(ParsedSymbol::Var(Box::new(another_parse_tree)));
                 parse_tree
             }
+            Parent::Recovered(c, k) => {
+                let mut parse_tree = self.build(*c, *k);
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Error(self.word[*c..r - 1].to_vec()));
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Term(self.word[r - 1].clone()));
+                parse_tree
+            }
+            Parent::Truncated(c, k) => {
+                let mut parse_tree = self.build(*c, *k);
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Error(self.word[*c..r].to_vec()));
+                parse_tree
+            }
+            Parent::Leo(k) => {
+                let mut parse_tree = self.build(r, *k);
+                for (l, penult) in self.parser.leo_path(self.chart, r, i) {
+                    let mut another_parse_tree = self.build(l, penult);
+                    another_parse_tree
+                        .children
+                        .push(ParsedSymbol::Var(Box::new(parse_tree)));
+                    parse_tree = another_parse_tree;
+                }
+                parse_tree
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// Builds the parse forest top-down from a filled chart, so that only the nodes taking part in
+/// some parse tree of the word are created.
+struct ParseForestBuilder<'a, T: Term> {
+    parser: &'a EarleyParser<T>,
+    word: &'a [T],
+    states: Vec<HashSet<State>>,
+    /// For every position and variable, the positions from which the variable derives the word up
+    /// to that position.
+    completed: Vec<HashMap<Var, Vec<usize>>>,
+    nodes: Vec<ForestNode<T>>,
+    node_indices: HashMap<NodeKey, usize>,
+}
+
+impl<'a, T: Term> ParseForestBuilder<'a, T> {
+    fn new(parser: &'a EarleyParser<T>, word: &'a [T], chart: &Chart) -> Self {
+        // The states skipped over by Leo's optimization are needed to build the forest, so they are
+        // restored first. They are restored from every completed state rather than only from those
+        // recorded as parents, since the top of a path may have been reached from several of them.
+        let mut states: Vec<HashSet<State>> = chart
+            .states
+            .iter()
+            .map(|states| states.iter().cloned().collect())
+            .collect();
+        for (r, column) in chart.states.iter().enumerate() {
+            for state in column.iter() {
+                if state.parsed != parser.rules[state.rule_idx].body.len() {
+                    continue;
+                }
+                for (l, penult) in parser.leo_path_from(chart, *state) {
+                    let state = chart.states[l][penult];
+                    states[r].insert(State {
+                        parsed: state.parsed + 1,
+                        ..state
+                    });
+                }
+            }
+        }
+
+        let mut completed = vec![HashMap::<Var, Vec<usize>>::new(); states.len()];
+        for (r, states) in states.iter().enumerate() {
+            for state in states {
+                let rule = &parser.rules[state.rule_idx];
+                if state.parsed == rule.body.len() {
+                    completed[r].entry(rule.head).or_default().push(state.l);
+                }
+            }
+            for starts in completed[r].values_mut() {
+                starts.sort();
+                starts.dedup();
+            }
+        }
+
+        Self {
+            parser,
+            word,
+            states,
+            completed,
+            nodes: Vec::new(),
+            node_indices: HashMap::new(),
+        }
+    }
+
+    fn add_node(&mut self, key: NodeKey, label: ForestNodeLabel<T>, l: usize, r: usize) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(ForestNode {
+            label,
+            start: l,
+            end: r,
+            packed_nodes: Vec::new(),
+        });
+        self.node_indices.insert(key, idx);
+        idx
+    }
+
+    fn var_node(&mut self, var: Var, l: usize, r: usize) -> usize {
+        let key = NodeKey::Var(var, l, r);
+        if let Some(idx) = self.node_indices.get(&key) {
+            return *idx;
+        }
+        let idx = self.add_node(key, ForestNodeLabel::Symbol(Symbol::Var(var)), l, r);
+        for rule_idx in self.parser.rules_by_var[var.0].iter().cloned() {
+            let parsed = self.parser.rules[rule_idx].body.len();
+            if self.states[r].contains(&State {
+                l,
+                rule_idx,
+                parsed,
+            }) {
+                self.add_packed_nodes(idx, rule_idx, parsed, l, r);
+            }
+        }
+        idx
+    }
+
+    fn term_node(&mut self, k: usize) -> usize {
+        let key = NodeKey::Term(k);
+        if let Some(idx) = self.node_indices.get(&key) {
+            return *idx;
+        }
+        let term = self.word[k].clone();
+        self.add_node(key, ForestNodeLabel::Symbol(Symbol::Term(term)), k, k + 1)
+    }
+
+    /// Returns the node deriving the first `parsed` symbols of the rule's body, if any.
+    fn prefix_node(&mut self, rule_idx: usize, parsed: usize, l: usize, r: usize) -> Option<usize> {
+        match parsed {
+            0 => None,
+            1 => Some(self.symbol_node(&self.parser.rules[rule_idx].body[0], l, r)),
+            _ => {
+                let key = NodeKey::Intermediate(rule_idx, parsed, l, r);
+                if let Some(idx) = self.node_indices.get(&key) {
+                    return Some(*idx);
+                }
+                let label = ForestNodeLabel::Intermediate { rule_idx, parsed };
+                let idx = self.add_node(key, label, l, r);
+                self.add_packed_nodes(idx, rule_idx, parsed, l, r);
+                Some(idx)
+            }
+        }
+    }
+
+    fn symbol_node(&mut self, symbol: &Symbol<T>, l: usize, r: usize) -> usize {
+        match symbol {
+            Symbol::Var(var) => self.var_node(*var, l, r),
+            Symbol::Term(_) => self.term_node(l),
+        }
+    }
+
+    /// Adds a packed node to the given node for every way in which the first `parsed` symbols of
+    /// the rule's body derive the span `l..r`.
+    fn add_packed_nodes(&mut self, idx: usize, rule_idx: usize, parsed: usize, l: usize, r: usize) {
+        let parser = self.parser;
+        if parsed == 0 {
+            self.nodes[idx].packed_nodes.push(PackedNode {
+                rule_idx,
+                split: l,
+                left: None,
+                right: None,
+            });
+            return;
+        }
+
+        let last_symbol = &parser.rules[rule_idx].body[parsed - 1];
+        let splits = match last_symbol {
+            Symbol::Term(term) => {
+                if r > l && self.word[r - 1] == *term {
+                    vec![r - 1]
+                } else {
+                    vec![]
+                }
+            }
+            Symbol::Var(var) => self.completed[r].get(var).cloned().unwrap_or_default(),
+        };
+
+        for k in splits {
+            let prefix_state = State {
+                l,
+                rule_idx,
+                parsed: parsed - 1,
+            };
+            if k < l || !self.states[k].contains(&prefix_state) {
+                continue;
+            }
+            let left = self.prefix_node(rule_idx, parsed - 1, l, k);
+            let right = Some(self.symbol_node(last_symbol, k, r));
+            self.nodes[idx].packed_nodes.push(PackedNode {
+                rule_idx,
+                split: k,
+                left,
+                right,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 struct State {
     l: usize,
     rule_idx: usize,
@@ -214,4 +1224,254 @@ enum Parent {
     None,
     Term(usize),
     Var(usize, usize),
+    Nullable(usize),
+    Leo(usize),
+    /// The state was advanced past a synchronizing terminal from the state at the given column and
+    /// index, skipping over the part of the word in between.
+    Recovered(usize, usize),
+    /// The state was completed from the state at the given column and index by skipping over the
+    /// rest of the word.
+    Truncated(usize, usize),
+}
+
+/// The result of running the Earley algorithm on a word: the states of every column, along with
+/// the parent of each state and the Leo items of every column.
+struct Chart {
+    /// The states of every column, with the positions at which they begin as they were when the
+    /// column was filled. The columns moved by an edit of the word are shifted by [`Chart::state`].
+    states: Vec<Vec<State>>,
+    parents: Vec<Vec<Parent>>,
+    leo_items: Vec<FxHashMap<Var, LeoItem>>,
+    /// For every column moved by edits of the word since it was filled, how to shift the positions
+    /// at which its states begin, in order of the positions before the shift. Columns moved by
+    /// the same edits share their shifts.
+    shifts: Vec<Option<Shifts>>,
+    /// For the columns `r` and `r + 1`, where `r` is the column being processed, the states in
+    /// them. Since states are only ever added to these two columns, the sets are reused for the
+    /// columns after them, at `present[r % 2]`.
+    present: [FxHashSet<State>; 2],
+    /// For every column and variable, the indices of the states expecting the variable next.
+    waiting: Vec<FxHashMap<Var, Vec<usize>>>,
+    /// For every variable, the last column in which its rules were predicted. Since predictions
+    /// only happen in the column being processed, this is enough to avoid predicting twice.
+    last_predicted: Vec<Option<usize>>,
+    /// Whether the start variable is predicted in every column, so that every part of the word it
+    /// derives is found, rather than only the prefixes of the word.
+    is_searching: bool,
+}
+
+impl Chart {
+    fn new(n: usize, n_vars: usize) -> Self {
+        Self {
+            states: vec![Vec::new(); n + 1],
+            parents: vec![Vec::new(); n + 1],
+            leo_items: Vec::with_capacity(n + 1),
+            shifts: vec![None; n + 1],
+            present: [FxHashSet::default(), FxHashSet::default()],
+            waiting: vec![FxHashMap::default(); n + 1],
+            last_predicted: vec![None; n_vars],
+            is_searching: false,
+        }
+    }
+
+    fn add_column(&mut self) {
+        self.states.push(Vec::new());
+        self.parents.push(Vec::new());
+        self.shifts.push(None);
+        self.waiting.push(FxHashMap::default());
+    }
+
+    /// Returns the state at the given column and index, with the position at which it begins
+    /// shifted along with the column.
+    fn state(&self, r: usize, i: usize) -> State {
+        let state = self.states[r][i];
+        State {
+            l: self.shift(r, state.l),
+            ..state
+        }
+    }
+
+    /// Shifts a position at which a state of the column begins along with the column.
+    fn shift(&self, r: usize, l: usize) -> usize {
+        let Some(shifts) = &self.shifts[r] else {
+            return l;
+        };
+        let shift = shifts[shifts.partition_point(|shift| shift.from <= l) - 1];
+        l - shift.from + shift.to
+    }
+
+    /// Returns the states of a column, shifted along with it.
+    fn column(&self, r: usize) -> impl Iterator<Item = State> + '_ {
+        (0..self.states[r].len()).map(move |i| self.state(r, i))
+    }
+
+    /// Returns the Leo item of a column for the variable, if the column has been filled and has
+    /// one, with its top shifted along with the column.
+    fn leo_item(&self, r: usize, var: Var) -> Option<LeoItem> {
+        let leo_item = *self.leo_items.get(r)?.get(&var)?;
+        let top = State {
+            l: self.shift(r, leo_item.top.l),
+            ..leo_item.top
+        };
+        Some(LeoItem { top, ..leo_item })
+    }
+
+    /// Whether the part of the word spanned by the state at the given column and index is as it
+    /// was when the column was filled, i.e., no edit since then was made within it.
+    fn is_unedited(&self, r: usize, i: usize) -> bool {
+        self.shifts[r]
+            .as_ref()
+            .and_then(|shifts| shifts.last())
+            .is_none_or(|shift| self.states[r][i].l >= shift.from)
+    }
+
+    /// Removes the columns from `r` on, returning them as a chart of their own.
+    fn split_off(&mut self, r: usize) -> Chart {
+        // The columns removed are to be filled again, so their predictions must be forgotten,
+        // while every column kept is filled already.
+        self.last_predicted.fill(None);
+        Chart {
+            states: self.states.split_off(r),
+            parents: self.parents.split_off(r),
+            leo_items: self.leo_items.split_off(r),
+            shifts: self.shifts.split_off(r),
+            present: [FxHashSet::default(), FxHashSet::default()],
+            waiting: self.waiting.split_off(r),
+            last_predicted: Vec::new(),
+            is_searching: self.is_searching,
+        }
+    }
+
+    /// Appends the columns of an old chart after its `k`th column, which was column `o` of the
+    /// chart before it was split off and has the same incomplete states as the last column of this
+    /// one.
+    ///
+    /// Since every incomplete state in the `k`th column began either at `o` or before the columns
+    /// split off, so does every state after it, and only the states beginning at `o` or later have
+    /// to be shifted. The states are left as they are, and the shift is only recorded for every
+    /// column, to be made whenever a state is looked at. The parents of the states may still point
+    /// to the states of the `k`th column by their old indices, which are looked up again when
+    /// parse trees are built.
+    fn append_shifted(&mut self, old_chart: Chart, k: usize, o: usize) {
+        let c = self.states.len() - 1;
+        let mut last_shifts: Option<(Option<Shifts>, Shifts)> = None;
+        for shifts in old_chart.shifts.into_iter().skip(k + 1) {
+            let new_shifts = match &last_shifts {
+                Some((old_shifts, new_shifts)) if is_same_shifts(old_shifts, &shifts) => {
+                    new_shifts.clone()
+                }
+                _ => {
+                    let new_shifts = Shift::after_edit(shifts.as_deref(), o, c);
+                    last_shifts = Some((shifts, new_shifts.clone()));
+                    new_shifts
+                }
+            };
+            self.shifts.push(Some(new_shifts));
+        }
+        self.states.extend(old_chart.states.into_iter().skip(k + 1));
+        self.parents
+            .extend(old_chart.parents.into_iter().skip(k + 1));
+        self.leo_items
+            .extend(old_chart.leo_items.into_iter().skip(k + 1));
+        self.waiting
+            .extend(old_chart.waiting.into_iter().skip(k + 1));
+    }
+}
+
+fn is_same_shifts(shifts: &Option<Shifts>, other_shifts: &Option<Shifts>) -> bool {
+    match (shifts, other_shifts) {
+        (None, None) => true,
+        (Some(shifts), Some(other_shifts)) => Rc::ptr_eq(shifts, other_shifts),
+        _ => false,
+    }
+}
+
+type Shifts = Rc<[Shift]>;
+
+/// Where the positions at which the states of a column moved by edits begin are shifted to: those
+/// from `from` on, up to where the next shift of the column starts, are shifted to `to` on.
+#[derive(Debug, Clone, Copy)]
+struct Shift {
+    from: usize,
+    to: usize,
+}
+
+impl Shift {
+    /// Returns the shifts of a column after an edit moved the column at `o` and those after it to
+    /// `c`, given its shifts before the edit, if any. No state of the column begins in between the
+    /// edit and `o`.
+    fn after_edit(shifts: Option<&[Shift]>, o: usize, c: usize) -> Shifts {
+        let shifts = shifts.unwrap_or(&[Shift { from: 0, to: 0 }]);
+        let mut new_shifts = Vec::with_capacity(shifts.len() + 1);
+        for (i, shift) in shifts.iter().enumerate() {
+            if shift.to >= o {
+                new_shifts.push(Shift {
+                    to: shift.to - o + c,
+                    ..*shift
+                });
+                continue;
+            }
+            new_shifts.push(*shift);
+            // The positions from `o` on start a shift of their own even if `c` is `o`, so that the
+            // states spanning the edit are told apart from those after it.
+            let from = shift.from + o - shift.to;
+            if shifts
+                .get(i + 1)
+                .is_none_or(|next_shift| from < next_shift.from)
+            {
+                new_shifts.push(Shift { from, to: c });
+            }
+        }
+        new_shifts.into()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LeoItem {
+    /// The index of the only state of the column expecting the variable next.
+    penult: usize,
+    /// The topmost completed state of the deterministic reduction path.
+    top: State,
+}
+
+/// Returns, for every variable, the variables which are left corners of it: the variable itself,
+/// and those which begin the body of a given rule of a left corner, possibly after some nullable
+/// variables.
+pub(super) fn get_predictions<T: Term>(
+    rules: &[Rule<T>],
+    rules_by_var: &[Vec<usize>],
+    nullable_rules: &[Option<usize>],
+) -> Vec<Vec<Var>> {
+    let mut left_corners: Vec<Vec<Var>> = vec![vec![]; rules_by_var.len()];
+    for &rule_idx in rules_by_var.iter().flatten() {
+        let rule = &rules[rule_idx];
+        for symbol in rule.body.iter() {
+            let Symbol::Var(var) = symbol else {
+                break;
+            };
+            left_corners[rule.head.0].push(*var);
+            if nullable_rules[var.0].is_none() {
+                break;
+            }
+        }
+    }
+
+    (0..rules_by_var.len())
+        .map(|var| {
+            let mut is_visited = vec![false; rules_by_var.len()];
+            is_visited[var] = true;
+            let mut predictions = vec![Var(var)];
+            let mut i = 0;
+            while i < predictions.len() {
+                for left_corner in left_corners[predictions[i].0].iter().cloned() {
+                    if !is_visited[left_corner.0] {
+                        is_visited[left_corner.0] = true;
+                        predictions.push(left_corner);
+                    }
+                }
+                i += 1;
+            }
+            predictions
+        })
+        .collect()
 }
@@ -0,0 +1,163 @@
+use std::{collections::BTreeSet, error::Error, fmt::Display, rc::Rc};
+
+use crate::{Term, Var};
+
+use super::ParseTree;
+
+/// Where and why a word failed to parse.
+///
+/// The word goes wrong at the end of its longest part which is the beginning of some word in the
+/// language. What is found there, either a terminal or the end of the word, is not among what could
+/// come next, and the variables whose rules had been recognized up to there were expecting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError<T: Term> {
+    pub(crate) position: usize,
+    pub(crate) found: Option<T>,
+    pub(crate) expected: BTreeSet<Option<T>>,
+    pub(crate) vars: Vec<(Var, Rc<str>)>,
+}
+
+impl<T: Term> ParseError<T> {
+    /// Makes the error of a word going wrong at the position from what was expected there and the
+    /// variables being recognized, each along with where its rule started. A variable may be given
+    /// more than once, and is only listed where it starts furthest back.
+    pub(super) fn new(
+        word: &[T],
+        position: usize,
+        expected: BTreeSet<Option<T>>,
+        mut vars: Vec<(usize, Var)>,
+        var_names: &[Rc<str>],
+    ) -> Self {
+        vars.sort();
+        let mut is_listed = vec![false; var_names.len()];
+        vars.retain(|(_, var)| !std::mem::replace(&mut is_listed[var.0], true));
+
+        Self {
+            position,
+            found: word.get(position).cloned(),
+            expected,
+            vars: vars
+                .into_iter()
+                .map(|(_, var)| (var, var_names[var.0].clone()))
+                .collect(),
+        }
+    }
+
+    /// The length of the longest part of the word which is the beginning of a word in the language.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The terminal at the position, or `None` if the word ends there.
+    pub fn found(&self) -> Option<&T> {
+        self.found.as_ref()
+    }
+
+    /// The terminals which could come next at the position, along with `None` if the word could
+    /// end there.
+    pub fn expected(&self) -> &BTreeSet<Option<T>> {
+        &self.expected
+    }
+
+    /// The variables having a rule whose body was recognized up to the position, but not to its
+    /// end, from the ones starting furthest back.
+    pub fn vars(&self) -> impl Iterator<Item = (Var, &str)> {
+        self.vars.iter().map(|(var, var_name)| (*var, &**var_name))
+    }
+
+    /// Displays the error followed by the word it was found in, with the position marked under it.
+    pub fn in_word<'a>(&'a self, word: &'a [T]) -> ParseErrorInWord<'a, T> {
+        ParseErrorInWord { error: self, word }
+    }
+}
+
+fn fmt_lookahead<T: Term>(lookahead: Option<&T>) -> String {
+    match lookahead {
+        Some(term) => format!("{:?}", term),
+        None => "end of input".to_string(),
+    }
+}
+
+impl<T: Term + Display> Display for ParseError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unexpected {} at position {}",
+            fmt_lookahead(self.found()),
+            self.position
+        )?;
+        let expected: Vec<_> = self
+            .expected
+            .iter()
+            .map(|lookahead| fmt_lookahead(lookahead.as_ref()))
+            .collect();
+        match expected.as_slice() {
+            [] => {}
+            [expected] => write!(f, ", expected {}", expected)?,
+            _ => write!(f, ", expected one of {}", expected.join(", "))?,
+        }
+        if !self.vars.is_empty() {
+            let var_names: Vec<_> = self.vars().map(|(_, var_name)| var_name).collect();
+            write!(f, " while parsing {}", var_names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`ParseError`] displayed along with the word it was found in.
+pub struct ParseErrorInWord<'a, T: Term> {
+    error: &'a ParseError<T>,
+    word: &'a [T],
+}
+
+impl<T: Term + Display> Display for ParseErrorInWord<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.error)?;
+
+        // Words of characters are written out as they are, and other words with their terminals
+        // separated by spaces.
+        let terms: Vec<_> = self.word.iter().map(|term| term.to_string()).collect();
+        let separator = match terms.iter().all(|term| term.chars().count() == 1) {
+            true => "",
+            false => " ",
+        };
+        writeln!(f, "{}", terms.join(separator))?;
+        let column: usize = terms[..self.error.position]
+            .iter()
+            .map(|term| term.chars().count() + separator.len())
+            .sum();
+        write!(f, "{}^", " ".repeat(column))
+    }
+}
+
+impl<T: Term + Display> Error for ParseError<T> {}
+
+/// A parse tree of a word which may not be in the language, in which the parts of the word that
+/// could not be parsed are skipped over as [`ParsedSymbol::Error`](super::ParsedSymbol::Error)s,
+/// along with the errors found.
+///
+/// [`Interpreter::interpret`](crate::interpret::Interpreter::interpret) panics at the errors, and
+/// the parse tree is to be interpreted with
+/// [`Interpreter::interpret_with_errors`](crate::interpret::Interpreter::interpret_with_errors)
+/// instead, which gives the meaning of the rules with errors in them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery<T: Term> {
+    pub(crate) parse_tree: ParseTree<T>,
+    pub(crate) diagnostics: Vec<ParseError<T>>,
+}
+
+impl<T: Term> Recovery<T> {
+    pub fn parse_tree(&self) -> &ParseTree<T> {
+        &self.parse_tree
+    }
+
+    pub fn into_parse_tree(self) -> ParseTree<T> {
+        self.parse_tree
+    }
+
+    /// The errors found, in order of their positions. There are none if the word is in the
+    /// language.
+    pub fn diagnostics(&self) -> &[ParseError<T>] {
+        &self.diagnostics
+    }
+}
@@ -0,0 +1,1202 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, VecDeque},
+    rc::Rc,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Rule, Symbol, Term, Var};
+
+use super::{BigUint, ParseCount, ParseTree, ParsedSymbol, Semiring};
+
+/// A shared packed parse forest (SPPF) representing all parse trees of a word at once, in the
+/// binarised form described by Scott (2008).
+///
+/// Symbol nodes are labelled with a variable or terminal along with the span of the word it
+/// derives. Intermediate nodes are labelled with a rule and the number of symbols of its body
+/// parsed so far, and stand for the span derived by those symbols. Every packed node below a symbol
+/// or intermediate node is one way of deriving the span of its parent: its right child derives the
+/// last of the symbols and its left child, if any, derives the ones before it.
+///
+/// The forest takes up cubic space in the length of the word, even when the word has exponentially
+/// many parse trees. On grammars with cycles (`A =>+ A`), the forest itself contains cycles.
+#[derive(Debug, Clone)]
+pub struct ParseForest<'a, T: Term> {
+    pub(crate) var_names: &'a [Rc<str>],
+    pub(crate) nodes: Vec<ForestNode<T>>,
+    pub(crate) root: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForestNode<T: Term> {
+    pub(crate) label: ForestNodeLabel<T>,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) packed_nodes: Vec<PackedNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForestNodeLabel<T: Term> {
+    Symbol(Symbol<T>),
+    Intermediate { rule_idx: usize, parsed: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedNode {
+    pub(crate) rule_idx: usize,
+    pub(crate) split: usize,
+    pub(crate) left: Option<usize>,
+    pub(crate) right: Option<usize>,
+}
+
+impl<'a, T: Term> ParseForest<'a, T> {
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    pub fn node(&self, idx: usize) -> &ForestNode<T> {
+        &self.nodes[idx]
+    }
+
+    pub fn nodes(&self) -> &[ForestNode<T>] {
+        &self.nodes
+    }
+
+    /// Returns whether the word has more than one parse tree.
+    pub fn is_ambiguous(&self) -> bool {
+        self.nodes.iter().any(|node| node.packed_nodes.len() > 1)
+    }
+
+    /// Returns one of the parse trees of the word.
+    pub fn parse_tree(&self) -> ParseTree<T> {
+        let choices = self.finite_choices();
+        let mut children = Vec::new();
+        self.build_with_choices(self.root, &mut |idx| choices[idx].unwrap(), &mut children);
+        let Some(ParsedSymbol::Var(parse_tree)) = children.pop() else {
+            panic!("Expected root to be labelled with a variable");
+        };
+        *parse_tree
+    }
+
+    /// Returns all parse trees of the word, except the ones which go around a cycle of the forest.
+    ///
+    /// **Warning**: The number of parse trees may be exponential in the length of the word.
+    pub fn parse_trees(&self) -> Vec<ParseTree<T>> {
+        let mut on_path = vec![false; self.nodes.len()];
+        self.all_symbols(self.root, &mut on_path)
+            .into_iter()
+            .map(|symbol| {
+                let ParsedSymbol::Var(parse_tree) = symbol else {
+                    panic!("Expected root to be labelled with a variable");
+                };
+                *parse_tree
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the same parse trees as [`ParseForest::parse_trees`], which builds
+    /// them one at a time and in order of the number of rules applied in them, fewest first.
+    pub fn into_parse_trees(self) -> ParseTrees<'a, T> {
+        ParseTrees(self.into_cheapest_parse_trees(|_| 1.0))
+    }
+
+    /// Returns an iterator over the same parse trees as [`ParseForest::parse_trees`] along with
+    /// their costs, which builds them one at a time and in order of their costs, lowest first. The
+    /// cost of a parse tree is the sum of the costs of the rules applied in it, which must not be
+    /// negative.
+    pub fn into_cheapest_parse_trees(
+        self,
+        rule_cost: impl Fn(usize) -> f64,
+    ) -> CheapestParseTrees<'a, T> {
+        let costs: Vec<Vec<f64>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.packed_nodes
+                    .iter()
+                    .map(|packed_node| match node.label {
+                        ForestNodeLabel::Symbol(Symbol::Var(_)) => {
+                            let cost = rule_cost(packed_node.rule_idx);
+                            assert!(cost >= 0.0, "Expected rule costs not to be negative");
+                            cost
+                        }
+                        _ => 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+        let search = if self.is_cyclic() {
+            let mut best_first = BestFirst {
+                min_costs: self.min_costs(&costs),
+                queue: BinaryHeap::new(),
+                partial_trees: Vec::new(),
+            };
+            best_first.push(PartialTree {
+                cost: 0.0,
+                choices: Vec::new(),
+                pending: vec![(self.root, None)],
+            });
+            Search::BestFirst(best_first)
+        } else {
+            Search::Lazy(LazyKBest {
+                derivations: vec![Vec::new(); self.nodes.len()],
+                candidates: vec![None; self.nodes.len()],
+                seen: vec![FxHashSet::default(); self.nodes.len()],
+                n_returned: 0,
+            })
+        };
+        CheapestParseTrees {
+            forest: self,
+            costs,
+            search,
+        }
+    }
+
+    /// Returns the number of parse trees of the word, which is infinite when the forest has a cycle.
+    pub fn count_parse_trees(&self) -> ParseCount {
+        // Every node of the forest derives part of the word, so a cycle reachable from the root can
+        // be gone around any number of times. Otherwise, the children of a node are counted before
+        // the node itself, in a depth-first search whose path is marked by `on_path`.
+        let mut counts: Vec<Option<BigUint>> = vec![None; self.nodes.len()];
+        let mut on_path = vec![false; self.nodes.len()];
+        let mut stack = vec![(self.root, false)];
+        while let Some((idx, is_expanded)) = stack.pop() {
+            if is_expanded {
+                let node = &self.nodes[idx];
+                let count = if let ForestNodeLabel::Symbol(Symbol::Term(_)) = node.label {
+                    BigUint::from(1u32)
+                } else {
+                    node.packed_nodes
+                        .iter()
+                        .fold(BigUint::from(0u32), |count, packed_node| {
+                            let product = [packed_node.left, packed_node.right]
+                                .into_iter()
+                                .flatten()
+                                .fold(BigUint::from(1u32), |product, child| {
+                                    &product * counts[child].as_ref().unwrap()
+                                });
+                            &count + &product
+                        })
+                };
+                counts[idx] = Some(count);
+                on_path[idx] = false;
+                continue;
+            }
+            if counts[idx].is_some() {
+                continue;
+            }
+            if on_path[idx] {
+                return ParseCount::Infinite;
+            }
+            on_path[idx] = true;
+            stack.push((idx, true));
+            for packed_node in self.nodes[idx].packed_nodes.iter() {
+                for child in [packed_node.left, packed_node.right].into_iter().flatten() {
+                    if counts[child].is_none() {
+                        stack.push((child, false));
+                    }
+                }
+            }
+        }
+        ParseCount::Finite(counts[self.root].take().unwrap())
+    }
+
+    /// Returns the weight of the word in the semiring, given the weight of every rule by its index,
+    /// or `None` if the weight of a cycle of the forest does not settle.
+    ///
+    /// The weight of every node is the sum over its packed nodes of the weight of their rule, for
+    /// symbol nodes, times the weights of their children. Nodes are weighed after their children,
+    /// except on cycles of the forest, where the weights of the nodes of the cycle are computed
+    /// again for as many rounds as there are nodes on the cycle, which is enough for every parse
+    /// tree going around it at most once to be weighed. If one more round still changes them, the
+    /// trees going around the cycle more often add to the sum, as they do when counting parse
+    /// trees, and the word has no weight that could be computed in finitely many rounds. The
+    /// weights do settle in semirings in which going around a cycle never changes the sum, such as
+    /// [`Boolean`](super::Boolean), [`Viterbi`](super::Viterbi) with probabilities, or
+    /// [`Tropical`](super::Tropical) with non-negative costs.
+    pub fn weight<S: Semiring>(&self, rule_weight: impl Fn(usize) -> S) -> Option<S> {
+        let mut weights = vec![S::zero(); self.nodes.len()];
+        for component in self.strongly_connected_components() {
+            let n_rounds = if self.is_cyclic_component(&component) {
+                component.len() + 1
+            } else {
+                1
+            };
+            let mut changed = false;
+            for _ in 0..n_rounds {
+                changed = false;
+                for &idx in component.iter() {
+                    let weight = self.node_weight(idx, &weights, &rule_weight);
+                    if weight != weights[idx] {
+                        weights[idx] = weight;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            if changed && n_rounds > 1 {
+                return None;
+            }
+        }
+        Some(weights.swap_remove(self.root))
+    }
+
+    fn node_weight<S: Semiring>(
+        &self,
+        idx: usize,
+        weights: &[S],
+        rule_weight: &impl Fn(usize) -> S,
+    ) -> S {
+        let node = &self.nodes[idx];
+        match node.label {
+            ForestNodeLabel::Symbol(Symbol::Term(_)) => S::one(),
+            ForestNodeLabel::Symbol(Symbol::Var(_)) | ForestNodeLabel::Intermediate { .. } => node
+                .packed_nodes
+                .iter()
+                .fold(S::zero(), |weight, packed_node| {
+                    let product = match node.label {
+                        ForestNodeLabel::Symbol(_) => rule_weight(packed_node.rule_idx),
+                        ForestNodeLabel::Intermediate { .. } => S::one(),
+                    };
+                    let product = [packed_node.left, packed_node.right]
+                        .into_iter()
+                        .flatten()
+                        .fold(product, |product, child| product.times(&weights[child]));
+                    weight.plus(&product)
+                }),
+        }
+    }
+
+    /// Returns whether the forest has a cycle reachable from the root.
+    fn is_cyclic(&self) -> bool {
+        self.strongly_connected_components()
+            .iter()
+            .any(|component| self.is_cyclic_component(component))
+    }
+
+    fn is_cyclic_component(&self, component: &[usize]) -> bool {
+        component.len() > 1
+            || self
+                .children(component[0])
+                .any(|child| child == component[0])
+    }
+
+    fn children(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[idx]
+            .packed_nodes
+            .iter()
+            .flat_map(|packed_node| [packed_node.left, packed_node.right])
+            .flatten()
+    }
+
+    /// Finds the strongly connected components of the nodes reachable from the root with Tarjan's
+    /// (1972) algorithm, which lists every component after the ones its nodes point to.
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let mut components = Vec::new();
+        let mut indices: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut low_links = vec![0; self.nodes.len()];
+        let mut on_stack = vec![false; self.nodes.len()];
+        let mut stack = Vec::new();
+        // The nodes being visited, along with the number of the children of their packed nodes,
+        // left and right, looked at so far.
+        let mut path = vec![(self.root, 0)];
+        indices[self.root] = Some(0);
+        let mut n_visited = 1;
+        stack.push(self.root);
+        on_stack[self.root] = true;
+
+        while let Some((idx, next)) = path.last_mut() {
+            let idx = *idx;
+            let packed_nodes = &self.nodes[idx].packed_nodes;
+            if *next < 2 * packed_nodes.len() {
+                let packed_node = &packed_nodes[*next / 2];
+                let child = match *next % 2 {
+                    0 => packed_node.left,
+                    _ => packed_node.right,
+                };
+                *next += 1;
+                let Some(child) = child else {
+                    continue;
+                };
+                match indices[child] {
+                    None => {
+                        indices[child] = Some(n_visited);
+                        low_links[child] = n_visited;
+                        n_visited += 1;
+                        stack.push(child);
+                        on_stack[child] = true;
+                        path.push((child, 0));
+                    }
+                    Some(index) if on_stack[child] => {
+                        low_links[idx] = low_links[idx].min(index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            path.pop();
+            if let Some(&(parent, _)) = path.last() {
+                low_links[parent] = low_links[parent].min(low_links[idx]);
+            }
+            if Some(low_links[idx]) == indices[idx] {
+                let mut component = Vec::new();
+                while let Some(node) = stack.pop() {
+                    on_stack[node] = false;
+                    component.push(node);
+                    if node == idx {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+
+        components
+    }
+
+    /// Returns the forest holding the given parse tree only, for parsers which never find more
+    /// than one parse tree of a word.
+    pub(super) fn of_parse_tree(var_names: &'a [Rc<str>], parse_tree: &ParseTree<T>) -> Self {
+        let mut parse_forest = Self {
+            var_names,
+            nodes: Vec::new(),
+            root: 0,
+        };
+        parse_forest.root = parse_forest.add_parse_tree(parse_tree, 0);
+        parse_forest
+    }
+
+    /// Adds the nodes of a parse tree deriving the word from `start` on, and returns its root.
+    fn add_parse_tree(&mut self, parse_tree: &ParseTree<T>, start: usize) -> usize {
+        let rule_idx = parse_tree.rule_idx;
+        let mut end = start;
+        let mut prefix = None;
+        let mut packed_node = PackedNode {
+            rule_idx,
+            split: start,
+            left: None,
+            right: None,
+        };
+        for (parsed, child) in parse_tree.children.iter().enumerate() {
+            let split = end;
+            let child_idx = match child {
+                ParsedSymbol::Term(term) => {
+                    end += 1;
+                    let label = ForestNodeLabel::Symbol(Symbol::Term(term.clone()));
+                    self.add_node(label, split, end, Vec::new())
+                }
+                ParsedSymbol::Var(parse_tree) => {
+                    let child_idx = self.add_parse_tree(parse_tree, split);
+                    end = self.nodes[child_idx].end;
+                    child_idx
+                }
+                ParsedSymbol::Error(_) => panic!("Expected a parse tree without errors"),
+            };
+            packed_node = PackedNode {
+                rule_idx,
+                split,
+                left: prefix,
+                right: Some(child_idx),
+            };
+            // The whole body is derived by the root itself rather than by an intermediate node.
+            if parsed + 1 == parse_tree.children.len() {
+                break;
+            }
+            prefix = match parsed {
+                0 => Some(child_idx),
+                _ => Some(self.add_node(
+                    ForestNodeLabel::Intermediate {
+                        rule_idx,
+                        parsed: parsed + 1,
+                    },
+                    start,
+                    end,
+                    vec![packed_node],
+                )),
+            };
+        }
+        let label = ForestNodeLabel::Symbol(Symbol::Var(parse_tree.root_var));
+        self.add_node(label, start, end, vec![packed_node])
+    }
+
+    fn add_node(
+        &mut self,
+        label: ForestNodeLabel<T>,
+        start: usize,
+        end: usize,
+        packed_nodes: Vec<PackedNode>,
+    ) -> usize {
+        self.nodes.push(ForestNode {
+            label,
+            start,
+            end,
+            packed_nodes,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Removes the nodes which cannot be reached from the root, which parsers building the forest
+    /// bottom-up leave behind for parts of the word that fail to be part of a parse tree.
+    pub(crate) fn pruned(self) -> Self {
+        let mut indices = vec![None; self.nodes.len()];
+        let mut order = vec![self.root];
+        indices[self.root] = Some(0);
+        let mut i = 0;
+        while i < order.len() {
+            for packed_node in self.nodes[order[i]].packed_nodes.iter() {
+                for child in [packed_node.left, packed_node.right].into_iter().flatten() {
+                    if indices[child].is_none() {
+                        indices[child] = Some(order.len());
+                        order.push(child);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let mut nodes: Vec<_> = self.nodes.into_iter().map(Some).collect();
+        let nodes = order
+            .into_iter()
+            .map(|idx| {
+                let mut node = nodes[idx].take().unwrap();
+                for packed_node in node.packed_nodes.iter_mut() {
+                    packed_node.left = packed_node.left.and_then(|left| indices[left]);
+                    packed_node.right = packed_node.right.and_then(|right| indices[right]);
+                }
+                node
+            })
+            .collect();
+
+        Self {
+            var_names: self.var_names,
+            nodes,
+            root: 0,
+        }
+    }
+
+    /// For every node, finds the lowest cost of the parts of parse trees it stands for, given the
+    /// costs of its packed nodes, using Knuth's (1977) generalization of Dijkstra's algorithm.
+    fn min_costs(&self, costs: &[Vec<f64>]) -> Vec<f64> {
+        let mut min_costs = vec![f64::INFINITY; self.nodes.len()];
+        let mut is_done = vec![false; self.nodes.len()];
+        let mut n_pending_children = Vec::new();
+        let mut sums = costs.to_vec();
+        let mut dependants: Vec<Vec<(usize, usize)>> = vec![vec![]; self.nodes.len()];
+        let mut queue = BinaryHeap::new();
+
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if let ForestNodeLabel::Symbol(Symbol::Term(_)) = node.label {
+                queue.push(Reverse((Cost(0.0), idx)));
+            }
+            n_pending_children.push(Vec::with_capacity(node.packed_nodes.len()));
+            for (packed_idx, packed_node) in node.packed_nodes.iter().enumerate() {
+                let children = [packed_node.left, packed_node.right];
+                let n_children = children.iter().flatten().count();
+                n_pending_children[idx].push(n_children);
+                for child in children.into_iter().flatten() {
+                    dependants[child].push((idx, packed_idx));
+                }
+                if n_children == 0 {
+                    queue.push(Reverse((Cost(costs[idx][packed_idx]), idx)));
+                }
+            }
+        }
+
+        while let Some(Reverse((Cost(cost), child))) = queue.pop() {
+            if is_done[child] {
+                continue;
+            }
+            is_done[child] = true;
+            min_costs[child] = cost;
+            for (idx, packed_idx) in dependants[child].iter().cloned() {
+                sums[idx][packed_idx] += cost;
+                n_pending_children[idx][packed_idx] -= 1;
+                if n_pending_children[idx][packed_idx] == 0 {
+                    queue.push(Reverse((Cost(sums[idx][packed_idx]), idx)));
+                }
+            }
+        }
+
+        min_costs
+    }
+
+    /// For every node, chooses a packed node whose children have been given a choice before. Since
+    /// the choices never go around a cycle, following them always produces a finite parse tree.
+    fn finite_choices(&self) -> Vec<Option<usize>> {
+        let mut choices = vec![None; self.nodes.len()];
+        let mut is_done = vec![false; self.nodes.len()];
+        let mut n_pending_children = Vec::new();
+        let mut dependants: Vec<Vec<(usize, usize)>> = vec![vec![]; self.nodes.len()];
+        let mut queue = VecDeque::<usize>::new();
+
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if let ForestNodeLabel::Symbol(Symbol::Term(_)) = node.label {
+                is_done[idx] = true;
+                queue.push_back(idx);
+            }
+            n_pending_children.push(Vec::with_capacity(node.packed_nodes.len()));
+            for (packed_idx, packed_node) in node.packed_nodes.iter().enumerate() {
+                let children = [packed_node.left, packed_node.right];
+                let n_children = children.iter().flatten().count();
+                n_pending_children[idx].push(n_children);
+                for child in children.into_iter().flatten() {
+                    dependants[child].push((idx, packed_idx));
+                }
+                if n_children == 0 && !is_done[idx] {
+                    is_done[idx] = true;
+                    choices[idx] = Some(packed_idx);
+                    queue.push_back(idx);
+                }
+            }
+        }
+
+        while let Some(child) = queue.pop_front() {
+            for (idx, packed_idx) in dependants[child].iter().cloned() {
+                n_pending_children[idx][packed_idx] -= 1;
+                if n_pending_children[idx][packed_idx] == 0 && !is_done[idx] {
+                    is_done[idx] = true;
+                    choices[idx] = Some(packed_idx);
+                    queue.push_back(idx);
+                }
+            }
+        }
+
+        choices
+    }
+
+    /// Pushes the symbols derived by the given node onto `symbols`, choosing the packed node of
+    /// every node visited, from left to right, with `choose`.
+    fn build_with_choices(
+        &self,
+        idx: usize,
+        choose: &mut impl FnMut(usize) -> usize,
+        symbols: &mut Vec<ParsedSymbol<T>>,
+    ) {
+        let node = &self.nodes[idx];
+        match &node.label {
+            ForestNodeLabel::Symbol(Symbol::Term(term)) => {
+                symbols.push(ParsedSymbol::Term(term.clone()));
+            }
+            ForestNodeLabel::Symbol(Symbol::Var(var)) => {
+                let packed_node = &node.packed_nodes[choose(idx)];
+                let mut children = Vec::new();
+                for child in [packed_node.left, packed_node.right].into_iter().flatten() {
+                    self.build_with_choices(child, choose, &mut children);
+                }
+                symbols.push(ParsedSymbol::Var(Box::new(ParseTree {
+                    root_var: *var,
+                    root_var_name: self.var_names[var.0].to_owned(),
+                    rule_idx: packed_node.rule_idx,
+                    children,
+                })));
+            }
+            ForestNodeLabel::Intermediate { .. } => {
+                let packed_node = &node.packed_nodes[choose(idx)];
+                for child in [packed_node.left, packed_node.right].into_iter().flatten() {
+                    self.build_with_choices(child, choose, symbols);
+                }
+            }
+        }
+    }
+
+    /// Returns every possible sequence of symbols derived by the given node, without going around
+    /// a cycle. Symbol nodes always derive sequences consisting of a single symbol.
+    fn all_sequences(&self, idx: usize, on_path: &mut Vec<bool>) -> Vec<Vec<ParsedSymbol<T>>> {
+        match self.nodes[idx].label {
+            ForestNodeLabel::Symbol(_) => self
+                .all_symbols(idx, on_path)
+                .into_iter()
+                .map(|symbol| vec![symbol])
+                .collect(),
+            ForestNodeLabel::Intermediate { .. } => {
+                if on_path[idx] {
+                    return Vec::new();
+                }
+                on_path[idx] = true;
+                let mut sequences = Vec::new();
+                for packed_node in self.nodes[idx].packed_nodes.iter() {
+                    sequences.extend(self.all_packed_sequences(packed_node, on_path));
+                }
+                on_path[idx] = false;
+                sequences
+            }
+        }
+    }
+
+    fn all_packed_sequences(
+        &self,
+        packed_node: &PackedNode,
+        on_path: &mut Vec<bool>,
+    ) -> Vec<Vec<ParsedSymbol<T>>> {
+        let mut sequences = vec![Vec::new()];
+        for child in [packed_node.left, packed_node.right].into_iter().flatten() {
+            let child_sequences = self.all_sequences(child, on_path);
+            sequences = sequences
+                .iter()
+                .flat_map(|sequence| {
+                    child_sequences.iter().map(move |child_sequence| {
+                        let mut sequence = sequence.clone();
+                        sequence.extend(child_sequence.iter().cloned());
+                        sequence
+                    })
+                })
+                .collect();
+        }
+        sequences
+    }
+
+    fn all_symbols(&self, idx: usize, on_path: &mut Vec<bool>) -> Vec<ParsedSymbol<T>> {
+        let node = &self.nodes[idx];
+        let var = match &node.label {
+            ForestNodeLabel::Symbol(Symbol::Term(term)) => {
+                return vec![ParsedSymbol::Term(term.clone())];
+            }
+            ForestNodeLabel::Symbol(Symbol::Var(var)) => *var,
+            ForestNodeLabel::Intermediate { .. } => {
+                panic!("Expected a symbol node");
+            }
+        };
+        if on_path[idx] {
+            return Vec::new();
+        }
+        on_path[idx] = true;
+        let mut symbols = Vec::new();
+        for packed_node in node.packed_nodes.iter() {
+            for children in self.all_packed_sequences(packed_node, on_path) {
+                symbols.push(ParsedSymbol::Var(Box::new(ParseTree {
+                    root_var: var,
+                    root_var_name: self.var_names[var.0].to_owned(),
+                    rule_idx: packed_node.rule_idx,
+                    children,
+                })));
+            }
+        }
+        on_path[idx] = false;
+        symbols
+    }
+}
+
+/// The key of a node of a forest while it is being built: a variable or terminal along with its
+/// span, or a rule along with the number of symbols of its body parsed and their span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum NodeKey {
+    Var(Var, usize, usize),
+    Term(usize),
+    Intermediate(usize, usize, usize, usize),
+}
+
+/// Builds the parse forest of a word top-down, for parsers whose charts only tell which variables
+/// derive which parts of the word. The rules of every variable are matched against the word with
+/// `derives`, so that only the nodes taking part in some parse tree of the word are created.
+///
+/// `derives` is only asked about a variable at a position when the symbols before it in a rule of
+/// a node of the forest derive the word up to there, so it may leave out variables which cannot
+/// begin at a position, as goal-directed recognizers do.
+pub(super) struct SpanForestBuilder<'a, T: Term, D> {
+    rules: &'a [Rule<T>],
+    rules_by_var: Vec<Vec<usize>>,
+    word: &'a [T],
+    derives: D,
+    /// Whether the first symbols of the body of a rule derive a span, by the rule, the number of
+    /// symbols, and the span.
+    prefixes: FxHashMap<(usize, usize, usize, usize), bool>,
+    nodes: Vec<ForestNode<T>>,
+    node_indices: FxHashMap<NodeKey, usize>,
+}
+
+impl<'a, T: Term, D: FnMut(Var, usize, usize) -> bool> SpanForestBuilder<'a, T, D> {
+    pub(super) fn new(rules: &'a [Rule<T>], n_vars: usize, word: &'a [T], derives: D) -> Self {
+        let mut rules_by_var = vec![vec![]; n_vars];
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            rules_by_var[rule.head.0].push(rule_idx);
+        }
+        Self {
+            rules,
+            rules_by_var,
+            word,
+            derives,
+            prefixes: FxHashMap::default(),
+            nodes: Vec::new(),
+            node_indices: FxHashMap::default(),
+        }
+    }
+
+    /// Returns the forest of the parse trees of the word with the given variable at the root, or
+    /// `None` if the variable does not derive the word.
+    pub(super) fn build(mut self, var_names: &[Rc<str>], var: Var) -> Option<ParseForest<'_, T>> {
+        let n = self.word.len();
+        if !(self.derives)(var, 0, n) {
+            return None;
+        }
+        let root = self.var_node(var, 0, n);
+        Some(ParseForest {
+            var_names,
+            nodes: self.nodes,
+            root,
+        })
+    }
+
+    fn add_node(&mut self, key: NodeKey, label: ForestNodeLabel<T>, l: usize, r: usize) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(ForestNode {
+            label,
+            start: l,
+            end: r,
+            packed_nodes: Vec::new(),
+        });
+        self.node_indices.insert(key, idx);
+        idx
+    }
+
+    fn var_node(&mut self, var: Var, l: usize, r: usize) -> usize {
+        let key = NodeKey::Var(var, l, r);
+        if let Some(idx) = self.node_indices.get(&key) {
+            return *idx;
+        }
+        let idx = self.add_node(key, ForestNodeLabel::Symbol(Symbol::Var(var)), l, r);
+        for rule_idx in self.rules_by_var[var.0].clone() {
+            let parsed = self.rules[rule_idx].body.len();
+            self.add_packed_nodes(idx, rule_idx, parsed, l, r);
+        }
+        idx
+    }
+
+    fn term_node(&mut self, k: usize) -> usize {
+        let key = NodeKey::Term(k);
+        if let Some(idx) = self.node_indices.get(&key) {
+            return *idx;
+        }
+        let term = self.word[k].clone();
+        self.add_node(key, ForestNodeLabel::Symbol(Symbol::Term(term)), k, k + 1)
+    }
+
+    /// Returns the node deriving the first `parsed` symbols of the rule's body, if any.
+    fn prefix_node(&mut self, rule_idx: usize, parsed: usize, l: usize, r: usize) -> Option<usize> {
+        match parsed {
+            0 => None,
+            1 => Some(self.symbol_node(&self.rules[rule_idx].body[0], l, r)),
+            _ => {
+                let key = NodeKey::Intermediate(rule_idx, parsed, l, r);
+                if let Some(idx) = self.node_indices.get(&key) {
+                    return Some(*idx);
+                }
+                let label = ForestNodeLabel::Intermediate { rule_idx, parsed };
+                let idx = self.add_node(key, label, l, r);
+                self.add_packed_nodes(idx, rule_idx, parsed, l, r);
+                Some(idx)
+            }
+        }
+    }
+
+    fn symbol_node(&mut self, symbol: &Symbol<T>, l: usize, r: usize) -> usize {
+        match symbol {
+            Symbol::Var(var) => self.var_node(*var, l, r),
+            Symbol::Term(_) => self.term_node(l),
+        }
+    }
+
+    /// Adds a packed node to the given node for every way in which the first `parsed` symbols of
+    /// the rule's body derive the span `l..r`.
+    fn add_packed_nodes(&mut self, idx: usize, rule_idx: usize, parsed: usize, l: usize, r: usize) {
+        let rules = self.rules;
+        if parsed == 0 {
+            if l == r {
+                self.nodes[idx].packed_nodes.push(PackedNode {
+                    rule_idx,
+                    split: l,
+                    left: None,
+                    right: None,
+                });
+            }
+            return;
+        }
+
+        let last_symbol = &rules[rule_idx].body[parsed - 1];
+        for k in l..=r {
+            if !self.derives_prefix(rule_idx, parsed - 1, l, k)
+                || !self.derives_symbol(last_symbol, k, r)
+            {
+                continue;
+            }
+            let left = self.prefix_node(rule_idx, parsed - 1, l, k);
+            let right = Some(self.symbol_node(last_symbol, k, r));
+            self.nodes[idx].packed_nodes.push(PackedNode {
+                rule_idx,
+                split: k,
+                left,
+                right,
+            });
+        }
+    }
+
+    fn derives_symbol(&mut self, symbol: &Symbol<T>, l: usize, r: usize) -> bool {
+        match symbol {
+            Symbol::Var(var) => (self.derives)(*var, l, r),
+            Symbol::Term(term) => r == l + 1 && self.word[l] == *term,
+        }
+    }
+
+    /// Returns whether the first `parsed` symbols of the rule's body derive the span `l..r`.
+    fn derives_prefix(&mut self, rule_idx: usize, parsed: usize, l: usize, r: usize) -> bool {
+        if parsed == 0 {
+            return l == r;
+        }
+        let key = (rule_idx, parsed, l, r);
+        if let Some(&is_derived) = self.prefixes.get(&key) {
+            return is_derived;
+        }
+        let rules = self.rules;
+        let last_symbol = &rules[rule_idx].body[parsed - 1];
+        let is_derived = (l..=r).any(|k| {
+            self.derives_prefix(rule_idx, parsed - 1, l, k)
+                && self.derives_symbol(last_symbol, k, r)
+        });
+        self.prefixes.insert(key, is_derived);
+        is_derived
+    }
+}
+
+/// An iterator over the parse trees of a forest, in order of the number of rules applied in them,
+/// returned by [`ParseForest::into_parse_trees`].
+pub struct ParseTrees<'a, T: Term>(CheapestParseTrees<'a, T>);
+
+impl<T: Term> Iterator for ParseTrees<'_, T> {
+    type Item = ParseTree<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(parse_tree, _)| parse_tree)
+    }
+}
+
+/// An iterator over the parse trees of a forest along with their costs, in order of their costs,
+/// returned by [`ParseForest::into_cheapest_parse_trees`].
+///
+/// On forests without cycles, the trees are found with the lazy algorithm of Huang and Chiang
+/// (2005, Algorithm 3). Every node keeps the derivations of its span found so far, cheapest first,
+/// each given by a packed node and the ranks of the derivations of its children, along with a heap
+/// of candidates for the next one. The next derivation of a node is only looked for when a node
+/// above it asks for it, so that finding the first `k` trees takes time linear in the size of the
+/// forest, plus `k log k` times the size of the largest of the trees.
+///
+/// Forests with cycles are searched best-first instead, as the derivations of a node there may go
+/// through the node itself. Parse trees are built from the root down and from left to right, one
+/// choice of a packed node at a time. The partial tree to work on next is always the one with the
+/// lowest cost of the rules applied in it plus the lowest costs of its pending nodes, so complete
+/// trees come out in order. Partial trees going around a cycle of the forest are dropped, which
+/// keeps the number of trees finite. Every partial tree holds a copy of the choices made in it so
+/// far, and there may be exponentially many of them in the size of the trees.
+pub struct CheapestParseTrees<'a, T: Term> {
+    forest: ParseForest<'a, T>,
+    /// For every node, the cost of each of its packed nodes.
+    costs: Vec<Vec<f64>>,
+    search: Search,
+}
+
+enum Search {
+    Lazy(LazyKBest),
+    BestFirst(BestFirst),
+}
+
+struct LazyKBest {
+    /// For every node, the derivations of its span found so far, cheapest first.
+    derivations: Vec<Vec<Derivation>>,
+    /// For every node, the candidates for its next derivation, or `None` before it is first asked
+    /// for one.
+    candidates: Vec<Option<BinaryHeap<Reverse<Derivation>>>>,
+    /// For every node, the packed nodes and ranks of the derivations which have been candidates,
+    /// so that none of them is found twice.
+    seen: Vec<FxHashSet<(usize, [usize; 2])>>,
+    /// The number of derivations of the root returned so far.
+    n_returned: usize,
+}
+
+/// A derivation of the span of a node, by one of its packed nodes along with the ranks of the
+/// derivations of its left and right children, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Derivation {
+    cost: Cost,
+    packed_idx: usize,
+    ranks: [usize; 2],
+}
+
+struct BestFirst {
+    min_costs: Vec<f64>,
+    /// The estimated costs of the partial trees along with their indices. Ties are broken in
+    /// favour of the partial tree found last, which completes trees depth-first rather than
+    /// breadth-first.
+    queue: BinaryHeap<(Reverse<Cost>, usize)>,
+    partial_trees: Vec<Option<PartialTree>>,
+}
+
+#[derive(Clone)]
+struct PartialTree {
+    /// The cost of the rules applied in the tree so far.
+    cost: f64,
+    /// The packed nodes chosen so far, from the root down and from left to right.
+    choices: Vec<usize>,
+    /// The nodes left to choose a packed node for, the next one last, along with their ancestors.
+    pending: Vec<(usize, Option<Rc<Ancestor>>)>,
+}
+
+struct Ancestor {
+    node: usize,
+    parent: Option<Rc<Ancestor>>,
+}
+
+/// A cost, ordered so that it can be used in a [`BinaryHeap`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Cost(pub(super) f64);
+
+impl Eq for Cost {}
+
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<T: Term> ParseForest<'_, T> {
+    /// Returns the parse tree whose packed nodes are chosen in the given order, from the root down
+    /// and from left to right.
+    fn build_from_choices(&self, choices: Vec<usize>) -> ParseTree<T> {
+        let mut choices = choices.into_iter();
+        let mut children = Vec::new();
+        self.build_with_choices(self.root, &mut |_| choices.next().unwrap(), &mut children);
+        let Some(ParsedSymbol::Var(parse_tree)) = children.pop() else {
+            panic!("Expected root to be labelled with a variable");
+        };
+        *parse_tree
+    }
+}
+
+impl LazyKBest {
+    /// Returns the `k`th cheapest derivation of the span of the given node, counting from zero,
+    /// or `None` if it has fewer derivations.
+    fn kth<T: Term>(
+        &mut self,
+        forest: &ParseForest<T>,
+        costs: &[Vec<f64>],
+        idx: usize,
+        k: usize,
+    ) -> Option<Derivation> {
+        let is_term = matches!(
+            forest.nodes[idx].label,
+            ForestNodeLabel::Symbol(Symbol::Term(_))
+        );
+        if self.candidates[idx].is_none() {
+            self.candidates[idx] = Some(BinaryHeap::new());
+            if is_term {
+                self.push(idx, 0, [0, 0], Cost(0.0));
+            }
+            for packed_idx in 0..forest.nodes[idx].packed_nodes.len() {
+                self.push_candidate(forest, costs, idx, packed_idx, [0, 0]);
+            }
+        }
+        while self.derivations[idx].len() <= k {
+            if let Some(&last) = self.derivations[idx].last().filter(|_| !is_term) {
+                for i in 0..2 {
+                    let mut ranks = last.ranks;
+                    ranks[i] += 1;
+                    self.push_candidate(forest, costs, idx, last.packed_idx, ranks);
+                }
+            }
+            let Some(Reverse(derivation)) = self.candidates[idx].as_mut().unwrap().pop() else {
+                break;
+            };
+            self.derivations[idx].push(derivation);
+        }
+        self.derivations[idx].get(k).copied()
+    }
+
+    /// Makes the derivation by the given packed node and ranks of the derivations of its children
+    /// a candidate, unless it has been one before or a child has fewer derivations.
+    fn push_candidate<T: Term>(
+        &mut self,
+        forest: &ParseForest<T>,
+        costs: &[Vec<f64>],
+        idx: usize,
+        packed_idx: usize,
+        ranks: [usize; 2],
+    ) {
+        if self.seen[idx].contains(&(packed_idx, ranks)) {
+            return;
+        }
+        let packed_node = forest.nodes[idx].packed_nodes[packed_idx];
+        let mut cost = costs[idx][packed_idx];
+        for (child, rank) in [packed_node.left, packed_node.right].into_iter().zip(ranks) {
+            match child {
+                Some(child) => match self.kth(forest, costs, child, rank) {
+                    Some(derivation) => cost += derivation.cost.0,
+                    None => return,
+                },
+                None if rank > 0 => return,
+                None => {}
+            }
+        }
+        self.push(idx, packed_idx, ranks, Cost(cost));
+    }
+
+    fn push(&mut self, idx: usize, packed_idx: usize, ranks: [usize; 2], cost: Cost) {
+        self.seen[idx].insert((packed_idx, ranks));
+        self.candidates[idx]
+            .as_mut()
+            .unwrap()
+            .push(Reverse(Derivation {
+                cost,
+                packed_idx,
+                ranks,
+            }));
+    }
+
+    /// Pushes the packed nodes chosen in the `k`th derivation of the given node, from the node
+    /// down and from left to right, onto `choices`.
+    fn choices<T: Term>(
+        &self,
+        forest: &ParseForest<T>,
+        idx: usize,
+        k: usize,
+        choices: &mut Vec<usize>,
+    ) {
+        if let ForestNodeLabel::Symbol(Symbol::Term(_)) = forest.nodes[idx].label {
+            return;
+        }
+        let derivation = self.derivations[idx][k];
+        choices.push(derivation.packed_idx);
+        let packed_node = forest.nodes[idx].packed_nodes[derivation.packed_idx];
+        for (child, rank) in [packed_node.left, packed_node.right]
+            .into_iter()
+            .zip(derivation.ranks)
+        {
+            if let Some(child) = child {
+                self.choices(forest, child, rank, choices);
+            }
+        }
+    }
+}
+
+impl BestFirst {
+    fn push(&mut self, partial_tree: PartialTree) {
+        let estimate = partial_tree.cost
+            + partial_tree
+                .pending
+                .iter()
+                .map(|&(idx, _)| self.min_costs[idx])
+                .sum::<f64>();
+        self.queue
+            .push((Reverse(Cost(estimate)), self.partial_trees.len()));
+        self.partial_trees.push(Some(partial_tree));
+    }
+
+    fn next<T: Term>(
+        &mut self,
+        forest: &ParseForest<T>,
+        costs: &[Vec<f64>],
+    ) -> Option<(ParseTree<T>, f64)> {
+        while let Some((_, partial_idx)) = self.queue.pop() {
+            let mut partial_tree = self.partial_trees[partial_idx].take().unwrap();
+            while let Some(&(idx, _)) = partial_tree.pending.last() {
+                if let ForestNodeLabel::Symbol(Symbol::Term(_)) = forest.nodes[idx].label {
+                    partial_tree.pending.pop();
+                } else {
+                    break;
+                }
+            }
+
+            let Some((idx, parent)) = partial_tree.pending.pop() else {
+                let parse_tree = forest.build_from_choices(partial_tree.choices);
+                return Some((parse_tree, partial_tree.cost));
+            };
+
+            let mut ancestor = parent.as_deref();
+            while let Some(Ancestor { node, parent }) = ancestor {
+                if *node == idx {
+                    break;
+                }
+                ancestor = parent.as_deref();
+            }
+            if ancestor.is_some() {
+                continue;
+            }
+
+            let parent = Some(Rc::new(Ancestor { node: idx, parent }));
+            for (packed_idx, packed_node) in forest.nodes[idx].packed_nodes.iter().enumerate() {
+                let mut next_partial_tree = partial_tree.clone();
+                next_partial_tree.cost += costs[idx][packed_idx];
+                next_partial_tree.choices.push(packed_idx);
+                for child in [packed_node.right, packed_node.left].into_iter().flatten() {
+                    next_partial_tree.pending.push((child, parent.clone()));
+                }
+                self.push(next_partial_tree);
+            }
+        }
+        None
+    }
+}
+
+impl<T: Term> Iterator for CheapestParseTrees<'_, T> {
+    type Item = (ParseTree<T>, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.search {
+            Search::Lazy(lazy) => {
+                let root = self.forest.root;
+                let derivation = lazy.kth(&self.forest, &self.costs, root, lazy.n_returned)?;
+                let mut choices = Vec::new();
+                lazy.choices(&self.forest, root, lazy.n_returned, &mut choices);
+                lazy.n_returned += 1;
+                let parse_tree = self.forest.build_from_choices(choices);
+                Some((parse_tree, derivation.cost.0))
+            }
+            Search::BestFirst(best_first) => best_first.next(&self.forest, &self.costs),
+        }
+    }
+}
+
+impl<T: Term> ForestNode<T> {
+    pub fn label(&self) -> &ForestNodeLabel<T> {
+        &self.label
+    }
+
+    /// The position in the word at which the span of this node starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The position in the word at which the span of this node ends, exclusive.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn packed_nodes(&self) -> &[PackedNode] {
+        &self.packed_nodes
+    }
+}
+
+impl PackedNode {
+    pub fn rule_idx(&self) -> usize {
+        self.rule_idx
+    }
+
+    /// The position in the word at which the span of the right child starts.
+    pub fn split(&self) -> usize {
+        self.split
+    }
+
+    pub fn left(&self) -> Option<usize> {
+        self.left
+    }
+
+    pub fn right(&self) -> Option<usize> {
+        self.right
+    }
+}
@@ -0,0 +1,477 @@
+use std::{collections::BTreeSet, rc::Rc};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{ForestNode, ForestNodeLabel, PackedNode, ParseError, ParseForest, ParseTree, Parser};
+
+/// A generalized LL parser following Scott and Johnstone (2010, 2013).
+///
+/// The parser works like a recursive descent parser which tries every rule of a variable whose
+/// body may begin with the next terminal. Whenever it would call a variable, it records where to
+/// return to in a graph-structured call stack instead, and whenever it would have to choose between
+/// rules, it records a descriptor for each of them to be processed later. A descriptor holds a
+/// position in the body of a rule, the call stack node to return to, the position in the word and
+/// the parse forest node of the part of the body recognized so far, which is all a recursive
+/// descent parser needs to resume. Calls to the same variable at the same position share their
+/// call stack node, which is what makes left recursion terminate.
+pub struct GllParser<T: Term> {
+    start_var: Var,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    rules_by_var: Vec<Vec<usize>>,
+    /// For every rule, the terminals which may come next when the rule is chosen, with `None`
+    /// standing for the end of the word.
+    select_sets: Vec<BTreeSet<Option<T>>>,
+}
+
+impl<T: Term> GllParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        // Rules with variables deriving no word are never called, so that the furthest position
+        // reached is always the end of the beginning of a word in the language.
+        let rules_by_var = cfg.productive_rules_by_var();
+
+        let nullable_rules = cfg.nullable_rules();
+        let first_sets = cfg.first_sets();
+        let follow_sets = cfg.follow_sets(&first_sets);
+        let select_sets = cfg
+            .rules
+            .iter()
+            .map(|rule| {
+                let mut select_set = BTreeSet::new();
+                for symbol in rule.body.iter() {
+                    match symbol {
+                        Symbol::Term(term) => {
+                            select_set.insert(Some(term.clone()));
+                            return select_set;
+                        }
+                        Symbol::Var(var) => {
+                            select_set.extend(first_sets[var.0].iter().cloned().map(Some));
+                            if nullable_rules[var.0].is_none() {
+                                return select_set;
+                            }
+                        }
+                    }
+                }
+                select_set.extend(follow_sets[rule.head.0].iter().cloned());
+                select_set
+            })
+            .collect();
+
+        let Cfg {
+            start_var,
+            var_names,
+            rules,
+        } = cfg;
+
+        Self {
+            start_var,
+            var_names,
+            rules,
+            rules_by_var,
+            select_sets,
+        }
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for GllParser<T> {
+    type TermType = T;
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        let mut gll = Gll::new(self, &word);
+        gll.run();
+        let Some(root) = gll.root() else {
+            return Err(gll.parse_error());
+        };
+        let forest = ParseForest {
+            var_names: &self.var_names,
+            nodes: gll.forest_nodes,
+            root,
+        };
+        Ok(forest.pruned().parse_tree())
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let mut gll = Gll::new(self, &word);
+        gll.run();
+        let root = gll.root()?;
+        let forest = ParseForest {
+            var_names: &self.var_names,
+            nodes: gll.forest_nodes,
+            root,
+        };
+        Some(forest.pruned())
+    }
+
+    /// The word is only run over once, also choosing the rules which may derive the empty word at
+    /// the end of a prefix whatever comes next.
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let mut gll = Gll::new(self, &word);
+        gll.is_prefix = true;
+        gll.run();
+        let root = gll.longest_prefix?;
+        let end = gll.forest_nodes[root].end;
+        let forest = ParseForest {
+            var_names: &self.var_names,
+            nodes: gll.forest_nodes,
+            root,
+        };
+        Some((forest.pruned().parse_tree(), end))
+    }
+}
+
+/// A position in the body of a rule, as a pair of the rule and the number of symbols before it.
+type Slot = (usize, usize);
+
+struct Gll<'a, T: Term> {
+    parser: &'a GllParser<T>,
+    word: &'a [T],
+    descriptors: Vec<Descriptor>,
+    seen_descriptors: FxHashSet<Descriptor>,
+    stack_nodes: Vec<StackNode>,
+    stack_node_indices: FxHashMap<(Slot, usize), usize>,
+    stack_edges: FxHashSet<(usize, Option<usize>, usize)>,
+    forest_nodes: Vec<ForestNode<T>>,
+    forest_node_indices: FxHashMap<(NodeKey, usize, usize), usize>,
+    packed_nodes: FxHashSet<(usize, usize, usize)>,
+    /// The furthest position in the word reached so far, and the descriptors which reached it.
+    furthest: usize,
+    furthest_descriptors: Vec<Descriptor>,
+    /// Whether the descriptors reaching the furthest position are being resumed again to find out
+    /// what was expected there, along with what was found.
+    is_expecting: bool,
+    expected: BTreeSet<Option<T>>,
+    vars: Vec<(usize, Var)>,
+    /// Whether every prefix of the word is being parsed, along with the forest node of the start
+    /// variable deriving the longest one so far.
+    is_prefix: bool,
+    longest_prefix: Option<usize>,
+}
+
+/// The state of a recursive descent parser about to resume at `slot`: `node` is the call stack
+/// node to return to, `i` is the position in the word, and `forest_node` is the forest node of
+/// the symbols of the body before the slot, if there are any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Descriptor {
+    slot: Slot,
+    node: usize,
+    i: usize,
+    forest_node: Option<usize>,
+}
+
+/// A node of the call stack, standing for the calls to a variable at some position made from
+/// `slot`, the position right after the variable in the body of a rule. The root has no slot.
+#[derive(Debug)]
+struct StackNode {
+    slot: Option<Slot>,
+    /// The nodes of the calls to return to afterwards, along with the forest nodes of the symbols
+    /// recognized before the call.
+    edges: Vec<(Option<usize>, usize)>,
+    /// The forest nodes of the variable for every return from the calls so far.
+    popped: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NodeKey {
+    Var(Var),
+    Term,
+    Intermediate(usize, usize),
+}
+
+impl<'a, T: Term> Gll<'a, T> {
+    fn new(parser: &'a GllParser<T>, word: &'a [T]) -> Self {
+        Self {
+            parser,
+            word,
+            descriptors: Vec::new(),
+            seen_descriptors: FxHashSet::default(),
+            stack_nodes: vec![StackNode {
+                slot: None,
+                edges: Vec::new(),
+                popped: Vec::new(),
+            }],
+            stack_node_indices: FxHashMap::default(),
+            stack_edges: FxHashSet::default(),
+            forest_nodes: Vec::new(),
+            forest_node_indices: FxHashMap::default(),
+            packed_nodes: FxHashSet::default(),
+            furthest: 0,
+            furthest_descriptors: Vec::new(),
+            is_expecting: false,
+            expected: BTreeSet::new(),
+            vars: Vec::new(),
+            is_prefix: false,
+            longest_prefix: None,
+        }
+    }
+
+    fn run(&mut self) {
+        self.call(self.parser.start_var, 0, 0);
+
+        while let Some(descriptor) = self.descriptors.pop() {
+            self.resume(descriptor);
+        }
+    }
+
+    /// Returns the forest node of the start variable deriving the whole word, if it does.
+    fn root(&self) -> Option<usize> {
+        let key = (NodeKey::Var(self.parser.start_var), 0, self.word.len());
+        self.forest_node_indices.get(&key).copied()
+    }
+
+    /// Makes the error of the word going wrong at the furthest position reached.
+    ///
+    /// The descriptors which reached the position are resumed again, calling every rule there
+    /// regardless of the next terminal and stopping at the terminals instead of matching them.
+    /// The terminals stopped at were expected, and the variables being recognized are the heads of
+    /// the rules gone through at the position which started before it.
+    fn parse_error(mut self) -> ParseError<T> {
+        self.is_expecting = true;
+        let i = self.furthest;
+        if i == 0 {
+            self.call(self.parser.start_var, 0, 0);
+        }
+        self.descriptors
+            .extend(std::mem::take(&mut self.furthest_descriptors));
+        while let Some(descriptor) = self.descriptors.pop() {
+            self.resume(descriptor);
+        }
+
+        let start_var = NodeKey::Var(self.parser.start_var);
+        if self.forest_node_indices.contains_key(&(start_var, 0, i)) {
+            self.expected.insert(None);
+        }
+        ParseError::new(
+            self.word,
+            i,
+            self.expected,
+            self.vars,
+            &self.parser.var_names,
+        )
+    }
+
+    /// Adds a descriptor for every rule of the variable that may apply at position `i`.
+    fn call(&mut self, var: Var, node: usize, i: usize) {
+        let lookahead = self.word.get(i).cloned();
+        let is_expecting = self.is_expecting && i == self.furthest;
+        for &rule_idx in self.parser.rules_by_var[var.0].iter() {
+            let select_set = &self.parser.select_sets[rule_idx];
+            if is_expecting
+                || select_set.contains(&lookahead)
+                || (self.is_prefix && select_set.contains(&None))
+            {
+                self.add_descriptor((rule_idx, 0), node, i, None);
+            }
+        }
+    }
+
+    /// Keeps track of the descriptors reaching the furthest position.
+    fn reach(&mut self, i: usize, descriptor: Descriptor) {
+        if i > self.furthest {
+            self.furthest = i;
+            self.furthest_descriptors.clear();
+        }
+        if i == self.furthest && !self.is_expecting {
+            self.furthest_descriptors.push(descriptor);
+        }
+    }
+
+    /// Goes through the body of a rule from the given slot like a recursive descent parser would,
+    /// until the body is recognized, a terminal fails to match, or a variable has to be called.
+    fn resume(&mut self, descriptor: Descriptor) {
+        let Descriptor {
+            slot: (rule_idx, mut parsed),
+            node,
+            mut i,
+            mut forest_node,
+        } = descriptor;
+        let parser = self.parser;
+        let rule = &parser.rules[rule_idx];
+        let body = &rule.body;
+
+        while let Some(symbol) = body.get(parsed) {
+            if self.is_expecting && i == self.furthest {
+                if let Some(forest_node) = forest_node {
+                    let start = self.forest_nodes[forest_node].start;
+                    if start < i {
+                        self.vars.push((start, rule.head));
+                    }
+                }
+                if let Symbol::Term(term) = symbol {
+                    self.expected.insert(Some(term.clone()));
+                    return;
+                }
+            }
+            match symbol {
+                Symbol::Term(term) => {
+                    if self.word.get(i) != Some(term) {
+                        self.reach(i, descriptor);
+                        return;
+                    }
+                    let term_node = self.forest_node(NodeKey::Term, i, i + 1, || {
+                        ForestNodeLabel::Symbol(Symbol::Term(term.clone()))
+                    });
+                    i += 1;
+                    parsed += 1;
+                    forest_node =
+                        Some(self.prefix_node((rule_idx, parsed), forest_node, term_node));
+                }
+                Symbol::Var(var) => {
+                    self.reach(i, descriptor);
+                    let callee = self.push((rule_idx, parsed + 1), node, i, forest_node);
+                    self.call(*var, callee, i);
+                    return;
+                }
+            }
+        }
+        self.reach(i, descriptor);
+
+        let forest_node = match forest_node {
+            Some(forest_node) => forest_node,
+            None => {
+                let head = parser.rules[rule_idx].head;
+                let forest_node = self.forest_node(NodeKey::Var(head), i, i, || {
+                    ForestNodeLabel::Symbol(Symbol::Var(head))
+                });
+                self.add_packed_node(forest_node, rule_idx, i, None, None);
+                forest_node
+            }
+        };
+        self.pop(node, forest_node);
+    }
+
+    /// Records a call from `slot` at position `i`, to return to `node` afterwards, and returns the
+    /// call stack node of the call. If the call has already returned, it is resumed right away for
+    /// every return so far.
+    fn push(&mut self, slot: Slot, node: usize, i: usize, forest_node: Option<usize>) -> usize {
+        let n_stack_nodes = self.stack_nodes.len();
+        let callee = *self
+            .stack_node_indices
+            .entry((slot, i))
+            .or_insert(n_stack_nodes);
+        if callee == n_stack_nodes {
+            self.stack_nodes.push(StackNode {
+                slot: Some(slot),
+                edges: Vec::new(),
+                popped: Vec::new(),
+            });
+        }
+
+        if self.stack_edges.insert((callee, forest_node, node)) {
+            self.stack_nodes[callee].edges.push((forest_node, node));
+            for k in 0..self.stack_nodes[callee].popped.len() {
+                let popped = self.stack_nodes[callee].popped[k];
+                let prefix_node = self.prefix_node(slot, forest_node, popped);
+                let j = self.forest_nodes[popped].end;
+                self.add_descriptor(slot, node, j, Some(prefix_node));
+            }
+        }
+
+        callee
+    }
+
+    /// Returns from the calls of the given call stack node, having recognized the variable called
+    /// with the given forest node.
+    fn pop(&mut self, node: usize, forest_node: usize) {
+        let Some(slot) = self.stack_nodes[node].slot else {
+            let end = self.forest_nodes[forest_node].end;
+            if self
+                .longest_prefix
+                .is_none_or(|root| self.forest_nodes[root].end < end)
+            {
+                self.longest_prefix = Some(forest_node);
+            }
+            return;
+        };
+        self.stack_nodes[node].popped.push(forest_node);
+        let j = self.forest_nodes[forest_node].end;
+        for k in 0..self.stack_nodes[node].edges.len() {
+            let (left, caller) = self.stack_nodes[node].edges[k];
+            let prefix_node = self.prefix_node(slot, left, forest_node);
+            self.add_descriptor(slot, caller, j, Some(prefix_node));
+        }
+    }
+
+    fn add_descriptor(&mut self, slot: Slot, node: usize, i: usize, forest_node: Option<usize>) {
+        let descriptor = Descriptor {
+            slot,
+            node,
+            i,
+            forest_node,
+        };
+        if self.seen_descriptors.insert(descriptor) {
+            self.descriptors.push(descriptor);
+        }
+    }
+
+    /// Returns the forest node of the symbols of a rule's body before the given slot, derived as
+    /// the ones of `left` followed by the last one of `right`.
+    fn prefix_node(
+        &mut self,
+        (rule_idx, parsed): Slot,
+        left: Option<usize>,
+        right: usize,
+    ) -> usize {
+        let body_len = self.parser.rules[rule_idx].body.len();
+        if parsed == 1 && parsed < body_len {
+            return right;
+        }
+
+        let (split, end) = (self.forest_nodes[right].start, self.forest_nodes[right].end);
+        let start = left.map_or(split, |left| self.forest_nodes[left].start);
+        let forest_node = if parsed == body_len {
+            let head = self.parser.rules[rule_idx].head;
+            self.forest_node(NodeKey::Var(head), start, end, || {
+                ForestNodeLabel::Symbol(Symbol::Var(head))
+            })
+        } else {
+            self.forest_node(NodeKey::Intermediate(rule_idx, parsed), start, end, || {
+                ForestNodeLabel::Intermediate { rule_idx, parsed }
+            })
+        };
+        self.add_packed_node(forest_node, rule_idx, split, left, Some(right));
+        forest_node
+    }
+
+    fn forest_node(
+        &mut self,
+        key: NodeKey,
+        start: usize,
+        end: usize,
+        label: impl FnOnce() -> ForestNodeLabel<T>,
+    ) -> usize {
+        *self
+            .forest_node_indices
+            .entry((key, start, end))
+            .or_insert_with(|| {
+                self.forest_nodes.push(ForestNode {
+                    label: label(),
+                    start,
+                    end,
+                    packed_nodes: Vec::new(),
+                });
+                self.forest_nodes.len() - 1
+            })
+    }
+
+    fn add_packed_node(
+        &mut self,
+        forest_node: usize,
+        rule_idx: usize,
+        split: usize,
+        left: Option<usize>,
+        right: Option<usize>,
+    ) {
+        if self.packed_nodes.insert((forest_node, rule_idx, split)) {
+            self.forest_nodes[forest_node]
+                .packed_nodes
+                .push(PackedNode {
+                    rule_idx,
+                    split,
+                    left,
+                    right,
+                });
+        }
+    }
+}
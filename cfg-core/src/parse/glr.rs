@@ -0,0 +1,627 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    rc::Rc,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{
+    forest::NodeKey,
+    lr::{Item, Lr0Automaton},
+    ForestNode, ForestNodeLabel, PackedNode, ParseError, ParseForest, ParseTree, Parser,
+};
+
+/// A generalized LR parser following the RNGLR algorithm of Scott and Johnstone (2006).
+///
+/// The parser is driven by the SLR(1) table of the LR(0) automaton of the grammar. Wherever the
+/// table has conflicts, the stack is split, and the stacks are kept merged in a graph-structured
+/// stack. Rules with nullable suffixes are reduced as soon as the rest of their body is recognized,
+/// which is what makes the algorithm correct in the presence of rules deriving the empty word.
+///
+/// The parse forest is built along the way, as in the RNGLR algorithm: every path along which a
+/// reduction is carried out adds a packed node to the forest node of the rule's head, and the
+/// nullable variables deriving the empty word get their forests where they are first needed.
+///
+/// On deterministic parts of the grammar, only one stack is kept, so parsing takes linear time.
+pub struct GlrParser<T: Term> {
+    start_var: Var,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    /// For every variable, its rules whose body only has nullable variables, by which it derives
+    /// the empty word.
+    empty_rules: Vec<Vec<usize>>,
+    /// The terminals of the grammar, numbered so that the tables below can be indexed by them. The
+    /// end of the word is numbered after the last terminal.
+    term_ids: HashMap<T, usize>,
+    /// For every state and terminal, the state to shift to, if any.
+    shifts: Vec<Vec<Option<usize>>>,
+    /// For every state and variable, the state to go to after reducing to the variable, if any.
+    gotos: Vec<Vec<Option<usize>>>,
+    /// For every state and lookahead, the reductions to carry out as pairs of a rule and the number
+    /// of symbols of its body to pop off the stack. After the end of the word comes one more
+    /// lookahead standing for any terminal, which is used for terminals not in the grammar and to
+    /// find out what was expected where a word goes wrong.
+    reductions: Vec<Vec<Vec<(usize, usize)>>>,
+    accepting_state: Option<usize>,
+    /// For every state, its items whose rule has been recognized partway, from which the variables
+    /// being recognized are found when a word goes wrong.
+    partial_items: Vec<Vec<Item>>,
+}
+
+impl<T: Term> GlrParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        // Rules with variables deriving no word are left out of the automaton, so that every path
+        // through it spells out the beginning of a word in the language.
+        let automaton = Lr0Automaton::of_rules(&cfg, &cfg.productive_rules_by_var());
+        let nullable_rules = cfg.nullable_rules();
+        let is_nullable = |symbol: &Symbol<T>| match symbol {
+            Symbol::Var(var) => nullable_rules[var.0].is_some(),
+            Symbol::Term(_) => false,
+        };
+        let mut empty_rules = vec![Vec::new(); cfg.n_vars()];
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            if rule.body.iter().all(is_nullable) {
+                empty_rules[rule.head.0].push(rule_idx);
+            }
+        }
+        let follow_sets = cfg.follow_sets(&cfg.first_sets());
+
+        let mut term_ids = HashMap::new();
+        for rule in cfg.rules.iter() {
+            for symbol in rule.body.iter() {
+                if let Symbol::Term(term) = symbol {
+                    let n_terms = term_ids.len();
+                    term_ids.entry(term.clone()).or_insert(n_terms);
+                }
+            }
+        }
+        let n_terms = term_ids.len();
+        let lookahead_id = |lookahead: &Option<T>| match lookahead {
+            Some(term) => term_ids[term],
+            None => n_terms,
+        };
+
+        let n_states = automaton.n_states();
+        let mut shifts = vec![vec![None; n_terms]; n_states];
+        let mut gotos = vec![vec![None; cfg.n_vars()]; n_states];
+        let mut reductions = vec![vec![Vec::new(); n_terms + 2]; n_states];
+        let mut partial_items = vec![Vec::new(); n_states];
+        for state in 0..n_states {
+            for (term, &next_state) in automaton.term_gotos[state].iter() {
+                shifts[state][term_ids[term]] = Some(next_state);
+            }
+            for (var, &next_state) in automaton.var_gotos[state].iter() {
+                gotos[state][var.0] = Some(next_state);
+            }
+        }
+        for (state, items) in automaton.items.iter().enumerate() {
+            for item in items {
+                let Some(rule) = cfg.rules.get(item.rule_idx) else {
+                    continue;
+                };
+                if 0 < item.parsed && item.parsed < rule.body.len() {
+                    partial_items[state].push(*item);
+                }
+                if !rule.body[item.parsed..].iter().all(is_nullable) {
+                    continue;
+                }
+                for lookahead in follow_sets[rule.head.0].iter() {
+                    reductions[state][lookahead_id(lookahead)].push((item.rule_idx, item.parsed));
+                }
+                if !follow_sets[rule.head.0].is_empty() {
+                    reductions[state][n_terms + 1].push((item.rule_idx, item.parsed));
+                }
+            }
+        }
+
+        let accepting_state = gotos[0][cfg.start_var.0];
+
+        let Cfg {
+            start_var,
+            var_names,
+            rules,
+        } = cfg;
+
+        Self {
+            start_var,
+            var_names,
+            rules,
+            empty_rules,
+            term_ids,
+            shifts,
+            gotos,
+            reductions,
+            accepting_state,
+            partial_items,
+        }
+    }
+
+    /// Runs the parser over the word for as long as the stack has nodes left, and returns the
+    /// stack along with the last level reached. With `is_prefix`, the reductions on the end of the
+    /// word are carried out at every level as well, so that the stack keeps track of the longest
+    /// prefix of the word in the language.
+    fn run(&self, word: &[T], is_prefix: bool) -> (Gss<'_, T>, usize) {
+        // A terminal which does not occur in the grammar cannot be shifted, but the reductions
+        // before it are carried out on any lookahead.
+        let n_terms = self.term_ids.len();
+        let mut lookaheads: Vec<_> = word
+            .iter()
+            .map(|term| self.term_ids.get(term).copied().unwrap_or(n_terms + 1))
+            .collect();
+        lookaheads.push(n_terms);
+
+        let mut stack = Gss {
+            parser: self,
+            lookaheads,
+            nodes: vec![GssNode {
+                state: 0,
+                level: 0,
+                edges: Vec::new(),
+            }],
+            forest_nodes: Vec::new(),
+            forest_node_indices: FxHashMap::default(),
+            packed_nodes: FxHashSet::default(),
+            empty_nodes: FxHashSet::default(),
+            level_nodes: FxHashMap::from_iter([(0, 0)]),
+            pending_reductions: Vec::new(),
+            pending_shifts: Vec::new(),
+            is_prefix,
+            longest_prefix: None,
+        };
+        stack.add_actions(0, 0, None);
+
+        for (i, term) in word.iter().enumerate() {
+            stack.reduce(i);
+            if stack.pending_shifts.is_empty() {
+                return (stack, i);
+            }
+            stack.shift(i, term);
+        }
+        stack.reduce(word.len());
+        (stack, word.len())
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for GlrParser<T> {
+    type TermType = T;
+
+    fn test(&self, word: Vec<T>) -> bool {
+        let (stack, i) = self.run(&word, false);
+        i == word.len() && stack.root().is_some()
+    }
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        let (stack, i) = self.run(&word, false);
+        let Some(root) = stack.root().filter(|_| i == word.len()) else {
+            return Err(stack.parse_error(&word, i));
+        };
+        let parse_tree = stack.into_forest(root).pruned().parse_tree();
+        debug_assert_eq!(parse_tree.root_var, self.start_var);
+        Ok(parse_tree)
+    }
+
+    /// The word is only run over once, reducing on the end of the word as well at every level.
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let (stack, _) = self.run(&word, true);
+        let (end, root) = stack.longest_prefix?;
+        Some((stack.into_forest(root).pruned().parse_tree(), end))
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let (stack, i) = self.run(&word, false);
+        let root = stack.root().filter(|_| i == word.len())?;
+        Some(stack.into_forest(root).pruned())
+    }
+}
+
+/// The graph-structured stack, along with the parse forest labelling its edges.
+struct Gss<'a, T: Term> {
+    parser: &'a GlrParser<T>,
+    lookaheads: Vec<usize>,
+    nodes: Vec<GssNode>,
+    /// The nodes of the parse forest, binarised as in [`ParseForest`].
+    forest_nodes: Vec<ForestNode<T>>,
+    forest_node_indices: FxHashMap<NodeKey, usize>,
+    /// The packed nodes added so far, by their parent, rule and split.
+    packed_nodes: FxHashSet<(usize, usize, usize)>,
+    /// The nullable variables and positions whose forest deriving the empty word has been built.
+    empty_nodes: FxHashSet<(Var, usize)>,
+    /// The nodes of the current level by their state.
+    level_nodes: FxHashMap<usize, usize>,
+    pending_reductions: Vec<Reduction>,
+    pending_shifts: Vec<(usize, usize)>,
+    /// Whether the reductions on the end of the word are carried out at every level.
+    is_prefix: bool,
+    /// The last level at which the start variable derived the word up to there, along with its
+    /// forest node.
+    longest_prefix: Option<(usize, usize)>,
+}
+
+#[derive(Debug)]
+struct GssNode {
+    state: usize,
+    level: usize,
+    /// The edges to the nodes below, along with the forest nodes of the symbols between them.
+    edges: Vec<(usize, usize)>,
+}
+
+/// A reduction of `len` symbols of the body of a rule, to be carried out along the paths starting
+/// at a node. If `len` is not zero, the first edge of the paths has already been traversed, and
+/// `label` is its forest node.
+#[derive(Debug, Clone, Copy)]
+struct Reduction {
+    node: usize,
+    rule_idx: usize,
+    len: usize,
+    label: Option<usize>,
+}
+
+impl<'a, T: Term> Gss<'a, T> {
+    /// Returns the forest node of the start variable deriving the word up to the current level, if
+    /// it does.
+    fn root(&self) -> Option<usize> {
+        let &node = self.level_nodes.get(&self.parser.accepting_state?)?;
+        let &(_, root) = self.nodes[node]
+            .edges
+            .iter()
+            .find(|(target, _)| *target == 0)?;
+        Some(root)
+    }
+
+    /// Makes the error of the word going wrong at the current level `i`.
+    ///
+    /// The reductions of the level are carried out again on any lookahead, so that the nodes of the
+    /// level stand for every way of parsing the word up to `i`. The terminals they can shift were
+    /// expected, and the variables being recognized are the heads of the items recognized partway
+    /// in their states, which start where the paths of as many edges as the items have recognized
+    /// symbols lead.
+    fn parse_error(mut self, word: &[T], i: usize) -> ParseError<T> {
+        let parser = self.parser;
+        self.lookaheads[i] = parser.term_ids.len() + 1;
+        let mut nodes: Vec<_> = self.level_nodes.values().copied().collect();
+        for &node in nodes.iter() {
+            self.add_actions(i, node, None);
+            for (below, label) in self.nodes[node].edges.clone() {
+                self.add_edge_reductions(i, self.nodes[node].state, below, label);
+            }
+        }
+        self.reduce(i);
+
+        let mut expected = BTreeSet::new();
+        if self.root().is_some() {
+            expected.insert(None);
+        }
+        let mut vars = Vec::new();
+        let mut lowest_starts = FxHashMap::default();
+        nodes = self.level_nodes.values().copied().collect();
+        for node in nodes {
+            let state = self.nodes[node].state;
+            for (term, &term_id) in parser.term_ids.iter() {
+                if parser.shifts[state][term_id].is_some() {
+                    expected.insert(Some(term.clone()));
+                }
+            }
+            for item in parser.partial_items[state].iter() {
+                let start = self.lowest_start(node, item.parsed, &mut lowest_starts);
+                if start < i {
+                    vars.push((start, parser.rules[item.rule_idx].head));
+                }
+            }
+        }
+        ParseError::new(word, i, expected, vars, &parser.var_names)
+    }
+
+    /// Returns the lowest level reached by the paths of the given length starting at a node.
+    fn lowest_start(
+        &self,
+        node: usize,
+        len: usize,
+        lowest_starts: &mut FxHashMap<(usize, usize), usize>,
+    ) -> usize {
+        if len == 0 {
+            return self.nodes[node].level;
+        }
+        if let Some(&start) = lowest_starts.get(&(node, len)) {
+            return start;
+        }
+        let start = self.nodes[node]
+            .edges
+            .iter()
+            .map(|&(below, _)| self.lowest_start(below, len - 1, lowest_starts))
+            .min()
+            .unwrap_or(usize::MAX);
+        lowest_starts.insert((node, len), start);
+        start
+    }
+
+    /// Schedules the actions of a new node at the given level. If the node was not created by the
+    /// reduction of an empty span, `edge` is its edge to the node below along with its label.
+    fn add_actions(&mut self, i: usize, node: usize, edge: Option<(usize, usize)>) {
+        let state = self.nodes[node].state;
+        let lookahead = self.lookaheads[i];
+        if let Some(&Some(next_state)) = self.parser.shifts[state].get(lookahead) {
+            self.pending_shifts.push((node, next_state));
+        }
+        for (rule_idx, len) in self.reductions(state, i) {
+            if len == 0 {
+                self.pending_reductions.push(Reduction {
+                    node,
+                    rule_idx,
+                    len,
+                    label: None,
+                });
+            }
+        }
+        if let Some((below, label)) = edge {
+            self.add_edge_reductions(i, state, below, label);
+        }
+    }
+
+    /// Schedules the reductions going through a new edge labelled `label`, from a node in the state
+    /// `state` to the node `below`.
+    fn add_edge_reductions(&mut self, i: usize, state: usize, below: usize, label: usize) {
+        for (rule_idx, len) in self.reductions(state, i) {
+            if len != 0 {
+                self.pending_reductions.push(Reduction {
+                    node: below,
+                    rule_idx,
+                    len,
+                    label: Some(label),
+                });
+            }
+        }
+    }
+
+    /// Returns the reductions to carry out in the state at level `i`, along with the ones on the
+    /// end of the word if every prefix of the word is being parsed.
+    fn reductions(&self, state: usize, i: usize) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let reductions = &self.parser.reductions[state];
+        let lookahead = self.lookaheads[i];
+        let end = self.parser.term_ids.len();
+        let end_reductions = match self.is_prefix && lookahead < end {
+            true => &reductions[end][..],
+            false => &[],
+        };
+        reductions[lookahead]
+            .iter()
+            .chain(
+                end_reductions
+                    .iter()
+                    .filter(move |reduction| !reductions[lookahead].contains(reduction)),
+            )
+            .copied()
+    }
+
+    fn reduce(&mut self, i: usize) {
+        while let Some(Reduction {
+            node,
+            rule_idx,
+            len,
+            label,
+        }) = self.pending_reductions.pop()
+        {
+            let parser = self.parser;
+            let rule = &parser.rules[rule_idx];
+            let mut paths = Vec::new();
+            self.find_paths(node, len.saturating_sub(1), &mut Vec::new(), &mut paths);
+
+            for (below, mut children) in paths {
+                let forest_node = if len == 0 {
+                    self.empty_node(rule.head, i)
+                } else {
+                    children.reverse();
+                    children.extend(label);
+                    for symbol in rule.body[len..].iter() {
+                        let Symbol::Var(var) = symbol else {
+                            panic!("Expected nullable suffix to only have variables");
+                        };
+                        children.push(self.empty_node(*var, i));
+                    }
+                    self.rule_node(rule_idx, &children)
+                };
+
+                let below_state = self.nodes[below].state;
+                let state = parser.gotos[below_state][rule.head.0]
+                    .expect("Expected a goto on the head of a reduced rule");
+                match self.level_nodes.get(&state) {
+                    Some(&node) => {
+                        if self.nodes[node]
+                            .edges
+                            .iter()
+                            .any(|(target, _)| *target == below)
+                        {
+                            continue;
+                        }
+                        self.nodes[node].edges.push((below, forest_node));
+                        if len != 0 {
+                            self.add_edge_reductions(i, state, below, forest_node);
+                        }
+                    }
+                    None => {
+                        let node = self.add_node(state, i, below, forest_node);
+                        let edge = (len != 0).then_some((below, forest_node));
+                        self.add_actions(i, node, edge);
+                    }
+                }
+            }
+        }
+        if self.is_prefix {
+            if let Some(root) = self.root() {
+                self.longest_prefix = Some((i, root));
+            }
+        }
+    }
+
+    fn shift(&mut self, i: usize, term: &T) {
+        let forest_node = self.forest_node(NodeKey::Term(i), i, i + 1, || {
+            ForestNodeLabel::Symbol(Symbol::Term(term.clone()))
+        });
+        self.level_nodes.clear();
+
+        for (below, state) in std::mem::take(&mut self.pending_shifts) {
+            match self.level_nodes.get(&state) {
+                Some(&node) => {
+                    self.nodes[node].edges.push((below, forest_node));
+                    self.add_edge_reductions(i + 1, state, below, forest_node);
+                }
+                None => {
+                    let node = self.add_node(state, i + 1, below, forest_node);
+                    self.add_actions(i + 1, node, Some((below, forest_node)));
+                }
+            }
+        }
+    }
+
+    fn add_node(&mut self, state: usize, level: usize, below: usize, label: usize) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(GssNode {
+            state,
+            level,
+            edges: vec![(below, label)],
+        });
+        self.level_nodes.insert(state, node);
+        node
+    }
+
+    /// Returns the forest node of a nullable variable deriving the empty word at position `i`, with
+    /// a packed node for every rule by which it does.
+    fn empty_node(&mut self, var: Var, i: usize) -> usize {
+        let forest_node = self.forest_node(NodeKey::Var(var, i, i), i, i, || {
+            ForestNodeLabel::Symbol(Symbol::Var(var))
+        });
+        if !self.empty_nodes.insert((var, i)) {
+            return forest_node;
+        }
+        let parser = self.parser;
+        for &rule_idx in parser.empty_rules[var.0].iter() {
+            let children: Vec<_> = parser.rules[rule_idx]
+                .body
+                .iter()
+                .map(|symbol| {
+                    let Symbol::Var(var) = symbol else {
+                        panic!(
+                            "Expected body of a nullable rule to only have variables as symbols"
+                        );
+                    };
+                    self.empty_node(*var, i)
+                })
+                .collect();
+            if children.is_empty() {
+                self.add_packed_node(forest_node, rule_idx, i, None, None);
+            } else {
+                self.rule_node(rule_idx, &children);
+            }
+        }
+        forest_node
+    }
+
+    /// Returns the forest node of the head of a rule whose body derives the parts of the word of
+    /// the given forest nodes, one for every symbol.
+    fn rule_node(&mut self, rule_idx: usize, children: &[usize]) -> usize {
+        let mut left = None;
+        for (parsed, &child) in (1..).zip(children) {
+            left = Some(self.prefix_node(rule_idx, parsed, left, child));
+        }
+        left.expect("Expected a rule with a nonempty body")
+    }
+
+    /// Returns the forest node of the first `parsed` symbols of a rule's body, derived as the ones
+    /// of `left` followed by the last one of `right`.
+    fn prefix_node(
+        &mut self,
+        rule_idx: usize,
+        parsed: usize,
+        left: Option<usize>,
+        right: usize,
+    ) -> usize {
+        let rule = &self.parser.rules[rule_idx];
+        if parsed == 1 && parsed < rule.body.len() {
+            return right;
+        }
+
+        let (split, end) = (self.forest_nodes[right].start, self.forest_nodes[right].end);
+        let start = left.map_or(split, |left| self.forest_nodes[left].start);
+        let forest_node = if parsed == rule.body.len() {
+            let head = rule.head;
+            self.forest_node(NodeKey::Var(head, start, end), start, end, || {
+                ForestNodeLabel::Symbol(Symbol::Var(head))
+            })
+        } else {
+            let key = NodeKey::Intermediate(rule_idx, parsed, start, end);
+            self.forest_node(key, start, end, || ForestNodeLabel::Intermediate {
+                rule_idx,
+                parsed,
+            })
+        };
+        self.add_packed_node(forest_node, rule_idx, split, left, Some(right));
+        forest_node
+    }
+
+    fn forest_node(
+        &mut self,
+        key: NodeKey,
+        start: usize,
+        end: usize,
+        label: impl FnOnce() -> ForestNodeLabel<T>,
+    ) -> usize {
+        *self.forest_node_indices.entry(key).or_insert_with(|| {
+            self.forest_nodes.push(ForestNode {
+                label: label(),
+                start,
+                end,
+                packed_nodes: Vec::new(),
+            });
+            self.forest_nodes.len() - 1
+        })
+    }
+
+    fn add_packed_node(
+        &mut self,
+        forest_node: usize,
+        rule_idx: usize,
+        split: usize,
+        left: Option<usize>,
+        right: Option<usize>,
+    ) {
+        if self.packed_nodes.insert((forest_node, rule_idx, split)) {
+            self.forest_nodes[forest_node]
+                .packed_nodes
+                .push(PackedNode {
+                    rule_idx,
+                    split,
+                    left,
+                    right,
+                });
+        }
+    }
+
+    fn into_forest(self, root: usize) -> ParseForest<'a, T> {
+        ParseForest {
+            var_names: &self.parser.var_names,
+            nodes: self.forest_nodes,
+            root,
+        }
+    }
+
+    /// Finds every path of the given length starting at a node, along with the forest nodes
+    /// labelling its edges.
+    fn find_paths(
+        &self,
+        node: usize,
+        len: usize,
+        labels: &mut Vec<usize>,
+        paths: &mut Vec<(usize, Vec<usize>)>,
+    ) {
+        if len == 0 {
+            paths.push((node, labels.clone()));
+            return;
+        }
+        for &(below, label) in self.nodes[node].edges.iter() {
+            labels.push(label);
+            self.find_paths(below, len - 1, labels, paths);
+            labels.pop();
+        }
+    }
+}
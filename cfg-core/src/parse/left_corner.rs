@@ -0,0 +1,474 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    rc::Rc,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{
+    cyk::{contains_bit, insert_bit},
+    earley::get_predictions,
+    forest::SpanForestBuilder,
+    ParseError, ParseForest, ParseTree, ParsedSymbol, Parser,
+};
+
+/// A left-corner chart parser, which recognizes the rules of the grammar bottom-up from their
+/// left corners, but only starts a rule if its head is a left corner of a variable that some rule
+/// expects at that position.
+///
+/// Unlike the Earley parser, it never predicts the rules of a variable before seeing the symbol
+/// they begin with, which saves a lot of work on grammars with many rules beginning with
+/// terminals, such as lexicons.
+pub struct LeftCornerParser<T: Term> {
+    start_var: Var,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    nullable_rules: Vec<Option<usize>>,
+    /// For every variable, the variables which are left corners of it, as a bitset.
+    left_corners: Vec<Vec<u64>>,
+    /// For every terminal, the positions in rule bodies where it is a left corner, i.e., where it
+    /// comes after nullable variables only, as pairs of the rule and the number of symbols before
+    /// it.
+    term_corner_rules: HashMap<T, Vec<(usize, usize)>>,
+    /// The same as above, for every variable.
+    var_corner_rules: Vec<Vec<(usize, usize)>>,
+}
+
+impl<T: Term> LeftCornerParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        let nullable_rules = cfg.nullable_rules();
+        // Rules with variables deriving no word are left out of the tables, as in the Earley
+        // parser, so that every state of the chart can still be completed.
+        let rules_by_var = cfg.productive_rules_by_var();
+
+        let Cfg {
+            start_var,
+            var_names,
+            rules,
+        } = cfg;
+
+        let n_vars = var_names.len();
+
+        let n_words = n_vars.div_ceil(64);
+        let left_corners = get_predictions(&rules, &rules_by_var, &nullable_rules)
+            .into_iter()
+            .map(|predictions| {
+                let mut bits = vec![0; n_words];
+                for var in predictions {
+                    insert_bit(&mut bits, var.0);
+                }
+                bits
+            })
+            .collect();
+
+        let mut term_corner_rules = HashMap::<T, Vec<_>>::new();
+        let mut var_corner_rules = vec![vec![]; n_vars];
+        for &rule_idx in rules_by_var.iter().flatten() {
+            let rule = &rules[rule_idx];
+            for (k, symbol) in rule.body.iter().enumerate() {
+                match symbol {
+                    Symbol::Term(term) => {
+                        term_corner_rules
+                            .entry(term.clone())
+                            .or_default()
+                            .push((rule_idx, k));
+                        break;
+                    }
+                    Symbol::Var(var) => {
+                        var_corner_rules[var.0].push((rule_idx, k));
+                        if nullable_rules[var.0].is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            start_var,
+            var_names,
+            rules,
+            nullable_rules,
+            left_corners,
+            term_corner_rules,
+            var_corner_rules,
+        }
+    }
+
+    fn attempt_parse(&self, word: &[T]) -> Chart {
+        let n = word.len();
+        let n_words = self.var_names.len().div_ceil(64);
+
+        let mut chart = Chart {
+            states: vec![Vec::new(); n + 1],
+            parents: vec![Vec::new(); n + 1],
+            present: [FxHashSet::default(), FxHashSet::default()],
+            waiting: vec![FxHashMap::default(); n + 1],
+            relevant: vec![vec![0; n_words]; n + 1],
+        };
+        self.add_goal(&mut chart, 0, self.start_var);
+
+        for r in 0..=n {
+            let mut i = 0;
+            while i < chart.states[r].len() {
+                let State {
+                    l,
+                    rule_idx,
+                    parsed,
+                } = chart.states[r][i];
+
+                let head = self.rules[rule_idx].head;
+                let body = &self.rules[rule_idx].body;
+
+                match body.get(parsed) {
+                    // Every state spans a non-empty part of the word, so the states waiting on the
+                    // head are all in an earlier column, which is already complete.
+                    None => {
+                        if let Some(waiting) = chart.waiting[l].get(&head).cloned() {
+                            for j in waiting {
+                                let state = State {
+                                    parsed: chart.states[l][j].parsed + 1,
+                                    ..chart.states[l][j]
+                                };
+                                self.add_state(&mut chart, r, state, Parent::Var(i, j));
+                            }
+                        }
+                        for &(rule_idx, k) in self.var_corner_rules[head.0].iter() {
+                            self.start_rule(
+                                &mut chart,
+                                l,
+                                r,
+                                rule_idx,
+                                k,
+                                Parent::LeftCorner(Some(i)),
+                            );
+                        }
+                    }
+                    Some(Symbol::Var(var)) => {
+                        // As in the Earley parser, a nullable variable is skipped over right away.
+                        if self.nullable_rules[var.0].is_some() {
+                            let state = State {
+                                l,
+                                rule_idx,
+                                parsed: parsed + 1,
+                            };
+                            self.add_state(&mut chart, r, state, Parent::Nullable(i));
+                        }
+                    }
+                    Some(Symbol::Term(term)) => {
+                        if word.get(r) == Some(term) {
+                            let state = State {
+                                l,
+                                rule_idx,
+                                parsed: parsed + 1,
+                            };
+                            self.add_state(&mut chart, r + 1, state, Parent::Term(i));
+                        }
+                    }
+                }
+                i += 1;
+            }
+
+            // The variables which may begin at this position are only all known once the column
+            // is complete, so the rules beginning with the next terminal are started last.
+            if let Some(corner_rules) = word
+                .get(r)
+                .and_then(|term| self.term_corner_rules.get(term))
+            {
+                for &(rule_idx, k) in corner_rules.iter() {
+                    self.start_rule(&mut chart, r, r + 1, rule_idx, k, Parent::LeftCorner(None));
+                }
+            }
+            chart.present[r % 2].clear();
+        }
+
+        chart
+    }
+
+    /// Starts recognizing a rule whose body derives the word from `l` to `r` up to and including
+    /// the symbol at index `k`, all symbols before which derive the empty word, if the rule's head
+    /// may begin at `l`.
+    fn start_rule(
+        &self,
+        chart: &mut Chart,
+        l: usize,
+        r: usize,
+        rule_idx: usize,
+        k: usize,
+        parent: Parent,
+    ) {
+        let head = self.rules[rule_idx].head;
+        if !contains_bit(&chart.relevant[l], head.0) {
+            return;
+        }
+        let state = State {
+            l,
+            rule_idx,
+            parsed: k + 1,
+        };
+        self.add_state(chart, r, state, parent);
+    }
+
+    fn add_state(&self, chart: &mut Chart, r: usize, state: State, parent: Parent) {
+        if !chart.present[r % 2].insert(state) {
+            return;
+        }
+        let i = chart.states[r].len();
+        chart.states[r].push(state);
+        chart.parents[r].push(parent);
+        if let Some(Symbol::Var(var)) = self.rules[state.rule_idx].body.get(state.parsed) {
+            if !chart.waiting[r].contains_key(var) {
+                self.add_goal(chart, r, *var);
+            }
+            chart.waiting[r].entry(*var).or_default().push(i);
+        }
+    }
+
+    /// Finds out where the word goes wrong from the last column of the chart with states in it, as
+    /// the Earley parser does. Since the rules beginning with a terminal are only started once it
+    /// is seen, the terminals expected there also include the left corners of the rules whose
+    /// heads may begin there.
+    fn parse_error(&self, word: &[T], chart: &Chart) -> ParseError<T> {
+        let position = (0..=word.len())
+            .rev()
+            .find(|&r| !chart.states[r].is_empty())
+            .unwrap_or(0);
+
+        let mut expected = BTreeSet::new();
+        let mut vars = Vec::new();
+        if position == 0 && self.nullable_rules[self.start_var.0].is_some() {
+            expected.insert(None);
+        }
+        for state in chart.states[position].iter() {
+            let rule = &self.rules[state.rule_idx];
+            match rule.body.get(state.parsed) {
+                Some(Symbol::Term(term)) => {
+                    expected.insert(Some(term.clone()));
+                }
+                None if rule.head == self.start_var && state.l == 0 => {
+                    expected.insert(None);
+                }
+                _ => {}
+            }
+            if state.parsed < rule.body.len() {
+                vars.push((state.l, rule.head));
+            }
+        }
+        for (term, corner_rules) in self.term_corner_rules.iter() {
+            if corner_rules.iter().any(|&(rule_idx, _)| {
+                contains_bit(&chart.relevant[position], self.rules[rule_idx].head.0)
+            }) {
+                expected.insert(Some(term.clone()));
+            }
+        }
+
+        ParseError::new(word, position, expected, vars, &self.var_names)
+    }
+
+    /// Finds the state of a column in which a rule of the start variable spans the whole word up
+    /// to there.
+    fn final_state_pos(&self, chart: &Chart, r: usize) -> Option<usize> {
+        chart.states[r].iter().position(|state| {
+            self.rules[state.rule_idx].head == self.start_var
+                && state.l == 0
+                && state.parsed == self.rules[state.rule_idx].body.len()
+        })
+    }
+
+    /// Marks the left corners of a variable expected at position `r` as the variables which may
+    /// begin there.
+    fn add_goal(&self, chart: &mut Chart, r: usize, var: Var) {
+        for (relevant, left_corners) in chart.relevant[r]
+            .iter_mut()
+            .zip(self.left_corners[var.0].iter())
+        {
+            *relevant |= left_corners;
+        }
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for LeftCornerParser<T> {
+    type TermType = T;
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        let n = word.len();
+        if n == 0 && self.nullable_rules[self.start_var.0].is_some() {
+            return Ok(self.build_empty(self.start_var));
+        }
+
+        let chart = self.attempt_parse(&word);
+
+        let Some(final_state_pos) = self.final_state_pos(&chart, n) else {
+            return Err(self.parse_error(&word, &chart));
+        };
+
+        Ok(ParseTreeBuilder {
+            parser: self,
+            word,
+            chart,
+        }
+        .build(n, final_state_pos))
+    }
+
+    /// Since every column of the chart tells whether the word up to it is in the language, the
+    /// chart is only filled once.
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let chart = self.attempt_parse(&word);
+        let Some((end, final_state_pos)) = (1..=word.len())
+            .rev()
+            .find_map(|r| Some((r, self.final_state_pos(&chart, r)?)))
+        else {
+            // No state spans an empty part of the word.
+            self.nullable_rules[self.start_var.0]?;
+            return Some((self.build_empty(self.start_var), 0));
+        };
+
+        let parse_tree = ParseTreeBuilder {
+            parser: self,
+            word,
+            chart,
+        }
+        .build(end, final_state_pos);
+        Some((parse_tree, end))
+    }
+
+    /// The forest is built from the spans derived by the variables, as found by the completed
+    /// states. Variables derive the empty word exactly when they are nullable, since no state
+    /// spans an empty part of the word.
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let chart = self.attempt_parse(&word);
+        let mut completed = FxHashSet::default();
+        for (r, states) in chart.states.iter().enumerate() {
+            for state in states.iter() {
+                let rule = &self.rules[state.rule_idx];
+                if state.parsed == rule.body.len() {
+                    completed.insert((rule.head, state.l, r));
+                }
+            }
+        }
+
+        SpanForestBuilder::new(&self.rules, self.var_names.len(), &word, |var, l, r| {
+            if l == r {
+                self.nullable_rules[var.0].is_some()
+            } else {
+                completed.contains(&(var, l, r))
+            }
+        })
+        .build(&self.var_names, self.start_var)
+    }
+}
+
+impl<T: Term> LeftCornerParser<T> {
+    fn build_empty(&self, var: Var) -> ParseTree<T> {
+        let rule_idx = self.nullable_rules[var.0].expect("Expected a nullable variable");
+        let children = self.rules[rule_idx]
+            .body
+            .iter()
+            .map(|symbol| {
+                let Symbol::Var(var) = symbol else {
+                    panic!("Expected body of a nullable rule to only have variables as symbols");
+                };
+                ParsedSymbol::Var(Box::new(self.build_empty(*var)))
+            })
+            .collect();
+        ParseTree {
+            root_var: var,
+            root_var_name: self.var_names[var.0].to_owned(),
+            rule_idx,
+            children,
+        }
+    }
+}
+
+struct ParseTreeBuilder<'a, T: Term> {
+    parser: &'a LeftCornerParser<T>,
+    word: Vec<T>,
+    chart: Chart,
+}
+
+impl<T: Term> ParseTreeBuilder<'_, T> {
+    fn build(&self, r: usize, i: usize) -> ParseTree<T> {
+        let state = self.chart.states[r][i];
+        let body = &self.parser.rules[state.rule_idx].body;
+        match &self.chart.parents[r][i] {
+            Parent::LeftCorner(k) => {
+                let root_var = self.parser.rules[state.rule_idx].head;
+                let mut children: Vec<_> = body[..state.parsed - 1]
+                    .iter()
+                    .map(|symbol| {
+                        let Symbol::Var(var) = symbol else {
+                            panic!("Expected only variables before a left corner");
+                        };
+                        ParsedSymbol::Var(Box::new(self.parser.build_empty(*var)))
+                    })
+                    .collect();
+                children.push(match k {
+                    Some(k) => ParsedSymbol::Var(Box::new(self.build(r, *k))),
+                    None => ParsedSymbol::Term(self.word[r - 1].clone()),
+                });
+                ParseTree {
+                    root_var,
+                    root_var_name: self.parser.var_names[root_var.0].to_owned(),
+                    rule_idx: state.rule_idx,
+                    children,
+                }
+            }
+            Parent::Term(k) => {
+                let mut parse_tree = self.build(r - 1, *k);
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Term(self.word[r - 1].clone()));
+                parse_tree
+            }
+            Parent::Nullable(k) => {
+                let mut parse_tree = self.build(r, *k);
+                let Symbol::Var(var) = body[state.parsed - 1] else {
+                    panic!("Expected a variable to be skipped over");
+                };
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Var(Box::new(self.parser.build_empty(var))));
+                parse_tree
+            }
+            Parent::Var(k, j) => {
+                let mut parse_tree = self.build(self.chart.states[r][*k].l, *j);
+                let another_parse_tree = self.build(r, *k);
+                parse_tree
+                    .children
+                    .push(ParsedSymbol::Var(Box::new(another_parse_tree)));
+                parse_tree
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+struct State {
+    l: usize,
+    rule_idx: usize,
+    parsed: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Parent {
+    /// The state was started from its left corner, which is either the terminal before it or the
+    /// completed state at the given index of the same column.
+    LeftCorner(Option<usize>),
+    Term(usize),
+    Var(usize, usize),
+    Nullable(usize),
+}
+
+struct Chart {
+    states: Vec<Vec<State>>,
+    parents: Vec<Vec<Parent>>,
+    /// For the columns `r` and `r + 1`, where `r` is the column being processed, the states in
+    /// them, at `present[r % 2]` and `present[(r + 1) % 2]`.
+    present: [FxHashSet<State>; 2],
+    /// For every column and variable, the indices of the states expecting the variable next.
+    waiting: Vec<FxHashMap<Var, Vec<usize>>>,
+    /// For every column, the variables which may begin there, as a bitset.
+    relevant: Vec<Vec<u64>>,
+}
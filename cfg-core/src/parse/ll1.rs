@@ -0,0 +1,333 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt::Display,
+    rc::Rc,
+};
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{ParseError, ParseForest, ParseTree, ParsedSymbol, Parser};
+
+/// A predictive parser for LL(1) grammars, which chooses the rule to apply to every variable by
+/// looking at the next terminal only, and so parses in linear time.
+pub struct LL1Parser<T: Term> {
+    start_var: Var,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    /// The terminals of the grammar, numbered so that the table below can be indexed by them. The
+    /// end of the word is numbered after the last terminal.
+    term_ids: HashMap<T, usize>,
+    /// For every variable and lookahead, the rule to apply, if any.
+    table: Vec<Vec<Option<usize>>>,
+    /// For every variable, the terminals which may begin the words it derives, and whether it
+    /// derives the empty word, from which what was expected is found when a word goes wrong.
+    first_sets: Vec<BTreeSet<T>>,
+    nullable: Vec<bool>,
+}
+
+/// A variable being parsed, along with the rule applied to it, where it starts and the children
+/// parsed so far.
+type Frame<T> = (usize, usize, Vec<ParsedSymbol<T>>);
+
+impl<T: Term> LL1Parser<T> {
+    /// Builds the LL(1) table of the grammar, or returns every entry of the table for which more
+    /// than one rule applies if the grammar is not LL(1).
+    pub fn of(cfg: Cfg<T>) -> Result<Self, LL1Conflicts<T>> {
+        let nullable_rules = cfg.nullable_rules();
+        let first_sets = cfg.first_sets();
+        let follow_sets = cfg.follow_sets(&first_sets);
+
+        let mut term_ids = HashMap::new();
+        for rule in cfg.rules.iter() {
+            for symbol in rule.body.iter() {
+                if let Symbol::Term(term) = symbol {
+                    let n_terms = term_ids.len();
+                    term_ids.entry(term.clone()).or_insert(n_terms);
+                }
+            }
+        }
+        let n_terms = term_ids.len();
+
+        // The rules applying to every variable and lookahead, with `None` standing for the end of
+        // the word.
+        let mut entries = vec![HashMap::<Option<T>, Vec<usize>>::new(); cfg.n_vars()];
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            let (first, is_nullable) = cfg.first_of(&rule.body, &first_sets, &nullable_rules);
+            let mut lookaheads: BTreeSet<_> = first.into_iter().map(Some).collect();
+            if is_nullable {
+                lookaheads.extend(follow_sets[rule.head.0].iter().cloned());
+            }
+            for lookahead in lookaheads {
+                entries[rule.head.0]
+                    .entry(lookahead)
+                    .or_default()
+                    .push(rule_idx);
+            }
+        }
+
+        let mut table = vec![vec![None; n_terms + 1]; cfg.n_vars()];
+        let mut conflicts = Vec::new();
+        for (var, entries) in entries.into_iter().enumerate() {
+            let mut entries: Vec<_> = entries.into_iter().collect();
+            entries.sort();
+            for (lookahead, rule_idxs) in entries {
+                let lookahead_id = match &lookahead {
+                    Some(term) => term_ids[term],
+                    None => n_terms,
+                };
+                if let [rule_idx] = rule_idxs[..] {
+                    table[var][lookahead_id] = Some(rule_idx);
+                    continue;
+                }
+                conflicts.push(LL1Conflict {
+                    var: Var(var),
+                    lookahead,
+                    rules: rule_idxs
+                        .into_iter()
+                        .map(|rule_idx| (rule_idx, cfg.rules[rule_idx].clone()))
+                        .collect(),
+                });
+            }
+        }
+
+        let Cfg {
+            start_var,
+            var_names,
+            rules,
+        } = cfg;
+
+        if !conflicts.is_empty() {
+            return Err(LL1Conflicts {
+                var_names,
+                conflicts,
+            });
+        }
+
+        Ok(Self {
+            start_var,
+            var_names,
+            rules,
+            term_ids,
+            table,
+            first_sets,
+            nullable: nullable_rules.iter().map(Option::is_some).collect(),
+        })
+    }
+
+    fn rule_for(&self, var: Var, lookahead: Option<&T>) -> Option<usize> {
+        let lookahead_id = match lookahead {
+            Some(term) => *self.term_ids.get(term)?,
+            None => self.term_ids.len(),
+        };
+        self.table[var.0][lookahead_id]
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for LL1Parser<T> {
+    type TermType = T;
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        self.parse_tree(&word)
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let parse_tree = self.parse_tree(&word).ok()?;
+        Some(ParseForest::of_parse_tree(&self.var_names, &parse_tree))
+    }
+}
+
+impl<T: Term> LL1Parser<T> {
+    /// Runs the table on the word, stopping at the first error.
+    ///
+    /// A variable deriving the empty word may be expanded to it on a terminal which may follow the
+    /// variable somewhere else, so the table only runs into the error after expanding to the empty
+    /// word every variable it meets since the last terminal was matched. These variables are kept
+    /// track of, along with the variables whose rules were recognized up to there, to find what
+    /// was expected.
+    fn parse_tree(&self, word: &[T]) -> Result<ParseTree<T>, ParseError<T>> {
+        let mut i = 0;
+        let mut expanded = Vec::new();
+        let mut vars = Vec::new();
+        let Some(rule_idx) = self.rule_for(self.start_var, word.first()) else {
+            let rest = [Symbol::Var(self.start_var)];
+            return Err(self.parse_error(word, i, &rest, &[], &expanded, vars));
+        };
+        let mut stack: Vec<Frame<T>> = vec![(rule_idx, 0, Vec::new())];
+
+        loop {
+            let (rule_idx, start, children) = stack.last_mut().unwrap();
+            let rule = &self.rules[*rule_idx];
+            match rule.body.get(children.len()) {
+                Some(Symbol::Term(term)) if word.get(i) == Some(term) => {
+                    children.push(ParsedSymbol::Term(term.clone()));
+                    i += 1;
+                    expanded.clear();
+                    vars.clear();
+                }
+                Some(symbol) => {
+                    if *start < i {
+                        vars.push((*start, rule.head));
+                    }
+                    let rule_idx = match symbol {
+                        Symbol::Var(var) => {
+                            expanded.push(*var);
+                            self.rule_for(*var, word.get(i))
+                        }
+                        Symbol::Term(_) => None,
+                    };
+                    let Some(rule_idx) = rule_idx else {
+                        let rest = &rule.body[children.len()..];
+                        let below = &stack[..stack.len() - 1];
+                        return Err(self.parse_error(word, i, rest, below, &expanded, vars));
+                    };
+                    stack.push((rule_idx, i, Vec::new()));
+                }
+                None => {
+                    let (rule_idx, _, children) = stack.pop().unwrap();
+                    let parse_tree = ParseTree {
+                        root_var: rule.head,
+                        root_var_name: self.var_names[rule.head.0].clone(),
+                        rule_idx,
+                        children,
+                    };
+                    match stack.last_mut() {
+                        Some((_, _, children)) => {
+                            children.push(ParsedSymbol::Var(Box::new(parse_tree)));
+                        }
+                        None if i == word.len() => return Ok(parse_tree),
+                        None => return Err(self.parse_error(word, i, &[], &[], &expanded, vars)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Makes the error of the word going wrong at `i`, given what is left of the rule the table ran
+    /// into the error in, the variables being parsed below it, and the variables expanded and
+    /// those being recognized since the last terminal was matched.
+    ///
+    /// What was expected are the terminals which may begin the expanded variables, and those which
+    /// may begin what is left of the rules on the stack up to the first symbol which does not
+    /// derive the empty word, or the end of the word if there is no such symbol.
+    fn parse_error(
+        &self,
+        word: &[T],
+        i: usize,
+        rest: &[Symbol<T>],
+        stack: &[Frame<T>],
+        expanded: &[Var],
+        mut vars: Vec<(usize, Var)>,
+    ) -> ParseError<T> {
+        let mut expected: BTreeSet<_> = expanded
+            .iter()
+            .flat_map(|var| self.first_sets[var.0].iter().cloned().map(Some))
+            .collect();
+        let mut is_nullable = self.add_first(rest, &mut expected);
+        for (rule_idx, start, children) in stack.iter().rev() {
+            if !is_nullable {
+                break;
+            }
+            let rule = &self.rules[*rule_idx];
+            let rest = &rule.body[children.len() + 1..];
+            if *start < i && !rest.is_empty() {
+                vars.push((*start, rule.head));
+            }
+            is_nullable = self.add_first(rest, &mut expected);
+        }
+        if is_nullable {
+            expected.insert(None);
+        }
+        ParseError::new(word, i, expected, vars, &self.var_names)
+    }
+
+    /// Adds the terminals which may begin the words derived by the symbols to `expected`, and
+    /// returns whether the symbols derive the empty word.
+    fn add_first(&self, symbols: &[Symbol<T>], expected: &mut BTreeSet<Option<T>>) -> bool {
+        for symbol in symbols {
+            match symbol {
+                Symbol::Term(term) => {
+                    expected.insert(Some(term.clone()));
+                    return false;
+                }
+                Symbol::Var(var) => {
+                    expected.extend(self.first_sets[var.0].iter().cloned().map(Some));
+                    if !self.nullable[var.0] {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The conflicts which keep a grammar from being LL(1).
+#[derive(Debug, Clone)]
+pub struct LL1Conflicts<T: Term> {
+    var_names: Vec<Rc<str>>,
+    conflicts: Vec<LL1Conflict<T>>,
+}
+
+/// An entry of the LL(1) table for which more than one rule applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LL1Conflict<T: Term> {
+    var: Var,
+    lookahead: Option<T>,
+    rules: Vec<(usize, Rule<T>)>,
+}
+
+impl<T: Term> LL1Conflicts<T> {
+    pub fn conflicts(&self) -> &[LL1Conflict<T>] {
+        &self.conflicts
+    }
+
+    pub fn var_name(&self, var: Var) -> &str {
+        &self.var_names[var.0]
+    }
+}
+
+impl<T: Term> LL1Conflict<T> {
+    pub fn var(&self) -> Var {
+        self.var
+    }
+
+    /// The next terminal for which the rules clash, or `None` for the end of the word.
+    pub fn lookahead(&self) -> Option<&T> {
+        self.lookahead.as_ref()
+    }
+
+    /// The clashing rules, along with their indices in the order in which they were added to the
+    /// grammar.
+    pub fn rules(&self) -> &[(usize, Rule<T>)] {
+        &self.rules
+    }
+}
+
+impl<T: Term> Display for LL1Conflicts<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the grammar is not LL(1):")?;
+        for conflict in self.conflicts.iter() {
+            write!(f, "\n  `{}` on ", self.var_name(conflict.var))?;
+            match &conflict.lookahead {
+                Some(term) => write!(f, "{:?}", term)?,
+                None => write!(f, "end of word")?,
+            }
+            write!(f, " may be derived by")?;
+            for (i, (_, rule)) in conflict.rules.iter().enumerate() {
+                let separator = if i == 0 { "" } else { " or" };
+                write!(f, "{} `{} =>", separator, self.var_name(rule.head))?;
+                for symbol in rule.body.iter() {
+                    match symbol {
+                        Symbol::Var(var) => write!(f, " {}", self.var_name(*var))?,
+                        Symbol::Term(term) => write!(f, " {:?}", term)?,
+                    }
+                }
+                write!(f, "`")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Term> Error for LL1Conflicts<T> {}
@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::{Cfg, Symbol, Term, Var};
+
+/// The LR(0) automaton of a grammar augmented with a rule `S' => S`, where `S` is the start
+/// variable. The augmented rule is given the index right after the last rule of the grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct Lr0Automaton<T: Term> {
+    /// For every state, its items: the kernel items first, then the ones added by the closure.
+    pub(crate) items: Vec<Vec<Item>>,
+    pub(crate) term_gotos: Vec<HashMap<T, usize>>,
+    pub(crate) var_gotos: Vec<HashMap<Var, usize>>,
+}
+
+/// A rule along with the number of symbols of its body recognized so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Item {
+    pub(crate) rule_idx: usize,
+    pub(crate) parsed: usize,
+}
+
+impl<T: Term> Lr0Automaton<T> {
+    pub(crate) fn of(cfg: &Cfg<T>) -> Self {
+        let mut rules_by_var = vec![vec![]; cfg.n_vars()];
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            rules_by_var[rule.head.0].push(rule_idx);
+        }
+        Self::of_rules(cfg, &rules_by_var)
+    }
+
+    /// Builds the automaton from the given rules of every variable only, leaving out the others.
+    pub(crate) fn of_rules(cfg: &Cfg<T>, rules_by_var: &[Vec<usize>]) -> Self {
+        let augmented_body = [Symbol::Var(cfg.start_var)];
+        let body = |rule_idx: usize| match cfg.rules.get(rule_idx) {
+            Some(rule) => &rule.body[..],
+            None => &augmented_body[..],
+        };
+
+        let mut automaton = Self {
+            items: Vec::new(),
+            term_gotos: Vec::new(),
+            var_gotos: Vec::new(),
+        };
+        let initial_kernel = vec![Item {
+            rule_idx: cfg.rules.len(),
+            parsed: 0,
+        }];
+        let mut states = HashMap::from([(initial_kernel.clone(), 0)]);
+        let mut queue = VecDeque::from([initial_kernel]);
+
+        while let Some(kernel) = queue.pop_front() {
+            let items = closure(kernel, body, rules_by_var);
+
+            let mut kernels = BTreeMap::<&Symbol<T>, Vec<Item>>::new();
+            for item in items.iter() {
+                if let Some(symbol) = body(item.rule_idx).get(item.parsed) {
+                    kernels.entry(symbol).or_default().push(Item {
+                        rule_idx: item.rule_idx,
+                        parsed: item.parsed + 1,
+                    });
+                }
+            }
+
+            let mut term_gotos = HashMap::new();
+            let mut var_gotos = HashMap::new();
+            for (symbol, mut kernel) in kernels {
+                kernel.sort();
+                let n_states = states.len();
+                let state = *states.entry(kernel.clone()).or_insert_with(|| {
+                    queue.push_back(kernel);
+                    n_states
+                });
+                match symbol {
+                    Symbol::Term(term) => term_gotos.insert(term.clone(), state),
+                    Symbol::Var(var) => var_gotos.insert(*var, state),
+                };
+            }
+
+            automaton.items.push(items);
+            automaton.term_gotos.push(term_gotos);
+            automaton.var_gotos.push(var_gotos);
+        }
+
+        automaton
+    }
+
+    pub(crate) fn n_states(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// Extends the kernel of a state with the items predicted by it.
+fn closure<'a, T: Term + 'a>(
+    mut items: Vec<Item>,
+    body: impl Fn(usize) -> &'a [Symbol<T>],
+    rules_by_var: &[Vec<usize>],
+) -> Vec<Item> {
+    let mut is_predicted = vec![false; rules_by_var.len()];
+    let mut i = 0;
+    while i < items.len() {
+        let item = items[i];
+        i += 1;
+        let Some(Symbol::Var(var)) = body(item.rule_idx).get(item.parsed) else {
+            continue;
+        };
+        if is_predicted[var.0] {
+            continue;
+        }
+        is_predicted[var.0] = true;
+        items.extend(rules_by_var[var.0].iter().map(|&rule_idx| Item {
+            rule_idx,
+            parsed: 0,
+        }));
+    }
+    items
+}
+
+/// The canonical LR(1) automaton of a grammar augmented with a rule `S' => S`, where `S` is the
+/// start variable. The augmented rule is given the index right after the last rule of the grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct Lr1Automaton<T: Term> {
+    /// For every state, its items along with their lookaheads, with `None` standing for the end of
+    /// the word.
+    pub(crate) items: Vec<BTreeSet<(Item, Option<T>)>>,
+    pub(crate) term_gotos: Vec<HashMap<T, usize>>,
+    pub(crate) var_gotos: Vec<HashMap<Var, usize>>,
+}
+
+impl<T: Term> Lr1Automaton<T> {
+    pub(crate) fn of(cfg: &Cfg<T>) -> Self {
+        let mut rules_by_var = vec![vec![]; cfg.n_vars()];
+        for (rule_idx, rule) in cfg.rules.iter().enumerate() {
+            rules_by_var[rule.head.0].push(rule_idx);
+        }
+        let nullable_rules = cfg.nullable_rules();
+        let first_sets = cfg.first_sets();
+        let augmented_body = [Symbol::Var(cfg.start_var)];
+        let body = |rule_idx: usize| match cfg.rules.get(rule_idx) {
+            Some(rule) => &rule.body[..],
+            None => &augmented_body[..],
+        };
+
+        // Extends a kernel with the items predicted by it, along with the lookaheads they are
+        // predicted with.
+        let closure = |kernel: &BTreeSet<(Item, Option<T>)>| {
+            let mut items = kernel.clone();
+            let mut queue: Vec<_> = kernel.iter().cloned().collect();
+            while let Some((item, lookahead)) = queue.pop() {
+                let body = body(item.rule_idx);
+                let Some(Symbol::Var(var)) = body.get(item.parsed) else {
+                    continue;
+                };
+                let (first, is_rest_nullable) =
+                    cfg.first_of(&body[item.parsed + 1..], &first_sets, &nullable_rules);
+                let mut lookaheads: Vec<_> = first.into_iter().map(Some).collect();
+                if is_rest_nullable {
+                    lookaheads.push(lookahead);
+                }
+                for &rule_idx in rules_by_var[var.0].iter() {
+                    for lookahead in lookaheads.iter() {
+                        let item = (
+                            Item {
+                                rule_idx,
+                                parsed: 0,
+                            },
+                            lookahead.clone(),
+                        );
+                        if items.insert(item.clone()) {
+                            queue.push(item);
+                        }
+                    }
+                }
+            }
+            items
+        };
+
+        let mut automaton = Self {
+            items: Vec::new(),
+            term_gotos: Vec::new(),
+            var_gotos: Vec::new(),
+        };
+        let initial_item = Item {
+            rule_idx: cfg.rules.len(),
+            parsed: 0,
+        };
+        let initial_kernel = BTreeSet::from([(initial_item, None)]);
+        let mut states = HashMap::from([(initial_kernel.clone(), 0)]);
+        let mut queue = VecDeque::from([initial_kernel]);
+
+        while let Some(kernel) = queue.pop_front() {
+            let items = closure(&kernel);
+
+            let mut kernels = BTreeMap::<&Symbol<T>, BTreeSet<_>>::new();
+            for (item, lookahead) in items.iter() {
+                if let Some(symbol) = body(item.rule_idx).get(item.parsed) {
+                    let item = Item {
+                        rule_idx: item.rule_idx,
+                        parsed: item.parsed + 1,
+                    };
+                    kernels
+                        .entry(symbol)
+                        .or_default()
+                        .insert((item, lookahead.clone()));
+                }
+            }
+
+            let mut term_gotos = HashMap::new();
+            let mut var_gotos = HashMap::new();
+            for (symbol, kernel) in kernels {
+                let n_states = states.len();
+                let state = *states.entry(kernel.clone()).or_insert_with(|| {
+                    queue.push_back(kernel);
+                    n_states
+                });
+                match symbol {
+                    Symbol::Term(term) => term_gotos.insert(term.clone(), state),
+                    Symbol::Var(var) => var_gotos.insert(*var, state),
+                };
+            }
+
+            automaton.items.push(items);
+            automaton.term_gotos.push(term_gotos);
+            automaton.var_gotos.push(var_gotos);
+        }
+
+        automaton
+    }
+
+    /// Merges the states whose items are the same up to lookaheads, which turns the automaton into
+    /// the LALR(1) automaton of the grammar.
+    pub(crate) fn merge_cores(self) -> Self {
+        let mut core_states = HashMap::<BTreeSet<Item>, usize>::new();
+        let state_map: Vec<usize> = self
+            .items
+            .iter()
+            .map(|items| {
+                let core = items.iter().map(|(item, _)| *item).collect();
+                let n_core_states = core_states.len();
+                *core_states.entry(core).or_insert(n_core_states)
+            })
+            .collect();
+
+        let n_states = core_states.len();
+        let mut automaton = Self {
+            items: vec![BTreeSet::new(); n_states],
+            term_gotos: vec![HashMap::new(); n_states],
+            var_gotos: vec![HashMap::new(); n_states],
+        };
+        for (state, items) in self.items.into_iter().enumerate() {
+            let merged_state = state_map[state];
+            automaton.items[merged_state].extend(items);
+            for (term, next_state) in self.term_gotos[state].iter() {
+                automaton.term_gotos[merged_state].insert(term.clone(), state_map[*next_state]);
+            }
+            for (var, next_state) in self.var_gotos[state].iter() {
+                automaton.var_gotos[merged_state].insert(*var, state_map[*next_state]);
+            }
+        }
+
+        automaton
+    }
+}
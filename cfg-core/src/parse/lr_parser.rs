@@ -0,0 +1,757 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    rc::Rc,
+};
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{
+    lr::{Item, Lr0Automaton, Lr1Automaton},
+    ParseError, ParseForest, ParseTree, ParsedSymbol, Parser,
+};
+
+/// The kinds of LR tables, from the least to the most powerful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrTableKind {
+    /// Reduces whenever a rule's body is recognized, regardless of the next terminal.
+    Lr0,
+    /// Reduces to a variable on the terminals which may follow the variable anywhere.
+    Slr1,
+    /// Reduces on the lookaheads of the canonical LR(1) automaton, after merging its states which
+    /// only differ in their lookaheads.
+    Lalr1,
+    /// Reduces on the lookaheads of the canonical LR(1) automaton.
+    Lr1,
+}
+
+/// A deterministic shift-reduce parser driven by an LR table. It parses in linear time, but only
+/// accepts grammars whose table of the chosen kind has no conflicts.
+pub struct LrParser<T: Term> {
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    /// The terminals of the grammar, numbered so that the table below can be indexed by them. The
+    /// end of the word is numbered after the last terminal.
+    term_ids: HashMap<T, usize>,
+    /// For every state and lookahead, the action to take.
+    actions: Vec<Vec<Action>>,
+    /// For every state and variable, the state to go to after reducing to the variable, if any.
+    gotos: Vec<Vec<Option<usize>>>,
+    /// For every state, its items whose rule has been recognized partway, from which the variables
+    /// being recognized are found when a word goes wrong.
+    partial_items: Vec<Vec<Item>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Error,
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+impl<T: Term> LrParser<T> {
+    /// Builds the LR table of the given kind for the grammar, or returns every entry of the table
+    /// for which more than one action applies if there are any.
+    pub fn of(cfg: Cfg<T>, kind: LrTableKind) -> Result<Self, LrConflicts<T>> {
+        let states = States::of(&cfg, kind);
+
+        let mut term_ids = HashMap::new();
+        for rule in cfg.rules.iter() {
+            for symbol in rule.body.iter() {
+                if let Symbol::Term(term) = symbol {
+                    let n_terms = term_ids.len();
+                    term_ids.entry(term.clone()).or_insert(n_terms);
+                }
+            }
+        }
+        let n_terms = term_ids.len();
+        let lookahead_id = |lookahead: &Option<T>| match lookahead {
+            Some(term) => term_ids[term],
+            None => n_terms,
+        };
+
+        let n_states = states.term_gotos.len();
+        let mut actions = vec![vec![Action::Error; n_terms + 1]; n_states];
+        let mut gotos = vec![vec![None; cfg.n_vars()]; n_states];
+        let mut conflicts = Vec::new();
+        for state in 0..n_states {
+            for (var, &next_state) in states.var_gotos[state].iter() {
+                gotos[state][var.0] = Some(next_state);
+            }
+
+            let mut state_actions = BTreeMap::<Option<T>, Vec<LrAction>>::new();
+            for (term, _) in states.term_gotos[state].iter() {
+                state_actions
+                    .entry(Some(term.clone()))
+                    .or_default()
+                    .push(LrAction::Shift);
+            }
+            for (lookahead, rule_idxs) in states.reductions[state].iter() {
+                state_actions
+                    .entry(lookahead.clone())
+                    .or_default()
+                    .extend(rule_idxs.iter().map(|&rule_idx| {
+                        if rule_idx == cfg.rules.len() {
+                            LrAction::Accept
+                        } else {
+                            LrAction::Reduce(rule_idx)
+                        }
+                    }));
+            }
+
+            for (lookahead, lr_actions) in state_actions {
+                let action = match lr_actions[..] {
+                    [LrAction::Shift] => {
+                        let term = lookahead.as_ref().unwrap();
+                        Action::Shift(states.term_gotos[state][term])
+                    }
+                    [LrAction::Reduce(rule_idx)] => Action::Reduce(rule_idx),
+                    [LrAction::Accept] => Action::Accept,
+                    _ => {
+                        let (example, derivations) =
+                            counterexample(&cfg, &states, state, &lookahead, &lr_actions);
+                        conflicts.push(LrConflict {
+                            state,
+                            lookahead,
+                            actions: lr_actions,
+                            example,
+                            derivations,
+                        });
+                        continue;
+                    }
+                };
+                actions[state][lookahead_id(&lookahead)] = action;
+            }
+        }
+
+        let Cfg {
+            var_names, rules, ..
+        } = cfg;
+
+        if !conflicts.is_empty() {
+            return Err(LrConflicts {
+                kind,
+                var_names,
+                rules,
+                conflicts,
+            });
+        }
+
+        Ok(Self {
+            var_names,
+            rules,
+            term_ids,
+            actions,
+            gotos,
+            partial_items: states.partial_items,
+        })
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for LrParser<T> {
+    type TermType = T;
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        self.parse_tree(&word)
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let parse_tree = self.parse_tree(&word).ok()?;
+        Some(ParseForest::of_parse_tree(&self.var_names, &parse_tree))
+    }
+}
+
+impl<T: Term> LrParser<T> {
+    /// Runs the table on the word, stopping at the first error.
+    ///
+    /// Tables other than LR(1) tables may reduce on a terminal which cannot come next, so the stack
+    /// right after the last terminal was shifted is kept to find out what was expected. Entries of
+    /// the stack hold a state along with where the symbol leading to it starts.
+    fn parse_tree(&self, word: &[T]) -> Result<ParseTree<T>, ParseError<T>> {
+        let mut stack = vec![(0, 0)];
+        let mut symbols = Vec::new();
+        let mut i = 0;
+        // The stack right after the last shift, and the number of its entries which have not
+        // been popped off since.
+        let mut shifted = stack.clone();
+        let mut n_kept = 1;
+
+        loop {
+            let lookahead_id = match word.get(i) {
+                Some(term) => self.term_ids.get(term).copied(),
+                None => Some(self.term_ids.len()),
+            };
+            let action = match lookahead_id {
+                Some(lookahead_id) => self.actions[stack.last().unwrap().0][lookahead_id],
+                None => Action::Error,
+            };
+            match action {
+                Action::Error => return Err(self.parse_error(word, i, &shifted)),
+                Action::Shift(state) => {
+                    stack.push((state, i));
+                    symbols.push(ParsedSymbol::Term(word[i].clone()));
+                    i += 1;
+                    shifted.truncate(n_kept);
+                    shifted.extend_from_slice(&stack[n_kept..]);
+                    n_kept = stack.len();
+                }
+                Action::Reduce(rule_idx) => {
+                    let rule = &self.rules[rule_idx];
+                    let len = symbols.len() - rule.body.len();
+                    let children = symbols.split_off(len);
+                    self.reduce(&mut stack, rule_idx, i);
+                    n_kept = n_kept.min(stack.len() - 1);
+                    symbols.push(ParsedSymbol::Var(Box::new(ParseTree {
+                        root_var: rule.head,
+                        root_var_name: self.var_names[rule.head.0].clone(),
+                        rule_idx,
+                        children,
+                    })));
+                }
+                Action::Accept => {
+                    let Some(ParsedSymbol::Var(parse_tree)) = symbols.pop() else {
+                        panic!("Expected the start variable on the stack");
+                    };
+                    return Ok(*parse_tree);
+                }
+            }
+        }
+    }
+
+    /// Pops the body of the rule off the stack and goes to the state for its head, which ends at
+    /// `i`.
+    fn reduce(&self, stack: &mut Vec<(usize, usize)>, rule_idx: usize, i: usize) {
+        let rule = &self.rules[rule_idx];
+        let len = stack.len() - rule.body.len();
+        let start = stack.get(len).map_or(i, |&(_, start)| start);
+        stack.truncate(len);
+        let state = self.gotos[stack.last().unwrap().0][rule.head.0]
+            .expect("Expected a goto on the head of a reduced rule");
+        stack.push((state, start));
+    }
+
+    /// Makes the error of the word going wrong at `i`, given the stack right after the last shift.
+    /// A lookahead was expected if the table, reducing on it from there, gets to shift it or to
+    /// accept, and the variables being recognized are the heads of the items recognized partway
+    /// in the states gone through on the way, which started before `i`.
+    fn parse_error(&self, word: &[T], i: usize, shifted: &[(usize, usize)]) -> ParseError<T> {
+        let lookaheads = self
+            .term_ids
+            .iter()
+            .map(|(term, &lookahead_id)| (Some(term), lookahead_id))
+            .chain([(None, self.term_ids.len())]);
+
+        let mut expected = BTreeSet::new();
+        let mut vars = Vec::new();
+        for (lookahead, lookahead_id) in lookaheads {
+            let mut stack = shifted.to_vec();
+            loop {
+                let state = stack.last().unwrap().0;
+                for item in self.partial_items[state].iter() {
+                    let start = stack[stack.len() - item.parsed].1;
+                    if start < i {
+                        vars.push((start, self.rules[item.rule_idx].head));
+                    }
+                }
+                match self.actions[state][lookahead_id] {
+                    Action::Error => break,
+                    Action::Shift(_) | Action::Accept => {
+                        expected.insert(lookahead.cloned());
+                        break;
+                    }
+                    Action::Reduce(rule_idx) => {
+                        self.reduce(&mut stack, rule_idx, i);
+                    }
+                }
+            }
+        }
+        ParseError::new(word, i, expected, vars, &self.var_names)
+    }
+}
+
+/// The states of an LR automaton along with the rules to reduce in every state on every lookahead,
+/// with `None` standing for the end of the word. The reductions are all that the kinds of LR
+/// tables differ in.
+struct States<T: Term> {
+    term_gotos: Vec<HashMap<T, usize>>,
+    var_gotos: Vec<HashMap<Var, usize>>,
+    reductions: Vec<BTreeMap<Option<T>, BTreeSet<usize>>>,
+    partial_items: Vec<Vec<Item>>,
+}
+
+impl<T: Term> States<T> {
+    fn of(cfg: &Cfg<T>, kind: LrTableKind) -> Self {
+        let body_len = |rule_idx: usize| cfg.rules.get(rule_idx).map_or(1, |rule| rule.body.len());
+
+        let (items, term_gotos, var_gotos) = match kind {
+            LrTableKind::Lr0 | LrTableKind::Slr1 => {
+                let automaton = Lr0Automaton::of(cfg);
+                let lookaheads = match kind {
+                    LrTableKind::Lr0 => {
+                        let mut lookaheads: BTreeSet<_> = cfg
+                            .rules
+                            .iter()
+                            .flat_map(|rule| rule.body.iter())
+                            .filter_map(|symbol| match symbol {
+                                Symbol::Term(term) => Some(Some(term.clone())),
+                                Symbol::Var(_) => None,
+                            })
+                            .collect();
+                        lookaheads.insert(None);
+                        vec![lookaheads; cfg.n_vars()]
+                    }
+                    _ => cfg.follow_sets(&cfg.first_sets()),
+                };
+                let items = automaton
+                    .items
+                    .into_iter()
+                    .map(|items| {
+                        items
+                            .into_iter()
+                            .flat_map(|item| match cfg.rules.get(item.rule_idx) {
+                                Some(rule) => lookaheads[rule.head.0]
+                                    .iter()
+                                    .map(|lookahead| (item, lookahead.clone()))
+                                    .collect(),
+                                None => vec![(item, None)],
+                            })
+                            .collect()
+                    })
+                    .collect();
+                (items, automaton.term_gotos, automaton.var_gotos)
+            }
+            LrTableKind::Lalr1 | LrTableKind::Lr1 => {
+                let mut automaton = Lr1Automaton::of(cfg);
+                if kind == LrTableKind::Lalr1 {
+                    automaton = automaton.merge_cores();
+                }
+                (automaton.items, automaton.term_gotos, automaton.var_gotos)
+            }
+        };
+
+        let (reductions, partial_items) = items
+            .into_iter()
+            .map(|items: BTreeSet<(Item, Option<T>)>| {
+                let mut reductions = BTreeMap::<_, BTreeSet<_>>::new();
+                let mut partial_items = BTreeSet::new();
+                for (item, lookahead) in items {
+                    if item.parsed == body_len(item.rule_idx) {
+                        reductions
+                            .entry(lookahead)
+                            .or_default()
+                            .insert(item.rule_idx);
+                    } else if item.parsed > 0 && item.rule_idx < cfg.rules.len() {
+                        partial_items.insert(item);
+                    }
+                }
+                (reductions, partial_items.into_iter().collect())
+            })
+            .unzip();
+
+        Self {
+            term_gotos,
+            var_gotos,
+            reductions,
+            partial_items,
+        }
+    }
+}
+
+/// Finds a shortest sequence of symbols leading to the state, and for every action, a derivation
+/// from the start variable in which the action applies after the sequence.
+///
+/// The derivations are searched for among the items of the grammar rather than the states of the
+/// automaton, while keeping track of the terminals which may follow each of them. A reduction is
+/// thus only derived with the lookahead it conflicts on, unless the conflict is an artifact of the
+/// kind of table, in which case no such derivation exists and the lookahead is disregarded.
+fn counterexample<T: Term>(
+    cfg: &Cfg<T>,
+    states: &States<T>,
+    state: usize,
+    lookahead: &Option<T>,
+    actions: &[LrAction],
+) -> (Vec<Symbol<T>>, Vec<Derivation>) {
+    let example = shortest_prefix(states, state);
+    let search = DerivationSearch {
+        cfg,
+        example: &example,
+        augmented_body: [Symbol::Var(cfg.start_var)],
+        nullable_rules: cfg.nullable_rules(),
+        first_sets: cfg.first_sets(),
+    };
+
+    let derivations = actions
+        .iter()
+        .map(|action| {
+            let is_target = |item: Item, lookaheads: &BTreeSet<Option<T>>| {
+                let body = search.body(item.rule_idx);
+                match action {
+                    LrAction::Shift => {
+                        matches!((&body.get(item.parsed), lookahead),
+                            (Some(Symbol::Term(term)), Some(lookahead)) if term == lookahead)
+                    }
+                    LrAction::Reduce(rule_idx) => {
+                        item.rule_idx == *rule_idx
+                            && item.parsed == body.len()
+                            && lookaheads.contains(lookahead)
+                    }
+                    LrAction::Accept => item.rule_idx == cfg.rules.len() && item.parsed == 1,
+                }
+            };
+            let is_shift = matches!(action, LrAction::Shift);
+            search
+                .find(!is_shift, &is_target)
+                .or_else(|| {
+                    search.find(false, &|item, _| {
+                        is_target(item, &[lookahead.clone()].into())
+                    })
+                })
+                .expect("Expected every item of a state to be valid after any prefix leading to it")
+        })
+        .collect();
+
+    (example, derivations)
+}
+
+/// Finds a shortest sequence of symbols taking the automaton from its initial state to the given
+/// one.
+fn shortest_prefix<T: Term>(states: &States<T>, state: usize) -> Vec<Symbol<T>> {
+    let mut parents = vec![None; states.term_gotos.len()];
+    let mut queue = vec![0];
+    let mut i = 0;
+    while i < queue.len() && parents[state].is_none() && state != 0 {
+        let current = queue[i];
+        i += 1;
+        let mut gotos: Vec<_> = states.var_gotos[current]
+            .iter()
+            .map(|(var, next)| (Symbol::Var(*var), *next))
+            .chain(
+                states.term_gotos[current]
+                    .iter()
+                    .map(|(term, next)| (Symbol::Term(term.clone()), *next)),
+            )
+            .collect();
+        gotos.sort();
+        for (symbol, next) in gotos {
+            if next != 0 && parents[next].is_none() {
+                parents[next] = Some((current, symbol));
+                queue.push(next);
+            }
+        }
+    }
+
+    let mut prefix = Vec::new();
+    let mut current = state;
+    while let Some((parent, symbol)) = parents[current].take() {
+        prefix.push(symbol);
+        current = parent;
+    }
+    prefix.reverse();
+    prefix
+}
+
+struct DerivationSearch<'a, T: Term> {
+    cfg: &'a Cfg<T>,
+    example: &'a [Symbol<T>],
+    /// The body of the augmented rule, which derives the start variable.
+    augmented_body: [Symbol<T>; 1],
+    nullable_rules: Vec<Option<usize>>,
+    first_sets: Vec<BTreeSet<T>>,
+}
+
+/// The rules being recognized at some point of a derivation, from the outermost one in, along with
+/// the number of symbols of their bodies recognized so far.
+type Derivation = Vec<(usize, usize)>;
+
+/// A point in a derivation: the number of symbols of the example recognized, the item being
+/// recognized, and the terminals which may follow the item.
+type Config<T> = (usize, Item, BTreeSet<Option<T>>);
+
+impl<T: Term> DerivationSearch<'_, T> {
+    fn body(&self, rule_idx: usize) -> &[Symbol<T>] {
+        match self.cfg.rules.get(rule_idx) {
+            Some(rule) => &rule.body,
+            None => &self.augmented_body,
+        }
+    }
+
+    /// Searches breadth-first for a derivation from the start variable in which the whole example
+    /// is recognized and then an item satisfying `is_target` is reached, and returns the items of
+    /// the derivation which are being recognized at that point, from the outermost one in.
+    fn find(
+        &self,
+        track_lookaheads: bool,
+        is_target: &dyn Fn(Item, &BTreeSet<Option<T>>) -> bool,
+    ) -> Option<Derivation> {
+        let initial_item = Item {
+            rule_idx: self.cfg.rules.len(),
+            parsed: 0,
+        };
+        let initial_lookaheads = if track_lookaheads {
+            BTreeSet::from([None])
+        } else {
+            BTreeSet::new()
+        };
+        let mut configs: Vec<(Config<T>, Option<usize>)> =
+            vec![((0, initial_item, initial_lookaheads), None)];
+        let mut seen = HashSet::from([configs[0].0.clone()]);
+
+        let mut i = 0;
+        while i < configs.len() {
+            let ((pos, item, lookaheads), _) = configs[i].clone();
+            if pos == self.example.len() && is_target(item, &lookaheads) {
+                return Some(self.derivation(&configs, i));
+            }
+
+            let body = self.body(item.rule_idx);
+            let mut next_configs = Vec::new();
+            if let Some(symbol) = body.get(item.parsed) {
+                if self.example.get(pos) == Some(symbol) {
+                    let item = Item {
+                        rule_idx: item.rule_idx,
+                        parsed: item.parsed + 1,
+                    };
+                    next_configs.push((pos + 1, item, lookaheads.clone()));
+                }
+                if let Symbol::Var(var) = symbol {
+                    let mut next_lookaheads = BTreeSet::new();
+                    if track_lookaheads {
+                        let (first, is_rest_nullable) = self.cfg.first_of(
+                            &body[item.parsed + 1..],
+                            &self.first_sets,
+                            &self.nullable_rules,
+                        );
+                        next_lookaheads.extend(first.into_iter().map(Some));
+                        if is_rest_nullable {
+                            next_lookaheads.extend(lookaheads.iter().cloned());
+                        }
+                    }
+                    for (rule_idx, rule) in self.cfg.rules.iter().enumerate() {
+                        if rule.head == *var {
+                            let item = Item {
+                                rule_idx,
+                                parsed: 0,
+                            };
+                            next_configs.push((pos, item, next_lookaheads.clone()));
+                        }
+                    }
+                }
+            }
+
+            for config in next_configs {
+                if seen.insert(config.clone()) {
+                    configs.push((config, Some(i)));
+                }
+            }
+            i += 1;
+        }
+
+        None
+    }
+
+    /// Replays the path of the search to the given config, keeping a stack of the items being
+    /// recognized.
+    fn derivation(&self, configs: &[(Config<T>, Option<usize>)], i: usize) -> Derivation {
+        let mut path = vec![i];
+        while let Some(parent) = configs[*path.last().unwrap()].1 {
+            path.push(parent);
+        }
+        path.reverse();
+
+        let mut stack = vec![configs[path[0]].0 .1];
+        for window in path.windows(2) {
+            let ((pos, _, _), _) = &configs[window[0]];
+            let ((next_pos, next_item, _), _) = &configs[window[1]];
+            if next_pos > pos {
+                stack.last_mut().unwrap().parsed += 1;
+            } else {
+                stack.push(*next_item);
+            }
+        }
+
+        // The augmented rule is left out, unless accepting is the action being derived.
+        if stack.len() > 1 {
+            stack.remove(0);
+        }
+        stack
+            .into_iter()
+            .map(|item| (item.rule_idx, item.parsed))
+            .collect()
+    }
+}
+
+/// The conflicts which keep a grammar from having an LR table of some kind.
+#[derive(Debug, Clone)]
+pub struct LrConflicts<T: Term> {
+    kind: LrTableKind,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    conflicts: Vec<LrConflict<T>>,
+}
+
+/// An entry of an LR table for which more than one action applies, along with a counterexample
+/// showing how each of the actions may come about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrConflict<T: Term> {
+    state: usize,
+    lookahead: Option<T>,
+    actions: Vec<LrAction>,
+    example: Vec<Symbol<T>>,
+    derivations: Vec<Derivation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrAction {
+    Shift,
+    /// Reduces by the rule with the given index.
+    Reduce(usize),
+    Accept,
+}
+
+impl<T: Term> LrConflicts<T> {
+    pub fn kind(&self) -> LrTableKind {
+        self.kind
+    }
+
+    pub fn conflicts(&self) -> &[LrConflict<T>] {
+        &self.conflicts
+    }
+
+    pub fn var_name(&self, var: Var) -> &str {
+        &self.var_names[var.0]
+    }
+}
+
+impl<T: Term> LrConflict<T> {
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    /// The next terminal on which the actions clash, or `None` for the end of the word.
+    pub fn lookahead(&self) -> Option<&T> {
+        self.lookahead.as_ref()
+    }
+
+    pub fn actions(&self) -> &[LrAction] {
+        &self.actions
+    }
+
+    pub fn is_shift_reduce(&self) -> bool {
+        self.actions.contains(&LrAction::Shift)
+    }
+
+    /// A shortest sequence of symbols after which the actions clash.
+    pub fn example(&self) -> &[Symbol<T>] {
+        &self.example
+    }
+
+    /// For every action, a derivation from the start variable in which the action applies after
+    /// the example. A derivation is given by the rules being recognized after the example, from
+    /// the outermost one in, along with the number of symbols of their bodies recognized so far.
+    pub fn derivations(&self) -> &[Vec<(usize, usize)>] {
+        &self.derivations
+    }
+}
+
+impl Display for LrTableKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LrTableKind::Lr0 => "LR(0)",
+            LrTableKind::Slr1 => "SLR(1)",
+            LrTableKind::Lalr1 => "LALR(1)",
+            LrTableKind::Lr1 => "LR(1)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl<T: Term> LrConflicts<T> {
+    fn symbol_name(&self, symbol: &Symbol<T>) -> String {
+        match symbol {
+            Symbol::Var(var) => self.var_name(*var).to_string(),
+            Symbol::Term(term) => format!("{:?}", term),
+        }
+    }
+
+    /// Writes out a derivation with every rule below the symbol of the rule above it that it
+    /// derives, like bison does.
+    fn fmt_derivation(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        derivation: &[(usize, usize)],
+    ) -> std::fmt::Result {
+        let mut column = 0;
+        for (k, &(rule_idx, parsed)) in derivation.iter().enumerate() {
+            let mut line = " ".repeat(column);
+            if k > 0 {
+                line.push_str("↳ ");
+            }
+            let Some(rule) = self.rules.get(rule_idx) else {
+                writeln!(f, "      {}accept", line)?;
+                continue;
+            };
+            let is_innermost = k + 1 == derivation.len();
+            line.push_str(self.var_name(rule.head));
+            line.push_str(" =>");
+            for (i, symbol) in rule.body.iter().enumerate() {
+                if i == parsed && is_innermost {
+                    line.push_str(" •");
+                }
+                line.push(' ');
+                if i == parsed {
+                    column = line.chars().count();
+                }
+                line.push_str(&self.symbol_name(symbol));
+            }
+            if parsed == rule.body.len() && is_innermost {
+                line.push_str(" •");
+            }
+            writeln!(f, "      {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Term> Display for LrConflicts<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "the grammar is not {}:", self.kind)?;
+        for conflict in self.conflicts.iter() {
+            let kind = if conflict.is_shift_reduce() {
+                "shift/reduce"
+            } else {
+                "reduce/reduce"
+            };
+            write!(f, "  {} conflict in state {} ", kind, conflict.state)?;
+            match &conflict.lookahead {
+                Some(term) => writeln!(f, "on {:?}:", term)?,
+                None => writeln!(f, "at the end of the word:")?,
+            }
+
+            write!(f, "    example:")?;
+            for symbol in conflict.example.iter() {
+                write!(f, " {}", self.symbol_name(symbol))?;
+            }
+            write!(f, " •")?;
+            if let Some(term) = &conflict.lookahead {
+                write!(f, " {:?}", term)?;
+            }
+            writeln!(f)?;
+
+            for (action, derivation) in conflict.actions.iter().zip(conflict.derivations.iter()) {
+                match action {
+                    LrAction::Shift => writeln!(f, "    shift derivation:")?,
+                    LrAction::Reduce(rule_idx) => {
+                        writeln!(f, "    reduce derivation (rule {}):", rule_idx)?
+                    }
+                    LrAction::Accept => writeln!(f, "    accept derivation:")?,
+                }
+                self.fmt_derivation(f, derivation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Term> Error for LrConflicts<T> {}
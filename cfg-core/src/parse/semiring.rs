@@ -0,0 +1,319 @@
+use std::{collections::BTreeSet, rc::Rc};
+
+use crate::{Cfg, Rule, Symbol, Term};
+
+use super::{EarleyParser, ParseError, ParseForest, ParseTree, ParsedSymbol, Parser};
+
+/// A semiring in which to weigh the parses of words, following Goodman (1999). The weight of a
+/// parse tree is the product of the weights of its rules, taken in the order in which they appear
+/// in the tree from the root down and from left to right, and the weight of a word is the sum of the
+/// weights of its parse trees.
+pub trait Semiring: Clone + PartialEq {
+    /// The identity of `plus`, which is the weight of a word with no parse trees.
+    fn zero() -> Self;
+
+    /// The identity of `times`.
+    fn one() -> Self;
+
+    fn plus(&self, other: &Self) -> Self;
+
+    fn times(&self, other: &Self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+}
+
+/// Whether a word has a parse tree at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boolean(pub bool);
+
+/// The number of parse trees of a word. It saturates at `u64::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counting(pub u64);
+
+/// The probability of the most likely parse tree of a word, with rules weighted by probabilities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viterbi(pub f64);
+
+/// The cost of the cheapest parse tree of a word, with rules weighted by costs, which add up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tropical(pub f64);
+
+/// The logarithm of the total probability of a word, with rules weighted by the logarithms of their
+/// probabilities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogSumExp(pub f64);
+
+/// All parse trees of a word, each given by the rules applied in a leftmost derivation of the word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivations(pub BTreeSet<Vec<usize>>);
+
+/// The best parse tree of a word according to a semiring in which adding weights picks one of them,
+/// such as [`Viterbi`] or [`Tropical`], along with its weight. The parse tree is given by the rules
+/// applied in a leftmost derivation of the word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestDerivation<S: Semiring> {
+    pub weight: S,
+    pub derivation: Option<Vec<usize>>,
+}
+
+impl Semiring for Boolean {
+    fn zero() -> Self {
+        Boolean(false)
+    }
+
+    fn one() -> Self {
+        Boolean(true)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Boolean(self.0 || other.0)
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        Boolean(self.0 && other.0)
+    }
+}
+
+impl Semiring for Counting {
+    fn zero() -> Self {
+        Counting(0)
+    }
+
+    fn one() -> Self {
+        Counting(1)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Counting(self.0.saturating_add(other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        Counting(self.0.saturating_mul(other.0))
+    }
+}
+
+impl Semiring for Viterbi {
+    fn zero() -> Self {
+        Viterbi(0.0)
+    }
+
+    fn one() -> Self {
+        Viterbi(1.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Viterbi(self.0.max(other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        Viterbi(self.0 * other.0)
+    }
+}
+
+impl Semiring for Tropical {
+    fn zero() -> Self {
+        Tropical(f64::INFINITY)
+    }
+
+    fn one() -> Self {
+        Tropical(0.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Tropical(self.0.min(other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        Tropical(self.0 + other.0)
+    }
+}
+
+impl Semiring for LogSumExp {
+    fn zero() -> Self {
+        LogSumExp(f64::NEG_INFINITY)
+    }
+
+    fn one() -> Self {
+        LogSumExp(0.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        let (max, min) = if self.0 >= other.0 {
+            (self.0, other.0)
+        } else {
+            (other.0, self.0)
+        };
+        if min == f64::NEG_INFINITY {
+            return LogSumExp(max);
+        }
+        LogSumExp(max + (min - max).exp().ln_1p())
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        LogSumExp(self.0 + other.0)
+    }
+}
+
+impl Derivations {
+    /// The weight of a rule, as the derivation applying only that rule.
+    pub fn of_rule(rule_idx: usize) -> Self {
+        Derivations(BTreeSet::from([vec![rule_idx]]))
+    }
+}
+
+impl Semiring for Derivations {
+    fn zero() -> Self {
+        Derivations(BTreeSet::new())
+    }
+
+    fn one() -> Self {
+        Derivations(BTreeSet::from([Vec::new()]))
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Derivations(self.0.union(&other.0).cloned().collect())
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        let mut derivations = BTreeSet::new();
+        for derivation in self.0.iter() {
+            for other_derivation in other.0.iter() {
+                let mut derivation = derivation.clone();
+                derivation.extend(other_derivation.iter().cloned());
+                derivations.insert(derivation);
+            }
+        }
+        Derivations(derivations)
+    }
+}
+
+impl<S: Semiring> BestDerivation<S> {
+    /// The weight of a rule, along with the derivation applying only that rule.
+    pub fn of_rule(weight: S, rule_idx: usize) -> Self {
+        BestDerivation {
+            weight,
+            derivation: Some(vec![rule_idx]),
+        }
+    }
+}
+
+impl<S: Semiring> Semiring for BestDerivation<S> {
+    fn zero() -> Self {
+        BestDerivation {
+            weight: S::zero(),
+            derivation: None,
+        }
+    }
+
+    fn one() -> Self {
+        BestDerivation {
+            weight: S::one(),
+            derivation: Some(Vec::new()),
+        }
+    }
+
+    /// Picks the weight which is the sum of both, preferring `self` on ties.
+    fn plus(&self, other: &Self) -> Self {
+        if other.derivation.is_none() || self.weight.plus(&other.weight) == self.weight {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        let (Some(derivation), Some(other_derivation)) = (&self.derivation, &other.derivation)
+        else {
+            return Self::zero();
+        };
+        let mut derivation = derivation.clone();
+        derivation.extend(other_derivation.iter().cloned());
+        BestDerivation {
+            weight: self.weight.times(&other.weight),
+            derivation: Some(derivation),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.derivation.is_none()
+    }
+}
+
+/// A parser computing the weight of a word in any semiring, by weighing the parse forest of the
+/// word built by an [`EarleyParser`].
+///
+/// The chart is not filled in the semiring: the Earley parser fills its chart as usual, and the
+/// forest read off it is weighed afterwards, so the other parsers are not instances of this one.
+/// Every parse tree in the forest is weighed exactly once, since the trees sharing part of the
+/// forest share the weight of that part. On grammars with cycles (`A =>+ A`), the word has no
+/// weight in semirings in which going around a cycle changes the sum, such as [`Counting`] or
+/// [`LogSumExp`], as explained in [`ParseForest::weight`].
+pub struct SemiringParser<T: Term> {
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    earley: EarleyParser<T>,
+}
+
+impl<T: Term> SemiringParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        Self {
+            var_names: cfg.var_names.clone(),
+            rules: cfg.rules.clone(),
+            earley: EarleyParser::of(cfg),
+        }
+    }
+
+    /// Returns the weight of the word in the semiring, given the weight of every rule by its index,
+    /// or `None` if the parse trees of the word go around a cycle whose weight does not settle.
+    pub fn weight<S: Semiring>(&self, word: Vec<T>, rule_weight: impl Fn(usize) -> S) -> Option<S> {
+        self.earley.parse_forest(word).map_or_else(
+            || Some(S::zero()),
+            |parse_forest| parse_forest.weight(rule_weight),
+        )
+    }
+
+    /// Returns the parse tree given by the rules applied in a leftmost derivation.
+    pub fn derivation_tree(&self, derivation: &[usize]) -> ParseTree<T> {
+        let mut rule_idxs = derivation.iter().cloned();
+        let parse_tree = self.build(&mut rule_idxs);
+        assert!(rule_idxs.next().is_none(), "Expected a single derivation");
+        parse_tree
+    }
+
+    fn build(&self, rule_idxs: &mut impl Iterator<Item = usize>) -> ParseTree<T> {
+        let rule_idx = rule_idxs.next().expect("Expected a complete derivation");
+        let rule = &self.rules[rule_idx];
+        let children = rule
+            .body
+            .iter()
+            .map(|symbol| match symbol {
+                Symbol::Term(term) => ParsedSymbol::Term(term.clone()),
+                Symbol::Var(_) => ParsedSymbol::Var(Box::new(self.build(rule_idxs))),
+            })
+            .collect();
+        ParseTree {
+            root_var: rule.head,
+            root_var_name: self.var_names[rule.head.0].clone(),
+            rule_idx,
+            children,
+        }
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for SemiringParser<T> {
+    type TermType = T;
+
+    fn test(&self, word: Vec<T>) -> bool {
+        self.earley.test(word)
+    }
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        self.earley.parse(word)
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        self.earley.parse_forest(word)
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    rc::Rc,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{Cfg, Rule, Symbol, Term, Var};
+
+use super::{forest::SpanForestBuilder, ParseError, ParseForest, ParseTree, ParsedSymbol, Parser};
+
+/// A top-down parser following Unger (1968), which tries every way of partitioning the word among
+/// the symbols of every rule body, working directly on the rules of the grammar.
+///
+/// It is exponential in the worst case even with memoization, and meant to check other parsers
+/// against on small words rather than for actual parsing.
+pub struct UngerParser<T: Term> {
+    start_var: Var,
+    var_names: Vec<Rc<str>>,
+    rules: Vec<Rule<T>>,
+    rules_by_var: Vec<Vec<usize>>,
+    /// For every variable, the length of the shortest word it derives, or `None` if it derives no
+    /// word at all.
+    min_lens: Vec<Option<usize>>,
+}
+
+impl<T: Term> UngerParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        let Cfg {
+            start_var,
+            var_names,
+            rules,
+        } = cfg;
+
+        let mut rules_by_var = vec![vec![]; var_names.len()];
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            rules_by_var[rule.head.0].push(rule_idx);
+        }
+
+        let mut min_lens = vec![None; var_names.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in rules.iter() {
+                let body_len = rule.body.iter().try_fold(0, |len, symbol| match symbol {
+                    Symbol::Term(_) => Some(len + 1),
+                    Symbol::Var(var) => Some(len + min_lens[var.0]?),
+                });
+                if let Some(body_len) = body_len {
+                    if min_lens[rule.head.0].is_none_or(|min_len| body_len < min_len) {
+                        min_lens[rule.head.0] = Some(body_len);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        Self {
+            start_var,
+            var_names,
+            rules,
+            rules_by_var,
+            min_lens,
+        }
+    }
+
+    /// Returns all parse trees of the word, in the order of the rules applied from the root down
+    /// and then of the lengths of the parts of the word derived by their symbols. Trees in which a
+    /// variable derives the same part of the word as one of its ancestors are left out, since
+    /// grammars with cycles (`A =>+ A`) would otherwise give infinitely many of them.
+    ///
+    /// **Warning**: The number of parse trees may be exponential in the length of the word.
+    pub fn parse_trees(&self, word: Vec<T>) -> Vec<ParseTree<T>> {
+        Unger::new(self, &word).trees(self.start_var, 0, word.len())
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for UngerParser<T> {
+    type TermType = T;
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        match self.parse_forest(word.clone()) {
+            Some(parse_forest) => Ok(parse_forest.parse_tree()),
+            None => Err(Unger::new(self, &word).parse_error()),
+        }
+    }
+
+    /// The forest is built from the spans which the variables are found to derive by trying
+    /// every way of partitioning them, so that no parse tree is built before it is asked for.
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let mut unger = Unger::new(self, &word);
+        SpanForestBuilder::new(&self.rules, self.var_names.len(), &word, |var, i, j| {
+            unger.derives(var, i, j, false)
+        })
+        .build(&self.var_names, self.start_var)
+    }
+}
+
+struct Unger<'a, T: Term> {
+    parser: &'a UngerParser<T>,
+    word: &'a [T],
+    /// The parse trees of every variable and part of the word found so far, as if nothing else was
+    /// being parsed.
+    memo: FxHashMap<(Var, usize, usize), Vec<ParseTree<T>>>,
+    /// Whether every variable derives every part of the word, or a word beginning with it, as
+    /// found so far.
+    derived: FxHashMap<(Var, usize, usize, bool), bool>,
+    /// The variables and parts of the word being parsed, along with whether only a word beginning
+    /// with the part is being derived, and their depths.
+    path: FxHashMap<(Var, usize, usize, bool), usize>,
+    /// The lowest depth of the path at which parsing was cut short for going around a cycle. The
+    /// trees found below it depend on the path, and so are not memoized.
+    lowest_blocked: usize,
+}
+
+impl<'a, T: Term> Unger<'a, T> {
+    fn new(parser: &'a UngerParser<T>, word: &'a [T]) -> Self {
+        Self {
+            parser,
+            word,
+            memo: FxHashMap::default(),
+            derived: FxHashMap::default(),
+            path: FxHashMap::default(),
+            lowest_blocked: usize::MAX,
+        }
+    }
+
+    /// Returns whether the variable derives the part of the word from `i` to `j`, or with
+    /// `is_prefix` a word beginning with it, cutting short and memoizing in the same way as
+    /// [`Unger::trees`]. A span found to be derived is always memoized, since going around a cycle
+    /// can only ever add parse trees of it.
+    fn derives(&mut self, var: Var, i: usize, j: usize, is_prefix: bool) -> bool {
+        let key = (var, i, j, is_prefix);
+        if let Some(&is_derived) = self.derived.get(&key) {
+            return is_derived;
+        }
+        if let Some(&depth) = self.path.get(&key) {
+            self.lowest_blocked = self.lowest_blocked.min(depth);
+            return false;
+        }
+
+        let depth = self.path.len();
+        self.path.insert(key, depth);
+        let outer_lowest_blocked = std::mem::replace(&mut self.lowest_blocked, usize::MAX);
+
+        let parser = self.parser;
+        let is_derived = parser.rules_by_var[var.0]
+            .iter()
+            .any(|&rule_idx| self.derives_symbols(&parser.rules[rule_idx].body, i, j, is_prefix));
+
+        self.path.remove(&key);
+        if is_derived || self.lowest_blocked >= depth {
+            self.derived.insert(key, is_derived);
+        }
+        self.lowest_blocked = self.lowest_blocked.min(outer_lowest_blocked);
+        is_derived
+    }
+
+    /// Returns whether the symbols derive the part of the word from `i` to `j`, or with
+    /// `is_prefix` a word beginning with it.
+    fn derives_symbols(
+        &mut self,
+        symbols: &[Symbol<T>],
+        i: usize,
+        j: usize,
+        is_prefix: bool,
+    ) -> bool {
+        let Some((symbol, rest)) = symbols.split_first() else {
+            return i == j;
+        };
+        let Some(rest_min_len) = self.min_len(rest) else {
+            return false;
+        };
+        if is_prefix {
+            // Every word derived by the symbols begins with the empty part of the word, and the
+            // rest of the symbols may derive anything once the first one reaches past `j`.
+            return match symbol {
+                _ if i == j => self.min_len(symbols).is_some(),
+                Symbol::Term(term) => {
+                    self.word[i] == *term && self.derives_symbols(rest, i + 1, j, true)
+                }
+                Symbol::Var(var) => {
+                    self.derives(*var, i, j, true)
+                        || (i..j).any(|k| {
+                            self.derives(*var, i, k, false)
+                                && self.derives_symbols(rest, k, j, true)
+                        })
+                }
+            };
+        }
+
+        match symbol {
+            Symbol::Term(term) => {
+                i + 1 + rest_min_len <= j
+                    && self.word[i] == *term
+                    && self.derives_symbols(rest, i + 1, j, false)
+            }
+            Symbol::Var(var) => {
+                let Some(min_len) = self.parser.min_lens[var.0] else {
+                    return false;
+                };
+                (i + min_len..=j.saturating_sub(rest_min_len)).any(|k| {
+                    self.derives(*var, i, k, false) && self.derives_symbols(rest, k, j, false)
+                })
+            }
+        }
+    }
+
+    /// Finds out where the word goes wrong: at the end of the longest part of it from which the
+    /// start variable derives a word beginning with it. The rules of the variables that may come
+    /// at every position up to there are then gone through from the start variable down, as an
+    /// Earley parser would predict them, to find the terminals they expect there and the rules
+    /// whose bodies were recognized up to there.
+    fn parse_error(mut self) -> ParseError<T> {
+        let parser = self.parser;
+        let start_var = parser.start_var;
+        let position = (0..=self.word.len())
+            .rev()
+            .find(|&j| self.derives(start_var, 0, j, true))
+            .unwrap_or(0);
+
+        let mut expected = BTreeSet::new();
+        if self.derives(start_var, 0, position, false) {
+            expected.insert(None);
+        }
+        let mut vars = Vec::new();
+        let mut seen = HashSet::from([(start_var, 0)]);
+        let mut stack = vec![(start_var, 0)];
+        while let Some((var, l)) = stack.pop() {
+            for &rule_idx in parser.rules_by_var[var.0].iter() {
+                let body = &parser.rules[rule_idx].body;
+                if self.min_len(body).is_none() {
+                    continue;
+                }
+                // The positions up to which the symbols of the body gone through so far derive
+                // the word.
+                let mut ends = BTreeSet::from([l]);
+                for (k, symbol) in body.iter().enumerate() {
+                    if 0 < k && l < position && ends.contains(&position) {
+                        vars.push((l, var));
+                    }
+                    let mut next_ends = BTreeSet::new();
+                    for &i in ends.iter() {
+                        match symbol {
+                            Symbol::Term(term) if i == position => {
+                                expected.insert(Some(term.clone()));
+                            }
+                            Symbol::Term(term) => {
+                                if self.word[i] == *term {
+                                    next_ends.insert(i + 1);
+                                }
+                            }
+                            Symbol::Var(next_var) => {
+                                if seen.insert((*next_var, i)) {
+                                    stack.push((*next_var, i));
+                                }
+                                next_ends.extend(
+                                    (i..=position)
+                                        .filter(|&j| self.derives(*next_var, i, j, false)),
+                                );
+                            }
+                        }
+                    }
+                    ends = next_ends;
+                }
+            }
+        }
+
+        ParseError::new(self.word, position, expected, vars, &parser.var_names)
+    }
+
+    fn trees(&mut self, var: Var, i: usize, j: usize) -> Vec<ParseTree<T>> {
+        let key = (var, i, j, false);
+        if let Some(trees) = self.memo.get(&(var, i, j)) {
+            // The trees going through a variable and part of the word being parsed above would go
+            // around a cycle here.
+            let trees = trees.clone();
+            return trees
+                .into_iter()
+                .filter(|tree| match self.lowest_on_path(tree, i).1 {
+                    Some(depth) => {
+                        self.lowest_blocked = self.lowest_blocked.min(depth);
+                        false
+                    }
+                    None => true,
+                })
+                .collect();
+        }
+        if let Some(&depth) = self.path.get(&key) {
+            self.lowest_blocked = self.lowest_blocked.min(depth);
+            return Vec::new();
+        }
+
+        let depth = self.path.len();
+        self.path.insert(key, depth);
+        let outer_lowest_blocked = std::mem::replace(&mut self.lowest_blocked, usize::MAX);
+
+        let parser = self.parser;
+        let mut trees = Vec::new();
+        for &rule_idx in parser.rules_by_var[var.0].iter() {
+            for children in self.sequences(&parser.rules[rule_idx].body, i, j) {
+                trees.push(ParseTree {
+                    root_var: var,
+                    root_var_name: parser.var_names[var.0].clone(),
+                    rule_idx,
+                    children,
+                });
+            }
+        }
+
+        self.path.remove(&key);
+        if self.lowest_blocked >= depth {
+            self.memo.insert((var, i, j), trees.clone());
+        }
+        self.lowest_blocked = self.lowest_blocked.min(outer_lowest_blocked);
+        trees
+    }
+
+    /// Returns every way of deriving the part of the word from `i` to `j` by the symbols.
+    fn sequences(
+        &mut self,
+        symbols: &[Symbol<T>],
+        i: usize,
+        j: usize,
+    ) -> Vec<Vec<ParsedSymbol<T>>> {
+        let Some((symbol, rest)) = symbols.split_first() else {
+            return if i == j { vec![Vec::new()] } else { Vec::new() };
+        };
+        let Some(rest_min_len) = self.min_len(rest) else {
+            return Vec::new();
+        };
+
+        let mut sequences = Vec::new();
+        match symbol {
+            Symbol::Term(term) => {
+                if i + 1 + rest_min_len <= j && self.word[i] == *term {
+                    for mut sequence in self.sequences(rest, i + 1, j) {
+                        sequence.insert(0, ParsedSymbol::Term(term.clone()));
+                        sequences.push(sequence);
+                    }
+                }
+            }
+            Symbol::Var(var) => {
+                let Some(min_len) = self.parser.min_lens[var.0] else {
+                    return Vec::new();
+                };
+                for k in i + min_len..=j.saturating_sub(rest_min_len) {
+                    let trees = self.trees(*var, i, k);
+                    if trees.is_empty() {
+                        continue;
+                    }
+                    let rest_sequences = self.sequences(rest, k, j);
+                    for tree in trees.iter() {
+                        for rest_sequence in rest_sequences.iter() {
+                            let mut sequence = vec![ParsedSymbol::Var(Box::new(tree.clone()))];
+                            sequence.extend(rest_sequence.iter().cloned());
+                            sequences.push(sequence);
+                        }
+                    }
+                }
+            }
+        }
+        sequences
+    }
+
+    /// Returns where the tree starting at `start` ends, along with the lowest depth of the path at
+    /// which one of the variables and parts of the word of the tree is being parsed, if any is.
+    fn lowest_on_path(&self, parse_tree: &ParseTree<T>, start: usize) -> (usize, Option<usize>) {
+        let mut end = start;
+        let mut lowest = None;
+        for child in parse_tree.children.iter() {
+            match child {
+                ParsedSymbol::Term(_) => end += 1,
+                ParsedSymbol::Var(child) => {
+                    let (child_end, child_lowest) = self.lowest_on_path(child, end);
+                    end = child_end;
+                    lowest = lowest.into_iter().chain(child_lowest).min();
+                }
+                ParsedSymbol::Error(terms) => end += terms.len(),
+            }
+        }
+        let depth = self.path.get(&(parse_tree.root_var, start, end, false));
+        (end, lowest.into_iter().chain(depth.copied()).min())
+    }
+
+    fn min_len(&self, symbols: &[Symbol<T>]) -> Option<usize> {
+        symbols.iter().try_fold(0, |len, symbol| match symbol {
+            Symbol::Term(_) => Some(len + 1),
+            Symbol::Var(var) => Some(len + self.parser.min_lens[var.0]?),
+        })
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{cnf::Cnf, Cfg, Term, Var};
+
+use super::{
+    cyk::{contains_bit, insert_bit, ones, BitChart},
+    CykParser, ParseError, ParseForest, ParseTree, Parser,
+};
+
+/// A CYK parser which reduces filling the chart to boolean matrix multiplication, following the
+/// simplified version of Valiant's algorithm by Okhotin (2014).
+///
+/// The chart is split into square blocks, and every block is computed from products of blocks
+/// closer to the diagonal, in an order which ensures that the factors of every product are complete
+/// by the time it is taken. The products are taken on one bit matrix per variable of the CNF, by
+/// the method of the Four Russians for blocks of 64 rows or more, and row by row for the smaller
+/// blocks near the diagonal. Once the chart is filled, parse trees are rebuilt from it exactly as
+/// by [`CykParser`].
+///
+/// With the method of the Four Russians, a product of blocks of size `b` takes `O(b^3 / (64 log
+/// b))` time, which makes the parser run in `O(n^3 / (64 log n))` time for a word of length `n`:
+/// subcubic, though only by a logarithmic factor. The example `compare-valiant-with-cyk` times both
+/// parsers on words of growing length. Two matrices of `m^2` bits are kept for every variable of
+/// the CNF, where `m` is `n + 1` rounded up to a power of two, so the parser is best suited to long
+/// words over small grammars.
+#[derive(Debug, Clone)]
+pub struct ValiantParser<T: Term> {
+    cyk: CykParser<T>,
+}
+
+impl<T: Term> ValiantParser<T> {
+    pub fn of(cfg: Cfg<T>) -> Self {
+        Self {
+            cyk: CykParser::of(cfg),
+        }
+    }
+
+    pub fn of_cnf(cnf: Cnf<T>, start: Var) -> Self {
+        Self {
+            cyk: CykParser::of_cnf(cnf, start),
+        }
+    }
+
+    fn fill_tables(&self, word: &[T]) -> Tables {
+        let n = word.len();
+        let size = (n + 1).next_power_of_two();
+        let n_vars = self.cyk.heads.len();
+
+        let mut tables = Tables {
+            derived: vec![BitMatrix::new(size); n_vars],
+            products: vec![BitMatrix::new(size); n_vars],
+        };
+        for (i, term) in word.iter().enumerate() {
+            let Some(vars) = self.cyk.terms_to_vars.get(term) else {
+                continue;
+            };
+            for var in vars {
+                tables.derived[var.0].insert(i, i + 1);
+            }
+        }
+
+        let mut filler = TableFiller {
+            heads: &self.cyk.heads,
+            tables,
+            z_words: Vec::new(),
+            table: Vec::new(),
+            product: Vec::new(),
+        };
+        if n > 0 {
+            filler.compute(0, size);
+        }
+        filler.tables
+    }
+
+    /// Fills the tables, and copies them into a chart from which the [`CykParser`] can rebuild
+    /// parse trees.
+    fn fill_chart(&self, word: &[T]) -> BitChart {
+        let n = word.len();
+        let tables = self.fill_tables(word);
+
+        let mut chart = BitChart::new(n, self.cyk.n_words);
+        for (var, derived) in tables.derived.iter().enumerate() {
+            for i in 0..n {
+                for j in i + 1..=n {
+                    if derived.contains(i, j) {
+                        insert_bit(chart.cell_mut(i, j - 1), var);
+                    }
+                }
+            }
+        }
+        chart
+    }
+}
+
+impl<T: Term> Parser<Vec<T>> for ValiantParser<T> {
+    type TermType = T;
+
+    fn test(&self, word: Vec<T>) -> bool {
+        if word.is_empty() {
+            return self.cyk.nullable[self.cyk.start.0];
+        }
+
+        let tables = self.fill_tables(&word);
+        let start = self.cyk.vars_map[self.cyk.start.0];
+        tables.derived[start.0].contains(0, word.len())
+    }
+
+    fn parse(&self, word: Vec<T>) -> Result<ParseTree<T>, ParseError<T>> {
+        let chart = self.fill_chart(&word);
+        self.cyk.parse_chart(word, chart)
+    }
+
+    fn parse_forest(&self, word: Vec<T>) -> Option<ParseForest<'_, T>> {
+        let chart = self.fill_chart(&word);
+        self.cyk.chart_forest(&word, &chart)
+    }
+
+    fn parse_prefix(&self, word: Vec<T>) -> Option<(ParseTree<T>, usize)> {
+        let chart = self.fill_chart(&word);
+        self.cyk.chart_prefix(word, chart)
+    }
+}
+
+/// For every variable `A` of the CNF, the matrix of spans `i..j` derived by `A`, and the matrix of
+/// spans `i..j` for which a rule `A => BC` has been found to apply so far.
+struct Tables {
+    derived: Vec<BitMatrix>,
+    products: Vec<BitMatrix>,
+}
+
+struct TableFiller<'a> {
+    heads: &'a [Vec<(usize, Vec<u64>)>],
+    tables: Tables,
+    /// Buffers for [`TableFiller::multiply`], kept between products so that the many small ones
+    /// near the diagonal allocate nothing.
+    z_words: Vec<(usize, u64)>,
+    table: Vec<u64>,
+    product: Vec<u64>,
+}
+
+impl TableFiller<'_> {
+    /// Fills in every span `i..j` with `l <= i < j < m`, assuming `m - l` is a power of two.
+    fn compute(&mut self, l: usize, m: usize) {
+        let mid = (l + m) / 2;
+        if m - l >= 4 {
+            self.compute(l, mid);
+            self.compute(mid, m);
+        }
+        self.complete(l, mid, mid, m);
+    }
+
+    /// Fills in every span `i..j` with `i` in `l..m` and `j` in `l2..m2`, assuming that the spans
+    /// within `l..m` and within `l2..m2` are filled in, and that the products for these spans
+    /// already account for every split between `m` and `l2`.
+    fn complete(&mut self, l: usize, m: usize, l2: usize, m2: usize) {
+        if m - l == 1 {
+            // The span of a single terminal was filled in beforehand.
+            if m < l2 {
+                let Tables { derived, products } = &mut self.tables;
+                for (derived, products) in derived.iter_mut().zip(products.iter()) {
+                    if products.contains(l, l2) {
+                        derived.insert(l, l2);
+                    }
+                }
+            }
+            return;
+        }
+
+        let (b, b2) = ((l, (l + m) / 2), ((l + m) / 2, m));
+        let (c, c2) = ((l2, (l2 + m2) / 2), ((l2 + m2) / 2, m2));
+
+        self.complete(b2.0, b2.1, c.0, c.1);
+        self.multiply(b, b2, c);
+        self.complete(b.0, b.1, c.0, c.1);
+        self.multiply(b2, c, c2);
+        self.complete(b2.0, b2.1, c2.0, c2.1);
+        self.multiply(b, b2, c2);
+        self.multiply(b, c, c2);
+        self.complete(b.0, b.1, c2.0, c2.1);
+    }
+
+    /// For every rule `A => BC`, adds to the products of `A` the spans `i..k` with `i` in `x`, for
+    /// which there is a `j` in `y` such that `B` derives `i..j` and `C` derives `j..k` with `k` in
+    /// `z`.
+    fn multiply(&mut self, x: (usize, usize), y: (usize, usize), z: (usize, usize)) {
+        if x.1 - x.0 < 64 {
+            self.multiply_by_rows(x, y, z);
+        } else {
+            self.multiply_by_tables(x, y, z);
+        }
+    }
+
+    /// Takes the product for blocks narrower than a word, for which making tables does not pay off,
+    /// with one union of a row of `C` for every bit of the rows of `B`.
+    fn multiply_by_rows(&mut self, x: (usize, usize), y: (usize, usize), z: (usize, usize)) {
+        let Self {
+            heads,
+            tables: Tables { derived, products },
+            ..
+        } = self;
+        let (w, mask) = word_mask(y);
+        let (z_w, z_mask) = word_mask(z);
+
+        for (second, heads) in heads.iter().enumerate() {
+            for (third, heads) in heads {
+                for i in x.0..x.1 {
+                    let mut bits = derived[second].row(i)[w] & mask;
+                    let mut row = 0;
+                    while bits != 0 {
+                        let j = w * 64 + bits.trailing_zeros() as usize;
+                        bits &= bits - 1;
+                        row |= derived[*third].row(j)[z_w] & z_mask;
+                    }
+                    if row == 0 {
+                        continue;
+                    }
+                    for first in ones(heads) {
+                        products[first].row_mut(i)[z_w] |= row;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes the product by the method of the Four Russians (Arlazarov et al., 1970). The columns
+    /// `y` are split into chunks of `t` bits, and for every chunk, a table is made of the unions of
+    /// the rows of `C` for each of the `2^t` subsets of the chunk. The part of the row of the
+    /// product for a chunk is then looked up in the table by the bits of the row of `B` in the
+    /// chunk, which takes `|y| / t` lookups per row instead of one union per bit.
+    fn multiply_by_tables(&mut self, x: (usize, usize), y: (usize, usize), z: (usize, usize)) {
+        let Self {
+            heads,
+            tables: Tables { derived, products },
+            z_words,
+            table,
+            product,
+        } = self;
+        word_masks(z, z_words);
+        let n_words = z_words.len();
+        let chunk_len = chunk_len(x.1 - x.0);
+        let chunk_mask = (1 << chunk_len) - 1;
+        table.resize((1 << chunk_len) * n_words, 0);
+        product.resize((x.1 - x.0) * n_words, 0);
+
+        for (second, heads) in heads.iter().enumerate() {
+            for (third, heads) in heads {
+                product.fill(0);
+                let mut is_empty = true;
+                for start in (y.0..y.1).step_by(chunk_len) {
+                    let (w, shift) = (start / 64, start % 64);
+                    let chunk = |i: usize| (derived[second].row(i)[w] >> shift) & chunk_mask;
+                    if (x.0..x.1).all(|i| chunk(i) == 0) {
+                        continue;
+                    }
+                    // Every subset is the union of the subset without its lowest bit and the row of
+                    // that bit, which comes before it in the table.
+                    for subset in 1..1 << chunk_len {
+                        let (done, rest) = table.split_at_mut(subset * n_words);
+                        let smaller = &done[(subset & (subset - 1)) * n_words..][..n_words];
+                        let row = derived[*third].row(start + subset.trailing_zeros() as usize);
+                        for ((bits, smaller), &(w, mask)) in
+                            rest.iter_mut().zip(smaller).zip(z_words.iter())
+                        {
+                            *bits = smaller | row[w] & mask;
+                        }
+                    }
+                    for (i, product_row) in (x.0..x.1).zip(product.chunks_mut(n_words)) {
+                        let subset = chunk(i) as usize;
+                        if subset == 0 {
+                            continue;
+                        }
+                        is_empty = false;
+                        let union = &table[subset * n_words..][..n_words];
+                        for (bits, union) in product_row.iter_mut().zip(union) {
+                            *bits |= union;
+                        }
+                    }
+                }
+                if is_empty {
+                    continue;
+                }
+                for first in ones(heads) {
+                    for (i, product_row) in (x.0..x.1).zip(product.chunks(n_words)) {
+                        let products_row = products[first].row_mut(i);
+                        for (bits, &(w, _)) in product_row.iter().zip(z_words.iter()) {
+                            products_row[w] |= bits;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The number of bits of the chunks of the Four Russians' method for blocks of the given size,
+/// which is about the logarithm of the size, so that making the tables takes no longer than
+/// looking up the rows in them. It divides 64, so that no chunk straddles two words.
+fn chunk_len(size: usize) -> usize {
+    match size {
+        256.. => 8,
+        16.. => 4,
+        4.. => 2,
+        _ => 1,
+    }
+}
+
+/// Puts the words of a bitset covering the given range of bits into `words`, along with the masks
+/// of the bits of each word which are in the range.
+fn word_masks((start, end): (usize, usize), words: &mut Vec<(usize, u64)>) {
+    words.clear();
+    words.extend(
+        (start / 64..end.div_ceil(64))
+            .map(|w| word_mask((start.max(w * 64), end.min(w * 64 + 64)))),
+    );
+}
+
+/// The word of a bitset holding the given range of bits, which lies within a single word, along
+/// with the mask of the bits in the range.
+fn word_mask((start, end): (usize, usize)) -> (usize, u64) {
+    let (w, lo, hi) = (start / 64, start % 64, end - start / 64 * 64);
+    let mask = if hi - lo == 64 {
+        !0
+    } else {
+        ((1 << (hi - lo)) - 1) << lo
+    };
+    (w, mask)
+}
+
+/// A square boolean matrix with its rows stored as bitsets.
+#[derive(Debug, Clone)]
+struct BitMatrix {
+    n_words: usize,
+    bits: Vec<u64>,
+}
+
+impl BitMatrix {
+    fn new(size: usize) -> Self {
+        let n_words = size.div_ceil(64);
+        Self {
+            n_words,
+            bits: vec![0; size * n_words],
+        }
+    }
+
+    fn row(&self, i: usize) -> &[u64] {
+        &self.bits[i * self.n_words..(i + 1) * self.n_words]
+    }
+
+    fn row_mut(&mut self, i: usize) -> &mut [u64] {
+        &mut self.bits[i * self.n_words..(i + 1) * self.n_words]
+    }
+
+    fn insert(&mut self, i: usize, j: usize) {
+        insert_bit(self.row_mut(i), j);
+    }
+
+    fn contains(&self, i: usize, j: usize) -> bool {
+        contains_bit(self.row(i), j)
+    }
+}
@@ -43,12 +43,12 @@ struct InterpretedGrammar {
     interpreted_rule_sets: Vec<InterpretedRuleSet>,
 }
 
-impl Into<Grammar> for InterpretedGrammar {
-    fn into(self) -> Grammar {
-        let Self {
+impl From<InterpretedGrammar> for Grammar {
+    fn from(interpreted_grammar: InterpretedGrammar) -> Self {
+        let InterpretedGrammar {
             interpreted_rule_sets,
             ..
-        } = self;
+        } = interpreted_grammar;
         Grammar {
             rule_sets: interpreted_rule_sets
                 .into_iter()
@@ -64,9 +64,9 @@ struct InterpretedRuleSet {
     bodies: Vec<(Vec<Symbol>, Action)>,
 }
 
-impl Into<RuleSet> for InterpretedRuleSet {
-    fn into(self) -> RuleSet {
-        let Self { head, bodies } = self;
+impl From<InterpretedRuleSet> for RuleSet {
+    fn from(interpreted_rule_set: InterpretedRuleSet) -> Self {
+        let InterpretedRuleSet { head, bodies } = interpreted_rule_set;
         RuleSet {
             head,
             bodies: bodies.into_iter().map(|(symbols, _)| symbols).collect(),
@@ -9,7 +9,7 @@ fn main() {
         sum => product { _1 } | product '+' sum { _1 + _3 } | product '-' sum { _1 - _3 }
         product => term { _1 } | term '*' product { _1 * _3 } | term '/' product { _1 / _3 }
         term => number { _1 } | '(' expr ')' { _2 }
-        number => digit { _1 } | number digit { _1 * (10 as f64) + _2 }
+        number => digit { _1 } | number digit { _1 * 10.0 + _2 }
         digit =>
             '0' { 0.0 } |
             '1' { 1.0 } |
@@ -42,9 +42,12 @@ For example, typing in `(10*3)/2+7` will produce 22."
         };
 
         let word: Vec<char> = input.chars().collect();
-        let Some(parse_tree) = parser.parse(word) else {
-            println!("Ill-formed expression");
-            continue;
+        let parse_tree = match parser.parse(word.clone()) {
+            Ok(parse_tree) => parse_tree,
+            Err(parse_error) => {
+                println!("{}", parse_error.in_word(&word));
+                continue;
+            }
         };
         println!("{}", interpreter.interpret(parse_tree));
     }
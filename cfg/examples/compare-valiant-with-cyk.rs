@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+use cfg::{parse::ValiantParser, prelude::*};
+
+/// Times recognizing words of growing length with the CYK and Valiant parsers, on a grammar for
+/// which every span of the word is derived, so that neither parser can skip over empty cells.
+fn main() {
+    let cfg = grammar!(
+        start => start start | 'a' | 'b' start
+    );
+    let cyk_parser = CykParser::of(cfg.clone());
+    let valiant_parser = ValiantParser::of(cfg);
+
+    println!("{:>6} {:>12} {:>12} {:>8}", "n", "cyk", "valiant", "ratio");
+    let mut n = 128;
+    while n <= 2048 {
+        let word = vec!['a'; n];
+        let cyk_time = time(|| assert!(cyk_parser.test(word.clone())));
+        let valiant_time = time(|| assert!(valiant_parser.test(word.clone())));
+        println!(
+            "{:>6} {:>12.3?} {:>12.3?} {:>8.1}",
+            n,
+            cyk_time,
+            valiant_time,
+            cyk_time.as_secs_f64() / valiant_time.as_secs_f64()
+        );
+        n *= 2;
+    }
+}
+
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
@@ -1,4 +1,8 @@
-use cfg::{grammar, parse::CykParser, parse::Parser};
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{CykParser, EarleyParser, Parser},
+    Cfg, Rule, Symbol, Var,
+};
 
 mod test_cases;
 
@@ -17,7 +21,7 @@ fn cyk_parser_tests_brkt_seqs() {
             for ch in &brkt_seq {
                 print!("{}", ch);
             }
-            print!("\n");
+            println!();
             assert_eq!(cyk_parser.test(brkt_seq), expectation);
         }
     }
@@ -39,3 +43,246 @@ fn cyk_parser_tests_expressions() {
         assert_eq!(cyk_parser.test(word), *expected_result);
     }
 }
+
+#[test]
+fn cyk_parser_parses_in_original_grammar() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | product '+' sum { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | term '*' term '*' product { format!("(* {} {} {})", _1, _3, _5) }
+        term => number { _1 } | '(' expr ')' { _2 }
+        number => digit { _1 } | number digit { format!("{}{}", _1, _2) }
+        digit => '0' { "0".to_string() } | '1' { "1".to_string() } | '2' { "2".to_string() }
+    );
+
+    let cyk_parser = CykParser::of(cfg.clone());
+    let earley_parser = EarleyParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("1", Some("1")),
+        ("102", Some("102")),
+        ("1+2", Some("(+ 1 2)")),
+        ("1*2*10+(2+0)", Some("(+ (* 1 2 10) (+ 2 0))")),
+        ("((1))", Some("1")),
+        ("1*2", None),
+        ("1+", None),
+    ] {
+        let word: Vec<char> = word.chars().collect();
+        let result = cyk_parser
+            .parse(word.clone())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+        let earley_result = earley_parser
+            .parse(word)
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result, earley_result);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_with_unit_cycles() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        s => a { _1 }
+        a => b { format!("a({})", _1) } | 'x' { "x".to_string() } | a 'y' { format!("{}y", _1) }
+        b => a { format!("b({})", _1) } | c { format!("b({})", _1) }
+        c => 'z' { "z".to_string() }
+    );
+
+    let cyk_parser = CykParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("x", "x"),
+        ("z", "a(b(z))"),
+        ("zy", "a(b(z))y"),
+        ("xyy", "xyy"),
+    ] {
+        let parse_tree = cyk_parser
+            .parse(word.chars().collect())
+            .expect("word is correctly parsed");
+        assert_eq!(interpreter.interpret(parse_tree), expected_result);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_brkt_seqs() {
+    let (dyck_grammar, interpreter) = interpreted_grammar!(
+        char, String,
+        start => '(' ')' { "()".to_string() }
+            | '(' start ')' { format!("({})", _2) }
+            | start start { format!("{}{}", _1, _2) }
+    );
+
+    let cyk_parser = CykParser::of(dyck_grammar);
+
+    for len in 1..10 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            let expected_result = expectation.then(|| brkt_seq.iter().collect::<String>());
+            let result = cyk_parser
+                .parse(brkt_seq)
+                .map(|parse_tree| interpreter.interpret(parse_tree));
+            assert_eq!(result.ok(), expected_result);
+        }
+    }
+}
+
+#[test]
+fn cyk_parser_tests_optional_parts() {
+    let cfg = grammar!(
+        start => opt_sign list opt_dot
+        opt_sign => | '-'
+        list => | item list
+        item => 'a' | '(' start ')'
+        opt_dot => | '.' | '.' '.'
+    );
+
+    let cyk_parser = CykParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("", true),
+        ("-", true),
+        (".", true),
+        ("..", true),
+        ("...", false),
+        ("a", true),
+        ("-aaa..", true),
+        ("()", true),
+        ("(-)", true),
+        ("a(-a.)a.", true),
+        ("--a", false),
+        (".a", false),
+        ("(a", false),
+        ("a)", false),
+    ] {
+        let word = word.chars().collect();
+        assert_eq!(cyk_parser.test(word), expected_result);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_with_epsilon_rules() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        start => opt_sign list { format!("{}{}", _1, _2) }
+        opt_sign => { "+".to_string() } | '-' { "-".to_string() }
+        list => { "[]".to_string() } | item list { format!("{}:{}", _1, _2) }
+        item => 'a' { "a".to_string() } | '(' start ')' { format!("({})", _2) }
+    );
+
+    let cyk_parser = CykParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("", Some("+[]")),
+        ("-", Some("-[]")),
+        ("a", Some("+a:[]")),
+        ("-a()a", Some("-a:(+[]):a:[]")),
+        ("(-(a))", Some("+(-(+a:[]):[]):[]")),
+        ("a-", None),
+    ] {
+        let result = cyk_parser
+            .parse(word.chars().collect())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_with_nullable_cycles() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        start => a a { format!("{}{}", _1, _2) }
+        a => { String::new() } | b { _1 } | 'x' a { format!("x{}", _2) }
+        b => a 'y' a { format!("{}y{}", _1, _3) } | start { _1 }
+    );
+
+    let cyk_parser = CykParser::of(cfg);
+
+    for word in ["", "x", "y", "xx", "xyx", "yxyyx"] {
+        let parse_tree = cyk_parser
+            .parse(word.chars().collect())
+            .expect("word is correctly parsed");
+        // The grammar is ambiguous, so only check that the parse tree yields the word.
+        assert_eq!(interpreter.interpret(parse_tree), word);
+    }
+    assert!(cyk_parser.parse("xz".chars().collect()).is_err());
+}
+
+#[test]
+fn cyk_parser_tests_grammars_with_many_vars() {
+    let n_vars = 150;
+    let mut cfg_builder = Cfg::builder();
+    let vars: Vec<Var> = (0..n_vars)
+        .map(|i| cfg_builder.add_var(format!("v{}", i).into()))
+        .collect();
+    for i in 0..n_vars {
+        cfg_builder.add_rules([
+            Rule::new(
+                vars[i],
+                vec![Symbol::Var(vars[(i + 1) % n_vars]), Symbol::Term('a')],
+            ),
+            Rule::new(
+                vars[i],
+                vec![Symbol::Term('b'), Symbol::Var(vars[(i * 7) % n_vars])],
+            ),
+            Rule::new(vars[i], vec![Symbol::Term(['a', 'b', 'c'][i % 3])]),
+        ]);
+    }
+    let cfg = cfg_builder.build(vars[0]);
+
+    let cyk_parser = CykParser::of(cfg.clone());
+    let earley_parser = EarleyParser::of(cfg);
+
+    for word in test_cases::all_words(&['a', 'b', 'c'], 6) {
+        assert_eq!(
+            cyk_parser.test(word.clone()),
+            earley_parser.test(word.clone()),
+            "{:?}",
+            word
+        );
+    }
+}
+
+#[test]
+fn cyk_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&cyk_parser, cfg);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&cyk_parser, cfg);
+    }
+}
+
+#[test]
+fn cyk_parser_finds_parse_errors() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&cyk_parser, cfg, &['x', 'y']);
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&cyk_parser, cfg, alphabet);
+    }
+}
+
+#[test]
+fn cyk_parser_parses_longest_prefixes() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(
+            &cyk_parser,
+            cfg,
+            &['x', 'y'],
+        );
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let cyk_parser = CykParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(&cyk_parser, cfg, alphabet);
+    }
+}
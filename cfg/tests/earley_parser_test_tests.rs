@@ -1,4 +1,14 @@
-use cfg::{grammar, parse::EarleyParser, parse::Parser};
+use std::{collections::BTreeSet, ops::Range, rc::Rc};
+
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{
+        BigUint, CykParser, EarleyParser, EarleySession, Edit, EditCosts, ParseCount, ParseTree,
+        ParsedSymbol, Parser, SharedParseTree, SharedParsedSymbol, UngerParser, UnitEditCosts,
+    },
+    Cfg,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 mod test_cases;
 
@@ -17,7 +27,7 @@ fn earley_parser_tests_brkt_seqs() {
             for ch in &brkt_seq {
                 print!("{}", ch);
             }
-            print!("\n");
+            println!();
             assert_eq!(earley_parser.test(brkt_seq), expectation);
         }
     }
@@ -39,3 +49,1215 @@ fn earley_parser_tests_expressions() {
         assert_eq!(earley_parser.test(word), *expected_result);
     }
 }
+
+fn nullable_grammars() -> Vec<(Cfg<char>, &'static [char])> {
+    vec![
+        (
+            grammar!(
+                start => e t
+                e =>
+                t => e 'x'
+            ),
+            &['x'],
+        ),
+        (
+            grammar!(
+                start => a a 'x'
+                a => b
+                b =>
+            ),
+            &['x'],
+        ),
+        (
+            grammar!(
+                start => a b c 'x' c b a
+                a => b | 'a' |
+                b => c c | 'b'
+                c => a |
+            ),
+            &['a', 'b', 'x'],
+        ),
+        (
+            grammar!(
+                start => start start | '(' start ')' | a
+                a => b b b
+                b => a | 'x' |
+            ),
+            &['(', ')', 'x'],
+        ),
+        (
+            grammar!(
+                start => list
+                list => | item opt_sep list
+                item => 'a' | '[' list ']'
+                opt_sep => | ','
+            ),
+            &['a', '[', ']', ','],
+        ),
+    ]
+}
+
+#[test]
+fn earley_parser_tests_nullable_grammars() {
+    for (cfg, alphabet) in nullable_grammars() {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+
+        for word in test_cases::all_words(alphabet, 6) {
+            assert_eq!(
+                earley_parser.test(word.clone()),
+                cyk_parser.test(word.clone()),
+                "{:?}",
+                word
+            );
+            if let Ok(parse_tree) = earley_parser.parse(word.clone()) {
+                assert_eq!(test_cases::parse_tree_yield(&parse_tree), word);
+            }
+        }
+    }
+}
+
+#[test]
+fn earley_parser_finds_parse_errors() {
+    for (cfg, alphabet) in nullable_grammars() {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+
+        for word in test_cases::all_words(alphabet, 5) {
+            let Err(parse_error) = earley_parser.parse(word.clone()) else {
+                continue;
+            };
+            assert!(!parse_error
+                .expected()
+                .contains(&parse_error.found().cloned()));
+            assert_eq!(
+                earley_parser.test(word[..parse_error.position()].to_vec()),
+                parse_error.expected().contains(&None)
+            );
+            assert_eq!(
+                cyk_parser.parse(word.clone()),
+                Err(parse_error),
+                "{:?}",
+                word
+            );
+        }
+    }
+}
+
+#[test]
+fn earley_parser_formats_parse_errors() {
+    let cfg = grammar!(
+        expr => sum
+        sum => product | product '+' sum
+        product => term | term '*' product
+        term => 'x' | '(' expr ')'
+    );
+    let earley_parser = EarleyParser::of(cfg);
+
+    let word: Vec<_> = "(x+*x)".chars().collect();
+    let parse_error = earley_parser
+        .parse(word.clone())
+        .expect_err("expression is ill-formed");
+    assert_eq!(parse_error.position(), 3);
+    assert_eq!(parse_error.found(), Some(&'*'));
+    assert_eq!(
+        parse_error.expected().iter().collect::<Vec<_>>(),
+        vec![&Some('('), &Some('x')]
+    );
+    assert_eq!(
+        parse_error
+            .vars()
+            .map(|(_, var_name)| var_name)
+            .collect::<Vec<_>>(),
+        vec!["sum"]
+    );
+    assert_eq!(
+        parse_error.to_string(),
+        "unexpected '*' at position 3, expected one of '(', 'x' while parsing sum"
+    );
+    assert_eq!(
+        parse_error.in_word(&word).to_string(),
+        "unexpected '*' at position 3, expected one of '(', 'x' while parsing sum\n(x+*x)\n   ^"
+    );
+
+    let word: Vec<_> = "(x".chars().collect();
+    let parse_error = earley_parser
+        .parse(word.clone())
+        .expect_err("expression is ill-formed");
+    assert_eq!(
+        parse_error.in_word(&word).to_string(),
+        "unexpected end of input at position 2, expected one of ')', '*', '+' while parsing term, product, sum\n(x\n  ^"
+    );
+}
+
+fn apply_edits(word: &[char], edits: &[Edit<char>]) -> Vec<char> {
+    let mut edits = edits.iter().peekable();
+    let mut edited_word = Vec::new();
+    for position in 0..=word.len() {
+        while let Some(Edit::Insert { term, .. }) =
+            edits.next_if(|edit| matches!(edit, Edit::Insert { position: p, .. } if *p == position))
+        {
+            edited_word.push(*term);
+        }
+        match edits.next_if(|edit| match edit {
+            Edit::Delete { position: p, .. } | Edit::Substitute { position: p, .. } => {
+                *p == position
+            }
+            Edit::Insert { .. } => false,
+        }) {
+            Some(Edit::Substitute { replacement, .. }) => edited_word.push(*replacement),
+            Some(_) => {}
+            None => edited_word.extend(word.get(position)),
+        }
+    }
+    assert!(edits.next().is_none());
+    edited_word
+}
+
+fn levenshtein_distance(word: &[char], other_word: &[char]) -> usize {
+    let mut distances: Vec<usize> = (0..=other_word.len()).collect();
+    for (i, ch) in word.iter().enumerate() {
+        let mut next_distances = vec![i + 1];
+        for (j, other_ch) in other_word.iter().enumerate() {
+            next_distances.push(
+                (distances[j] + usize::from(ch != other_ch))
+                    .min(distances[j + 1] + 1)
+                    .min(next_distances[j] + 1),
+            );
+        }
+        distances = next_distances;
+    }
+    distances[other_word.len()]
+}
+
+#[test]
+fn earley_parser_corrects_words() {
+    let cfg = grammar!(
+        start => | '(' start ')' start
+    );
+    let earley_parser = EarleyParser::of(cfg);
+    let alphabet = ['(', ')'];
+
+    // Every word can be corrected by deleting all of it, so the closest words are at most twice
+    // as long.
+    let words_in_language: Vec<_> = test_cases::all_words(&alphabet, 10)
+        .into_iter()
+        .filter(|word| earley_parser.test(word.clone()))
+        .collect();
+
+    for word in test_cases::all_words(&alphabet, 5) {
+        let correction = earley_parser
+            .parse_with_corrections(word.clone(), &UnitEditCosts)
+            .expect("language is not empty");
+        let corrected_word = apply_edits(&word, correction.edits());
+        assert_eq!(
+            test_cases::parse_tree_yield(correction.parse_tree()),
+            corrected_word
+        );
+        assert_eq!(correction.cost(), correction.edits().len() as f64);
+        let distance = words_in_language
+            .iter()
+            .map(|other_word| levenshtein_distance(&word, other_word))
+            .min()
+            .unwrap();
+        assert_eq!(correction.cost(), distance as f64, "{:?}", word);
+        if earley_parser.test(word.clone()) {
+            assert!(correction.edits().is_empty());
+        }
+    }
+}
+
+struct ExpressionEditCosts {
+    insert: f64,
+    delete: f64,
+}
+
+impl EditCosts<char> for ExpressionEditCosts {
+    fn insert(&self, _term: &char) -> f64 {
+        self.insert
+    }
+
+    fn delete(&self, _term: &char) -> f64 {
+        self.delete
+    }
+
+    fn substitute(&self, _found: &char, _replacement: &char) -> f64 {
+        self.insert + self.delete
+    }
+}
+
+#[test]
+fn earley_parser_corrects_words_with_edit_costs() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | product '+' sum { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | term '*' product { format!("(* {} {})", _1, _3) }
+        term => unit { _1 } | '(' sum ')' { _2 }
+        unit => 'A' { "A".to_string() } | 'B' { "B".to_string() }
+    );
+    let earley_parser = EarleyParser::of(cfg);
+    let word: Vec<char> = "(A+*B)".chars().collect();
+
+    let correction = earley_parser
+        .parse_with_corrections(
+            word.clone(),
+            &ExpressionEditCosts {
+                insert: 1.0,
+                delete: 2.0,
+            },
+        )
+        .expect("language is not empty");
+    assert_eq!(
+        correction.edits(),
+        [Edit::Insert {
+            position: 3,
+            term: 'A'
+        }]
+    );
+    assert_eq!(correction.cost(), 1.0);
+    assert_eq!(
+        interpreter.interpret(correction.into_parse_tree()),
+        "(+ A (* A B))"
+    );
+
+    let correction = earley_parser
+        .parse_with_corrections(
+            word,
+            &ExpressionEditCosts {
+                insert: 2.0,
+                delete: 1.0,
+            },
+        )
+        .expect("language is not empty");
+    assert_eq!(
+        correction.edits(),
+        [Edit::Delete {
+            position: 3,
+            term: '*'
+        }]
+    );
+    assert_eq!(
+        interpreter.interpret(correction.into_parse_tree()),
+        "(+ A B)"
+    );
+}
+
+#[test]
+fn earley_parser_does_not_correct_words_of_empty_languages() {
+    let cfg = grammar!(
+        start => 'a' start
+    );
+    let earley_parser = EarleyParser::of(cfg);
+
+    assert!(earley_parser
+        .parse_with_corrections(vec!['a'], &UnitEditCosts)
+        .is_none());
+}
+
+#[test]
+fn earley_parser_recovers_from_errors() {
+    for (cfg, alphabet) in nullable_grammars() {
+        let earley_parser = EarleyParser::of(cfg);
+
+        for word in test_cases::all_words(alphabet, 5) {
+            let recovery = earley_parser
+                .parse_with_recovery(word.clone(), &alphabet[alphabet.len() - 1..])
+                .expect("language is not empty");
+            assert_eq!(test_cases::parse_tree_yield(recovery.parse_tree()), word);
+            match earley_parser.parse(word.clone()) {
+                Ok(parse_tree) => {
+                    assert_eq!(recovery.parse_tree(), &parse_tree);
+                    assert!(recovery.diagnostics().is_empty());
+                }
+                Err(parse_error) => assert_eq!(recovery.diagnostics()[0], parse_error),
+            }
+        }
+    }
+}
+
+fn statements_grammar() -> Cfg<char> {
+    grammar!(
+        program => | stmt program
+        stmt => var '=' expr ';'
+        expr => term | term '+' expr
+        term => var | '1' | '(' expr ')'
+        var => 'x' | 'y'
+    )
+}
+
+fn child_var_names(parse_tree: &ParseTree<char>) -> Vec<String> {
+    parse_tree
+        .children()
+        .iter()
+        .map(|child| match child {
+            ParsedSymbol::Term(term) => term.to_string(),
+            ParsedSymbol::Var(parse_tree) => parse_tree.root_var_name().to_string(),
+            ParsedSymbol::Error(terms) => format!("<ERROR {}>", String::from_iter(terms)),
+        })
+        .collect()
+}
+
+#[test]
+fn earley_parser_recovers_at_synchronizing_terms() {
+    let earley_parser = EarleyParser::of(statements_grammar());
+
+    let recovery = earley_parser
+        .parse_with_recovery("x=+1;y=(1+);x=1;".chars().collect(), &[';', ')'])
+        .expect("language is not empty");
+    let positions: Vec<_> = recovery
+        .diagnostics()
+        .iter()
+        .map(|parse_error| parse_error.position())
+        .collect();
+    assert_eq!(positions, vec![2, 10]);
+
+    let mut stmts = Vec::new();
+    let mut program = recovery.parse_tree();
+    while let [ParsedSymbol::Var(stmt), ParsedSymbol::Var(rest)] = program.children() {
+        stmts.push(child_var_names(stmt));
+        program = rest;
+    }
+    assert_eq!(
+        stmts,
+        vec![
+            vec!["var", "=", "<ERROR +1>", ";"],
+            vec!["var", "=", "expr", ";"],
+            vec!["var", "=", "expr", ";"],
+        ]
+    );
+    let ParsedSymbol::Var(second_stmt) = &recovery.parse_tree().children()[1] else {
+        panic!("Expected the rest of the program");
+    };
+    let ParsedSymbol::Var(second_stmt) = &second_stmt.children()[0] else {
+        panic!("Expected a statement");
+    };
+    let ParsedSymbol::Var(term) = &second_stmt.children()[2] else {
+        panic!("Expected an expression");
+    };
+    let ParsedSymbol::Var(term) = &term.children()[0] else {
+        panic!("Expected a term");
+    };
+    assert_eq!(child_var_names(term), vec!["(", "expr", "<ERROR +>", ")"]);
+}
+
+#[test]
+fn earley_parser_recovers_at_end_of_input() {
+    let earley_parser = EarleyParser::of(statements_grammar());
+
+    let recovery = earley_parser
+        .parse_with_recovery("x=1;y=".chars().collect(), &[';'])
+        .expect("language is not empty");
+    assert_eq!(recovery.diagnostics().len(), 1);
+    assert_eq!(recovery.diagnostics()[0].position(), 6);
+    assert_eq!(recovery.diagnostics()[0].found(), None);
+    assert_eq!(
+        child_var_names(recovery.parse_tree()),
+        vec!["stmt", "<ERROR y=>"]
+    );
+}
+
+#[test]
+fn earley_parser_recovery_is_interpreted_with_errors() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        program => { String::new() } | stmt program { format!("{}{}", _1, _2) }
+        stmt => var '=' expr ';' { format!("{}={};", _1, _3) }
+        expr => term { _1 } | term '+' expr { format!("(+ {} {})", _1, _3) }
+        term => var { _1 } | '1' { "1".to_string() } | '(' expr ')' { _2 }
+        var => 'x' { "x".to_string() } | 'y' { "y".to_string() }
+    );
+    let earley_parser = EarleyParser::of(cfg);
+
+    let recovery = earley_parser
+        .parse_with_recovery("x=+1;y=(1+);x=1;".chars().collect(), &[';', ')'])
+        .expect("language is not empty");
+    let on_error = |parse_tree: ParseTree<char>| format!("<{}>", parse_tree.root_var_name());
+    assert_eq!(
+        interpreter.interpret_with_errors(recovery.into_parse_tree(), &on_error),
+        "<stmt>y=<term>;x=1;"
+    );
+}
+
+#[test]
+fn earley_session_tells_what_may_come_next() {
+    let mut grammars = nullable_grammars();
+    grammars.push((
+        grammar!(
+            start => 'a' start | 'b' | 'a' dead
+            dead => 'c' dead
+        ),
+        &['a', 'b', 'c'],
+    ));
+
+    for (cfg, alphabet) in grammars {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+        // Whether the word is the beginning of some word in the language, according to the
+        // independently found parse errors of the CYK parser.
+        let is_viable = |word: &[char]| match cyk_parser.parse(word.to_vec()) {
+            Ok(_) => true,
+            Err(parse_error) => parse_error.position() == word.len(),
+        };
+
+        for word in test_cases::all_words(alphabet, 4) {
+            let mut session = earley_parser.start_session();
+            for (i, term) in word.iter().enumerate() {
+                let prefix = &word[..i];
+                assert_eq!(session.word(), prefix);
+                assert_eq!(session.is_complete(), cyk_parser.test(prefix.to_vec()));
+                assert_eq!(session.is_viable(), is_viable(prefix), "{:?}", prefix);
+                let expected: BTreeSet<char> = alphabet
+                    .iter()
+                    .cloned()
+                    .filter(|term| is_viable(&[prefix, &[*term]].concat()))
+                    .collect();
+                assert_eq!(session.expected(), expected, "{:?}", prefix);
+                session.push(*term);
+            }
+            assert_eq!(
+                session.parse().map(|parse_tree| parse_tree.to_parse_tree()),
+                earley_parser.parse(word)
+            );
+        }
+    }
+}
+
+/// Checks that a session agrees with a session given the word from scratch. For ambiguous
+/// grammars, the two may find different parse trees of the word.
+fn assert_same_session(session: &mut EarleySession<char>, fresh_session: &mut EarleySession<char>) {
+    assert_eq!(session.word(), fresh_session.word());
+    assert_eq!(session.is_complete(), fresh_session.is_complete());
+    assert_eq!(session.is_viable(), fresh_session.is_viable());
+    assert_eq!(session.expected(), fresh_session.expected());
+    match (session.parse(), fresh_session.parse()) {
+        (Ok(parse_tree), Ok(_)) => assert_eq!(
+            test_cases::parse_tree_yield(&parse_tree.to_parse_tree()),
+            session.word()
+        ),
+        (parse_result, fresh_parse_result) => assert_eq!(parse_result, fresh_parse_result),
+    }
+}
+
+#[test]
+fn earley_session_reparses_after_edits() {
+    let mut grammars = nullable_grammars();
+    grammars.push((
+        grammar!(
+            list => | list 'a' | list 'b' 'c'
+        ),
+        &['a', 'b', 'c'],
+    ));
+
+    for (cfg, alphabet) in grammars {
+        let earley_parser = EarleyParser::of(cfg);
+        let replacements = test_cases::all_words(alphabet, 2);
+
+        for word in test_cases::all_words(alphabet, 4) {
+            for start in 0..=word.len() {
+                for end in start..=word.len() {
+                    for replacement in replacements.iter() {
+                        let mut session = earley_parser.start_session();
+                        for term in word.iter() {
+                            session.push(*term);
+                        }
+                        session.replace(start..end, replacement.clone());
+
+                        let new_word = [&word[..start], replacement, &word[end..]].concat();
+                        let mut fresh_session = earley_parser.start_session();
+                        for term in new_word {
+                            fresh_session.push(term);
+                        }
+                        assert_same_session(&mut session, &mut fresh_session);
+
+                        session.push(alphabet[0]);
+                        fresh_session.push(alphabet[0]);
+                        assert_same_session(&mut session, &mut fresh_session);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn earley_session_reparses_statements_after_edits() {
+    let earley_parser = EarleyParser::of(grammar!(
+        program => | program stmt
+        stmt => var '=' expr ';'
+        expr => term | term '+' expr
+        term => var | '1' | '(' expr ')'
+        var => 'x' | 'y'
+    ));
+    let word: Vec<_> = "x=1;y=(x+1);".repeat(20).chars().collect();
+
+    let mut session = earley_parser.start_session();
+    for term in word.iter() {
+        session.push(*term);
+    }
+    let edits = [
+        (50..51, "(y+1)"),
+        (0..4, ""),
+        (100..100, "x=y;"),
+        (30..33, "1+"),
+        (31..32, ""),
+        (200..200, "y=1;"),
+        (42..43, "y"),
+        (42..43, "x"),
+    ];
+    for (range, terms) in edits {
+        session.replace(range, terms.chars().collect());
+        let mut fresh_session = earley_parser.start_session();
+        for term in session.word().to_vec() {
+            fresh_session.push(term);
+        }
+        assert_same_session(&mut session, &mut fresh_session);
+        assert_eq!(session.parse(), fresh_session.parse());
+    }
+}
+
+#[test]
+fn earley_session_shares_unedited_subtrees() {
+    let earley_parser = EarleyParser::of(grammar!(
+        program => | program stmt
+        stmt => var '=' expr ';'
+        expr => term | term '+' expr
+        term => var | '1' | '(' expr ')'
+        var => 'x' | 'y'
+    ));
+    // The statements of a parse tree of the grammar, from the last to the first.
+    fn stmts(parse_tree: &SharedParseTree<char>) -> Vec<Rc<SharedParseTree<char>>> {
+        let mut stmts = Vec::new();
+        let mut program = parse_tree;
+        while let [SharedParsedSymbol::Var(rest), SharedParsedSymbol::Var(stmt)] =
+            program.children()
+        {
+            stmts.push(stmt.clone());
+            program = rest;
+        }
+        stmts
+    }
+
+    let mut session = earley_parser.start_session();
+    for term in "x=1;y=(x+1);x=y;y=1;".chars() {
+        session.push(term);
+    }
+    let parse_tree = session.parse().expect("Expected the word to be parsed");
+    session.replace(7..8, "y".chars().collect());
+    let edited_parse_tree = session.parse().expect("Expected the word to be parsed");
+
+    let (stmts, edited_stmts) = (stmts(&parse_tree), stmts(&edited_parse_tree));
+    assert_eq!(stmts.len(), 4);
+    assert_eq!(edited_stmts.len(), 4);
+    for (i, (stmt, edited_stmt)) in stmts.iter().zip(&edited_stmts).enumerate() {
+        assert_eq!(Rc::ptr_eq(stmt, edited_stmt), i != 2);
+    }
+}
+
+#[test]
+fn earley_session_parses_like_earley_parser_after_random_edits() {
+    let grammars = [
+        grammar!(
+            program => | program stmt
+            stmt => var '=' expr ';'
+            expr => term | term '+' expr
+            term => var | '1' | '(' expr ')'
+            var => 'x' | 'y'
+        ),
+        grammar!(
+            program => | stmt program
+            stmt => var '=' expr ';'
+            expr => term | expr '+' term
+            term => var | '1' | '(' expr ')'
+            var => 'x' | 'y'
+        ),
+    ];
+    let stmts = ["x=1;", "y=(x+1);", "x=y+1;"];
+    let terms = ["x", "y", "1", "(x+1)"];
+    let junk = ["(", ")", "+", ";", "=", "x"];
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for cfg in grammars {
+        let earley_parser = EarleyParser::of(cfg);
+        let mut session = earley_parser.start_session();
+        let assert_parses_like_earley_parser = |session: &mut EarleySession<char>| {
+            assert_eq!(
+                session.parse().map(|parse_tree| parse_tree.to_parse_tree()),
+                earley_parser.parse(session.word().to_vec()),
+                "{:?}",
+                session.word()
+            );
+        };
+        for _ in 0..300 {
+            let word = session.word().to_vec();
+            // The positions at which statements start, and those of the terms on the right of
+            // the statements, keeping the word in the language when edited.
+            let stmt_starts: Vec<usize> = (0..=word.len())
+                .filter(|&i| i == 0 || word[i - 1] == ';')
+                .collect();
+            let term_positions: Vec<usize> = (0..word.len())
+                .filter(|&i| i > 0 && word[i - 1] != ';' && matches!(word[i], 'x' | 'y' | '1'))
+                .collect();
+            match rng.gen_range(0..4) {
+                0 => {
+                    let i = stmt_starts[rng.gen_range(0..stmt_starts.len())];
+                    let stmt = stmts[rng.gen_range(0..stmts.len())];
+                    session.replace(i..i, stmt.chars().collect());
+                }
+                1 if stmt_starts.len() > 1 => {
+                    let k = rng.gen_range(0..stmt_starts.len() - 1);
+                    session.replace(stmt_starts[k]..stmt_starts[k + 1], Vec::new());
+                }
+                2 if !term_positions.is_empty() => {
+                    let i = term_positions[rng.gen_range(0..term_positions.len())];
+                    let term = terms[rng.gen_range(0..terms.len())];
+                    session.replace(i..i + 1, term.chars().collect());
+                }
+                _ => {
+                    // An edit leaving the word out of the language, which is undone right away.
+                    let start = rng.gen_range(0..=word.len());
+                    let end = rng.gen_range(start..=word.len().min(start + 4));
+                    let junk: Vec<char> = junk[rng.gen_range(0..junk.len())].chars().collect();
+                    let len = junk.len();
+                    session.replace(start..end, junk);
+                    assert_parses_like_earley_parser(&mut session);
+                    session.replace(start..start + len, word[start..end].to_vec());
+                }
+            }
+            assert_parses_like_earley_parser(&mut session);
+        }
+    }
+}
+
+#[test]
+fn earley_parser_parses_longest_prefixes() {
+    for (cfg, alphabet) in nullable_grammars() {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+
+        for word in test_cases::all_words(alphabet, 5) {
+            let longest_prefix_end =
+                (0..=word.len()).rfind(|end| cyk_parser.test(word[..*end].to_vec()));
+            for (parse_tree, end) in [
+                earley_parser.parse_prefix(word.clone()),
+                cyk_parser.parse_prefix(word.clone()),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                assert_eq!(test_cases::parse_tree_yield(&parse_tree), word[..end]);
+            }
+            assert_eq!(
+                earley_parser.parse_prefix(word.clone()).map(|(_, end)| end),
+                longest_prefix_end,
+                "{:?}",
+                word
+            );
+            assert_eq!(
+                cyk_parser.parse_prefix(word.clone()).map(|(_, end)| end),
+                longest_prefix_end,
+                "{:?}",
+                word
+            );
+        }
+    }
+}
+
+#[test]
+fn earley_parser_reads_one_statement_at_a_time() {
+    let earley_parser = EarleyParser::of(grammar!(
+        stmt => var '=' expr ';'
+        expr => term | term '+' expr
+        term => var | '1' | '(' expr ')'
+        var => 'x' | 'y'
+    ));
+
+    let mut word: Vec<_> = "x=1;y=(x+1);x=1+y".chars().collect();
+    let mut statements = Vec::new();
+    while let Some((parse_tree, end)) = earley_parser.parse_prefix(word.clone()) {
+        assert_eq!(test_cases::parse_tree_yield(&parse_tree), word[..end]);
+        statements.push(String::from_iter(word.drain(..end)));
+    }
+    assert_eq!(statements, ["x=1;", "y=(x+1);"]);
+    assert_eq!(word, "x=1+y".chars().collect::<Vec<_>>());
+}
+
+#[test]
+fn earley_parser_finds_all_parts_in_the_language() {
+    let mut grammars = nullable_grammars();
+    grammars.extend([
+        (
+            grammar!(
+                start => 'a' start | 'b'
+            ),
+            &['a', 'b'][..],
+        ),
+        (
+            grammar!(
+                start => '(' list ')'
+                list => | start list
+            ),
+            &['(', ')', 'x'][..],
+        ),
+    ]);
+
+    for (cfg, alphabet) in grammars {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+
+        for text in test_cases::all_words(alphabet, 5) {
+            let ranges: Vec<_> = (0..=text.len())
+                .flat_map(|l| (l..=text.len()).map(move |r| l..r))
+                .filter(|range| cyk_parser.test(text[range.clone()].to_vec()))
+                .collect();
+            assert_eq!(earley_parser.find_all(&text), ranges, "{:?}", text);
+
+            let mut leftmost_longest_ranges: Vec<Range<usize>> = Vec::new();
+            for range in ranges {
+                match leftmost_longest_ranges.last_mut() {
+                    Some(last) if last.start == range.start => *last = range,
+                    Some(last) if range.start < last.end.max(last.start + 1) => {}
+                    _ => leftmost_longest_ranges.push(range),
+                }
+            }
+            assert_eq!(
+                earley_parser.find_leftmost_longest(&text),
+                leftmost_longest_ranges,
+                "{:?}",
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn earley_parser_finds_balanced_parentheses_in_text() {
+    let earley_parser = EarleyParser::of(grammar!(
+        start => '(' list ')'
+        list => | start list
+    ));
+
+    let text: Vec<_> = "f(()) + (x) + (()())) ()".chars().collect();
+    let found: Vec<String> = earley_parser
+        .find_leftmost_longest(&text)
+        .into_iter()
+        .map(|range| String::from_iter(&text[range]))
+        .collect();
+    assert_eq!(found, ["(())", "(()())", "()"]);
+    assert_eq!(earley_parser.find_all(&text).len(), 6);
+}
+
+#[test]
+fn earley_parser_parses_forests_of_ambiguous_words() {
+    let cfg = grammar!(
+        expr => expr '+' expr | 'a'
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    let catalan_numbers = [1, 1, 2, 5, 14, 42, 132];
+    for (n_ops, catalan_number) in catalan_numbers.into_iter().enumerate() {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+
+        let parse_forest = earley_parser
+            .parse_forest(word.clone())
+            .expect("word is correctly parsed");
+        assert_eq!(parse_forest.is_ambiguous(), catalan_number > 1);
+        assert!(parse_forest.nodes().len() <= 4 * word.len() * word.len());
+
+        let parse_trees = parse_forest.parse_trees();
+        assert_eq!(parse_trees.len(), catalan_number);
+        for (i, parse_tree) in parse_trees.iter().enumerate() {
+            assert_eq!(test_cases::parse_tree_yield(parse_tree), word);
+            assert!(!parse_trees[..i].contains(parse_tree));
+        }
+        assert!(parse_trees.contains(&parse_forest.parse_tree()));
+
+        let lazy_parse_trees: Vec<_> = parse_forest.into_parse_trees().collect();
+        assert_eq!(lazy_parse_trees.len(), catalan_number);
+        for (i, parse_tree) in lazy_parse_trees.iter().enumerate() {
+            assert!(parse_trees.contains(parse_tree));
+            assert!(!lazy_parse_trees[..i].contains(parse_tree));
+        }
+    }
+
+    assert!(earley_parser.parse_forest(vec!['a', '+']).is_none());
+}
+
+#[test]
+fn earley_parser_parses_forests_of_unambiguous_words() {
+    for (cfg, alphabet) in nullable_grammars().into_iter().skip(4) {
+        let earley_parser = EarleyParser::of(cfg);
+
+        for word in test_cases::all_words(alphabet, 5) {
+            let parse_tree = earley_parser.parse(word.clone());
+            let parse_forest = earley_parser.parse_forest(word);
+            assert_eq!(parse_tree.is_ok(), parse_forest.is_some());
+            let Some(parse_forest) = parse_forest else {
+                continue;
+            };
+            assert!(!parse_forest.is_ambiguous());
+            assert_eq!(parse_forest.parse_trees(), vec![parse_tree.unwrap()]);
+        }
+    }
+}
+
+#[test]
+fn earley_parser_parses_forests_with_cycles() {
+    let cfg = grammar!(
+        start => start | a 'x' | 'x'
+        a => start |
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    let parse_forest = earley_parser
+        .parse_forest(vec!['x', 'x'])
+        .expect("word is correctly parsed");
+    assert!(parse_forest.is_ambiguous());
+    assert_eq!(
+        test_cases::parse_tree_yield(&parse_forest.parse_tree()),
+        vec!['x', 'x']
+    );
+    for parse_tree in parse_forest.parse_trees() {
+        assert_eq!(test_cases::parse_tree_yield(&parse_tree), vec!['x', 'x']);
+    }
+}
+
+#[test]
+fn earley_parser_counts_parse_trees() {
+    let grammars = [
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        grammar!(
+            start => a b a | 'x' start
+            a => | 'x' | a 'y'
+            b => | a 'x' 'y' 'x'
+        ),
+    ];
+
+    for cfg in grammars {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let unger_parser = UngerParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 6) {
+            let n_parse_trees = unger_parser.parse_trees(word.clone()).len();
+            assert_eq!(
+                earley_parser.count_parses(word.clone()),
+                ParseCount::Finite(BigUint::from(n_parse_trees as u64)),
+                "{:?}",
+                word
+            );
+        }
+    }
+
+    // The 40th Catalan number does not fit in 64 bits.
+    let earley_parser = EarleyParser::of(grammar!(
+        expr => expr '+' expr | 'a'
+    ));
+    let mut word = vec!['a'];
+    for _ in 0..40 {
+        word.extend(['+', 'a']);
+    }
+    let count = earley_parser.count_parses(word);
+    assert!(count.is_ambiguous());
+    assert_eq!(count.to_string(), "2622127042276492108820");
+    assert_eq!(
+        earley_parser.count_parses(vec!['a', '+']),
+        ParseCount::Finite(BigUint::from(0u32))
+    );
+}
+
+#[test]
+fn earley_parser_counts_infinitely_many_parse_trees() {
+    let cfg = grammar!(
+        start => start | a 'x' | 'x'
+        a => start |
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    let count = earley_parser.count_parses(vec!['x', 'x']);
+    assert_eq!(count, ParseCount::Infinite);
+    assert_eq!(count.to_string(), "infinitely many");
+
+    // Cycles only make the count infinite when they are part of a parse tree of the word.
+    let cfg = grammar!(
+        start => a 'x' | 'y'
+        a => | b
+        b => a | 'y'
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    assert_eq!(
+        earley_parser.count_parses(vec!['y']),
+        ParseCount::Finite(BigUint::from(1u32))
+    );
+    assert_eq!(
+        earley_parser.count_parses(vec!['y', 'x']),
+        ParseCount::Infinite
+    );
+    assert_eq!(earley_parser.count_parses(vec!['x']), ParseCount::Infinite);
+}
+
+#[test]
+fn earley_parser_parses_all_parse_trees() {
+    let grammars = [
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ];
+
+    for cfg in grammars {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let unger_parser = UngerParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 5) {
+            let parse_trees: Vec<_> = earley_parser.parse_all(word.clone()).collect();
+            let unger_parse_trees: Vec<_> = unger_parser.parse_all(word.clone()).collect();
+            assert_eq!(parse_trees.len(), unger_parse_trees.len(), "{:?}", word);
+            for (i, parse_tree) in parse_trees.iter().enumerate() {
+                assert_eq!(test_cases::parse_tree_yield(parse_tree), word);
+                assert!(unger_parse_trees.contains(parse_tree));
+                assert!(!parse_trees[..i].contains(parse_tree));
+                assert_eq!(
+                    parse_tree.n_rules_applied(),
+                    unger_parse_trees[i].n_rules_applied()
+                );
+            }
+        }
+    }
+
+    // Readings are found one at a time, even when there are too many to list.
+    let earley_parser = EarleyParser::of(grammar!(
+        expr => expr '+' expr | 'a'
+    ));
+    let mut word = vec!['a'];
+    for _ in 0..40 {
+        word.extend(['+', 'a']);
+    }
+    assert_eq!(earley_parser.parse_all(word).take(3).count(), 3);
+}
+
+#[test]
+fn earley_parser_parses_all_readings_of_sentences() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        &'static str, String,
+        s => np vp { format!("(s {} {})", _1, _2) }
+        vp => v np { format!("(vp {} {})", _1, _2) } | vp pp { format!("(vp {} {})", _1, _2) }
+        np => n { _1 } | np pp { format!("(np {} {})", _1, _2) }
+        pp => p np { format!("(pp {} {})", _1, _2) }
+        n => "I" { "I".to_string() } | "stars" { "stars".to_string() } | "telescopes" { "telescopes".to_string() }
+        v => "saw" { "saw".to_string() }
+        p => "with" { "with".to_string() }
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    let readings: Vec<_> = earley_parser
+        .parse_all(vec!["I", "saw", "stars", "with", "telescopes"])
+        .map(|parse_tree| interpreter.interpret(parse_tree))
+        .collect();
+    assert_eq!(readings.len(), 2);
+    assert!(readings.contains(&"(s I (vp (vp saw stars) (pp with telescopes)))".to_string()));
+    assert!(readings.contains(&"(s I (vp saw (np stars (pp with telescopes))))".to_string()));
+
+    assert_eq!(earley_parser.parse_all(vec!["I", "saw"]).count(), 0);
+}
+
+fn parse_tree_cost(parse_tree: &ParseTree<char>, rule_costs: &[f64]) -> f64 {
+    let mut cost = rule_costs[parse_tree.rule_idx()];
+    for child in parse_tree.children() {
+        if let ParsedSymbol::Var(child) = child {
+            cost += parse_tree_cost(child, rule_costs);
+        }
+    }
+    cost
+}
+
+#[test]
+fn earley_parser_parses_k_best_parse_trees() {
+    let grammars = [
+        (
+            grammar!(
+                start => start start | 'x' | 'y' start 'x'
+            ),
+            vec![1.0, 0.5, 2.0],
+        ),
+        // Cycles, also through nullable variables, some of which cost nothing.
+        (
+            grammar!(
+                start => a a
+                a => | b | 'x' a
+                b => a 'y' a | start
+            ),
+            vec![0.0, 1.0, 0.0, 0.25, 3.0, 0.0],
+        ),
+    ];
+
+    for (cfg, rule_costs) in grammars {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let unger_parser = UngerParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 5) {
+            let mut costs: Vec<_> = unger_parser
+                .parse_trees(word.clone())
+                .iter()
+                .map(|parse_tree| parse_tree_cost(parse_tree, &rule_costs))
+                .collect();
+            costs.sort_by(f64::total_cmp);
+
+            let k_best =
+                earley_parser.parse_k_best(word.clone(), 4, |rule_idx| rule_costs[rule_idx]);
+            assert_eq!(k_best.len(), costs.len().min(4), "{:?}", word);
+            for (i, (parse_tree, cost)) in k_best.iter().enumerate() {
+                assert_eq!(test_cases::parse_tree_yield(parse_tree), word);
+                assert!((parse_tree_cost(parse_tree, &rule_costs) - cost).abs() < 1e-9);
+                assert!((costs[i] - cost).abs() < 1e-9, "{:?}", word);
+            }
+        }
+    }
+}
+
+#[test]
+fn earley_parser_parses_k_best_readings_of_sentences() {
+    // Attaching the prepositional phrase to the verb is preferred over attaching it to the noun.
+    let (cfg, interpreter) = interpreted_grammar!(
+        &'static str, String,
+        s => np vp { format!("(s {} {})", _1, _2) }
+        vp => v np { format!("(vp {} {})", _1, _2) } | vp pp { format!("(vp {} {})", _1, _2) }
+        np => n { _1 } | np pp { format!("(np {} {})", _1, _2) }
+        pp => p np { format!("(pp {} {})", _1, _2) }
+        n => "I" { "I".to_string() } | "stars" { "stars".to_string() } | "telescopes" { "telescopes".to_string() }
+        v => "saw" { "saw".to_string() }
+        p => "with" { "with".to_string() }
+    );
+    let rule_costs = [0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    let k_best: Vec<_> = earley_parser
+        .parse_k_best(
+            vec!["I", "saw", "stars", "with", "telescopes"],
+            3,
+            |rule_idx| rule_costs[rule_idx],
+        )
+        .into_iter()
+        .map(|(parse_tree, cost)| (interpreter.interpret(parse_tree), cost))
+        .collect();
+    assert_eq!(
+        k_best,
+        vec![
+            (
+                "(s I (vp (vp saw stars) (pp with telescopes)))".to_string(),
+                1.0
+            ),
+            (
+                "(s I (vp saw (np stars (pp with telescopes))))".to_string(),
+                2.0
+            ),
+        ]
+    );
+}
+
+#[test]
+fn earley_parser_parses_right_recursion() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | product '+' sum { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | term '*' product { format!("(* {} {})", _1, _3) }
+        term => unit { _1 } | '(' sum ')' { _2 }
+        unit => 'A' { "A".to_string() } | 'B' { "B".to_string() }
+    );
+
+    let earley_parser = EarleyParser::of(cfg.clone());
+    let cyk_parser = CykParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("A", Some("A")),
+        ("A+B+A", Some("(+ A (+ B A))")),
+        ("A*B+A*(B+A)*B", Some("(+ (* A B) (* A (* (+ B A) B)))")),
+        ("A+B+A*", None),
+    ] {
+        let word: Vec<char> = word.chars().collect();
+        let result = earley_parser
+            .parse(word.clone())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+        let cyk_result = cyk_parser
+            .parse(word)
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result, cyk_result);
+    }
+}
+
+#[test]
+fn earley_parser_parses_right_recursion_through_start_var() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, usize,
+        list => 'x' list { _2 + 1 } | 'x' { 1 } | 'y' list list { _2 + _3 }
+    );
+
+    let earley_parser = EarleyParser::of(cfg);
+
+    for len in 1..100 {
+        let word = vec!['x'; len];
+        let parse_tree = earley_parser
+            .parse(word.clone())
+            .expect("word is correctly parsed");
+        assert_eq!(interpreter.interpret(parse_tree), len);
+        let parse_forest = earley_parser
+            .parse_forest(word)
+            .expect("word is correctly parsed");
+        assert!(!parse_forest.is_ambiguous());
+    }
+
+    let word: Vec<char> = "yxyxxxx".chars().collect();
+    let parse_forest = earley_parser
+        .parse_forest(word.clone())
+        .expect("word is correctly parsed");
+    assert_eq!(parse_forest.parse_trees().len(), 6);
+    for parse_tree in parse_forest.parse_trees() {
+        assert_eq!(test_cases::parse_tree_yield(&parse_tree), word);
+    }
+    assert!(!earley_parser.test("xy".chars().collect()));
+}
+
+#[test]
+fn earley_parser_parses_words_with_large_columns() {
+    let earley_parser = EarleyParser::of(grammar!(
+        expr => expr op expr | unit
+        op => '+' | '-' | '*'
+        unit => 'a' | 'b' | '(' expr ')'
+    ));
+
+    let mut word = vec!['a'];
+    for i in 0..99 {
+        word.extend(match i % 3 {
+            0 => ['+', 'b'],
+            1 => ['-', '('],
+            _ => ['a', ')'],
+        });
+    }
+    word.pop();
+
+    assert!(!earley_parser.test(word.clone()));
+    word.push(')');
+    let parse_tree = earley_parser
+        .parse(word.clone())
+        .expect("word is correctly parsed");
+    assert_eq!(test_cases::parse_tree_yield(&parse_tree), word);
+    assert!(earley_parser.parse_forest(word).unwrap().is_ambiguous());
+}
@@ -0,0 +1,285 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{BigUint, EarleyParser, ForestNodeLabel, GllParser, ParseCount, ParsedSymbol, Parser},
+    Symbol,
+};
+
+mod test_cases;
+
+#[test]
+fn gll_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => '(' ')'
+        start => '(' start ')'
+        start => start start
+    );
+
+    let gll_parser = GllParser::of(dyck_grammar);
+
+    for len in 1..12 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(gll_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn gll_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression2 '+' expression1 | expression2 '-' expression1
+        expression2 => expression3 | expression3 '*' expression2 | expression3 '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let gll_parser = GllParser::of(cfg);
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(gll_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn gll_parser_parses_left_recursion() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | sum '+' product { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | product '*' term { format!("(* {} {})", _1, _3) }
+        term => number { _1 } | '(' expr ')' { _2 }
+        number => digit { _1 } | number digit { format!("{}{}", _1, _2) }
+        digit => '0' { "0".to_string() } | '1' { "1".to_string() } | '2' { "2".to_string() }
+    );
+
+    let gll_parser = GllParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("1", Some("1")),
+        ("102", Some("102")),
+        ("1+2+0", Some("(+ (+ 1 2) 0)")),
+        ("1*2*10+(2+0)", Some("(+ (* (* 1 2) 10) (+ 2 0))")),
+        ("((1))", Some("1")),
+        ("1*", None),
+        ("1+", None),
+        ("", None),
+    ] {
+        let result = gll_parser
+            .parse(word.chars().collect())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+    }
+}
+
+#[test]
+fn gll_parser_parses_deep_indirect_left_recursion() {
+    let cfg = grammar!(
+        start => sum
+        sum => operand '+' 'x' | 'x'
+        operand => sum
+    );
+
+    let gll_parser = GllParser::of(cfg);
+
+    let n_ops = 300;
+    let mut word = vec!['x'];
+    for _ in 0..n_ops {
+        word.extend(['+', 'x']);
+    }
+
+    let parse_tree = gll_parser
+        .parse(word.clone())
+        .expect("word is correctly parsed");
+    // The tree leans left all the way down, going through `operand` for every operator.
+    let mut node = &parse_tree;
+    let mut depth = 0;
+    while let Some(ParsedSymbol::Var(child)) = node.children().first() {
+        node = child;
+        depth += 1;
+    }
+    assert_eq!(depth, 1 + 2 * n_ops);
+    assert_eq!(node.root_var_name(), "sum");
+    assert_eq!(test_cases::parse_tree_yield(&parse_tree), word);
+
+    word.push('+');
+    assert!(gll_parser.parse(word).is_err());
+}
+
+#[test]
+fn gll_parser_shares_calls_in_highly_ambiguous_words() {
+    let cfg = grammar!(
+        start => start start | 'x'
+    );
+
+    let gll_parser = GllParser::of(cfg);
+
+    // The word has a Catalan number of parse trees, which are only found in polynomial time if
+    // the calls of `start` at every position, and the forest nodes of `start` over every span, are
+    // shared between all of them.
+    let n = 40;
+    let parse_forest = gll_parser
+        .parse_forest(vec!['x'; n])
+        .expect("word is correctly parsed");
+
+    let mut catalan_number = BigUint::from(1u32);
+    for k in 0..n - 1 {
+        catalan_number = catalan_number * (4 * k + 2) / (k + 2);
+    }
+    assert_eq!(
+        parse_forest.count_parse_trees(),
+        ParseCount::Finite(catalan_number)
+    );
+
+    let var_nodes: Vec<_> = parse_forest
+        .nodes()
+        .iter()
+        .filter(|node| matches!(node.label(), ForestNodeLabel::Symbol(Symbol::Var(_))))
+        .collect();
+    assert_eq!(var_nodes.len(), n * (n + 1) / 2);
+    for node in var_nodes {
+        let n_splits = (node.end() - node.start() - 1).max(1);
+        assert_eq!(node.packed_nodes().len(), n_splits);
+    }
+}
+
+#[test]
+fn gll_parser_parses_same_forests_as_earley_parser() {
+    let grammars = [
+        // Ambiguous.
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        // Hidden left recursion.
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        // Left and right recursion, with a nullable variable in between.
+        grammar!(
+            start => start e 'x' | 'y' e start | e
+            e => | 'y'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ];
+
+    for cfg in grammars {
+        let gll_parser = GllParser::of(cfg.clone());
+        let earley_parser = EarleyParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 5) {
+            let parse_forest = gll_parser.parse_forest(word.clone());
+            let earley_parse_forest = earley_parser.parse_forest(word.clone());
+            assert_eq!(
+                parse_forest.is_some(),
+                earley_parse_forest.is_some(),
+                "{:?}",
+                word
+            );
+            let (Some(parse_forest), Some(earley_parse_forest)) =
+                (parse_forest, earley_parse_forest)
+            else {
+                continue;
+            };
+            assert_eq!(
+                parse_forest.nodes().len(),
+                earley_parse_forest.nodes().len()
+            );
+            assert_eq!(
+                test_cases::parse_tree_yield(&parse_forest.parse_tree()),
+                word
+            );
+            assert_eq!(
+                parse_forest.count_parse_trees(),
+                earley_parse_forest.count_parse_trees()
+            );
+            let parse_trees = parse_forest.parse_trees();
+            let earley_parse_trees = earley_parse_forest.parse_trees();
+            assert_eq!(parse_trees.len(), earley_parse_trees.len());
+            for parse_tree in parse_trees.iter() {
+                assert!(earley_parse_trees.contains(parse_tree));
+            }
+            assert_eq!(
+                gll_parser.parse_all(word.clone()).count(),
+                parse_trees.len()
+            );
+            let rule_cost = |rule_idx| (rule_idx % 3) as f64;
+            let costs: Vec<_> = gll_parser
+                .parse_k_best(word.clone(), 3, rule_cost)
+                .into_iter()
+                .map(|(_, cost)| cost)
+                .collect();
+            let earley_costs: Vec<_> = earley_parser
+                .parse_k_best(word.clone(), 3, rule_cost)
+                .into_iter()
+                .map(|(_, cost)| cost)
+                .collect();
+            assert_eq!(costs, earley_costs);
+        }
+    }
+}
+
+#[test]
+fn gll_parser_parses_forests_of_ambiguous_words() {
+    let cfg = grammar!(
+        expr => expr '+' expr | 'a'
+    );
+
+    let gll_parser = GllParser::of(cfg);
+
+    let catalan_numbers = [1, 1, 2, 5, 14, 42, 132];
+    for (n_ops, catalan_number) in catalan_numbers.into_iter().enumerate() {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+
+        let parse_forest = gll_parser
+            .parse_forest(word.clone())
+            .expect("word is correctly parsed");
+        assert_eq!(parse_forest.is_ambiguous(), catalan_number > 1);
+        assert_eq!(parse_forest.parse_trees().len(), catalan_number);
+    }
+
+    assert!(gll_parser.parse_forest(vec!['a', '+']).is_none());
+}
+
+#[test]
+fn gll_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let gll_parser = GllParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&gll_parser, cfg);
+    }
+}
+
+#[test]
+fn gll_parser_finds_parse_errors() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let gll_parser = GllParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&gll_parser, cfg, &['x', 'y']);
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let gll_parser = GllParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&gll_parser, cfg, alphabet);
+    }
+}
+
+#[test]
+fn gll_parser_parses_longest_prefixes() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let gll_parser = GllParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(
+            &gll_parser,
+            cfg,
+            &['x', 'y'],
+        );
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let gll_parser = GllParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(&gll_parser, cfg, alphabet);
+    }
+}
@@ -0,0 +1,247 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{EarleyParser, GlrParser, ParseCount, Parser},
+};
+
+mod test_cases;
+
+#[test]
+fn glr_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => '(' ')'
+        start => '(' start ')'
+        start => start start
+    );
+
+    let glr_parser = GlrParser::of(dyck_grammar);
+
+    for len in 1..12 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(glr_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn glr_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression2 '+' expression1 | expression2 '-' expression1
+        expression2 => expression3 | expression3 '*' expression2 | expression3 '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let glr_parser = GlrParser::of(cfg);
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(glr_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn glr_parser_parses_in_original_grammar() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | sum '+' product { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | term '*' term '*' product { format!("(* {} {} {})", _1, _3, _5) }
+        term => number { _1 } | '(' expr ')' { _2 }
+        number => digit { _1 } | number digit { format!("{}{}", _1, _2) }
+        digit => '0' { "0".to_string() } | '1' { "1".to_string() } | '2' { "2".to_string() }
+    );
+
+    let glr_parser = GlrParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("1", Some("1")),
+        ("102", Some("102")),
+        ("1+2+0", Some("(+ (+ 1 2) 0)")),
+        ("1*2*10+(2+0)", Some("(+ (* 1 2 10) (+ 2 0))")),
+        ("((1))", Some("1")),
+        ("1*2", None),
+        ("1+", None),
+        ("", None),
+    ] {
+        let result = glr_parser
+            .parse(word.chars().collect())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+    }
+}
+
+#[test]
+fn glr_parser_parses_with_epsilon_rules() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        start => opt_sign list opt_dot { format!("{}{}{}", _1, _2, _3) }
+        opt_sign => { "+".to_string() } | '-' { "-".to_string() }
+        list => { "[]".to_string() } | item list { format!("{}:{}", _1, _2) }
+        item => 'a' { "a".to_string() } | '(' start ')' { format!("({})", _2) }
+        opt_dot => { String::new() } | '.' { ".".to_string() }
+    );
+
+    let glr_parser = GlrParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("", Some("+[]")),
+        ("-", Some("-[]")),
+        (".", Some("+[].")),
+        ("a", Some("+a:[]")),
+        ("-a().a", None),
+        ("-a()a.", Some("-a:(+[]):a:[].")),
+        ("(-(a))", Some("+(-(+a:[]):[]):[]")),
+        ("a-", None),
+    ] {
+        let result = glr_parser
+            .parse(word.chars().collect())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+    }
+}
+
+#[test]
+fn glr_parser_agrees_with_earley_parser() {
+    let grammars = [
+        // Ambiguous.
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        // Hidden left recursion.
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        // Hidden right recursion.
+        grammar!(
+            start => 'x' start e | 'y'
+            e => | e e | 'x'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+        // Left and right recursion, with a nullable variable in between.
+        grammar!(
+            start => start e 'x' | 'y' e start | e
+            e => | 'y'
+        ),
+    ];
+
+    for cfg in grammars {
+        let glr_parser = GlrParser::of(cfg.clone());
+        let earley_parser = EarleyParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 8) {
+            let parse_tree = glr_parser.parse(word.clone());
+            assert_eq!(
+                parse_tree.is_ok(),
+                earley_parser.test(word.clone()),
+                "{:?}",
+                word
+            );
+            if let Ok(parse_tree) = parse_tree {
+                assert_eq!(test_cases::parse_tree_yield(&parse_tree), word);
+            }
+        }
+    }
+}
+
+#[test]
+fn glr_parser_parses_long_words() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, usize,
+        list => { 0 } | list item { _1 + _2 }
+        item => 'a' { 1 } | '(' list ')' { _2 }
+    );
+
+    let glr_parser = GlrParser::of(cfg);
+
+    let word: Vec<char> = "a(aa)((a)a)".repeat(100).chars().collect();
+    let parse_tree = glr_parser.parse(word).expect("word is correctly parsed");
+    assert_eq!(interpreter.interpret(parse_tree), 500);
+}
+
+#[test]
+fn glr_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&glr_parser, cfg);
+    }
+}
+
+#[test]
+fn glr_parser_builds_forests_while_reducing() {
+    let glr_parser = GlrParser::of(grammar!(
+        expr => expr '+' expr | 'a' | opt expr opt
+        opt => | 'b'
+    ));
+
+    // The forest keeps every way of reducing a span, in cubic space, including those going around
+    // the cycle through the nullable variables.
+    for n_ops in 0..12 {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+        let parse_forest = glr_parser
+            .parse_forest(word.clone())
+            .expect("word is correctly parsed");
+        assert!(parse_forest.nodes().len() <= 4 * word.len() * word.len() + 8);
+        assert_eq!(glr_parser.count_parses(word), ParseCount::Infinite);
+    }
+
+    let glr_parser = GlrParser::of(grammar!(
+        expr => expr '+' expr | 'a'
+    ));
+    let catalan_numbers = [1u32, 1, 2, 5, 14, 42, 132, 429, 1430, 4862];
+    for (n_ops, catalan_number) in catalan_numbers.into_iter().enumerate() {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+        assert_eq!(
+            glr_parser.count_parses(word.clone()).to_string(),
+            catalan_number.to_string()
+        );
+        assert_eq!(glr_parser.parse_all(word).count(), catalan_number as usize);
+    }
+}
+
+#[test]
+fn glr_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&glr_parser, cfg);
+    }
+}
+
+#[test]
+fn glr_parser_finds_parse_errors() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&glr_parser, cfg, &['x', 'y']);
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&glr_parser, cfg, alphabet);
+    }
+}
+
+#[test]
+fn glr_parser_parses_longest_prefixes() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(
+            &glr_parser,
+            cfg,
+            &['x', 'y'],
+        );
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let glr_parser = GlrParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(&glr_parser, cfg, alphabet);
+    }
+}
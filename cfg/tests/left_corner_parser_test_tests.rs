@@ -0,0 +1,248 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{EarleyParser, LeftCornerParser, Parser, UngerParser},
+};
+
+mod test_cases;
+
+#[test]
+fn left_corner_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => | '(' start ')' start
+    );
+
+    let left_corner_parser = LeftCornerParser::of(dyck_grammar);
+
+    for len in 1..12 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(left_corner_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn left_corner_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression2 '+' expression1 | expression2 '-' expression1
+        expression2 => expression3 | expression3 '*' expression2 | expression3 '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let left_corner_parser = LeftCornerParser::of(cfg);
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(left_corner_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn left_corner_parser_parses_sentences() {
+    let cfg = grammar! {
+        sentence => noun_phrase verb_phrase
+        noun_phrase => pronoun | proper_noun | determiner nominal
+        nominal => nominal noun | noun
+        verb_phrase => verb | verb noun_phrase | verb noun_phrase prep_phrase | verb prep_phrase
+        prep_phrase => preposition noun_phrase
+        noun => "flights" | "flight" | "breeze" | "trip" | "morning"
+        verb => "is" | "prefer" | "like" | "need" | "want" | "fly" | "do"
+        pronoun => "me" | "I" | "you" | "it"
+        proper_noun => "Alaska" | "Baltimore" | "Los Angeles" | "Chicago" | "United" | "American"
+        determiner => "the" | "a" | "an" | "this" | "these" | "that"
+        preposition => "from" | "to" | "on" | "near" | "in"
+    };
+
+    let left_corner_parser = LeftCornerParser::of(cfg.clone());
+    let earley_parser = EarleyParser::of(cfg);
+
+    for (sentence, expectation) in [
+        ("I prefer the morning flight", true),
+        ("United fly from Chicago to Los Angeles", false),
+        ("I fly to Los Angeles", true),
+        ("you need a trip", true),
+        ("this flight is", true),
+        ("the morning flight trip", false),
+        ("prefer the flight", false),
+        ("I like to", false),
+        ("", false),
+    ] {
+        let mut word: Vec<&str> = sentence.split(' ').filter(|w| !w.is_empty()).collect();
+        if let Some(i) = word.iter().position(|w| *w == "Los") {
+            word.splice(i..i + 2, ["Los Angeles"]);
+        }
+        let parse_tree = left_corner_parser.parse(word.clone());
+        assert_eq!(parse_tree.is_ok(), expectation, "{}", sentence);
+        assert_eq!(parse_tree, earley_parser.parse(word), "{}", sentence);
+    }
+}
+
+#[test]
+fn left_corner_parser_starts_rules_of_expected_variables_only() {
+    let cfg = grammar! {
+        sentence => noun_phrase verb_phrase
+        noun_phrase => determiner noun | noun
+        verb_phrase => adverb verb | adverb verb noun_phrase | "fly" "away"
+        adverb => | "really"
+        noun => "fly" | "fly" "fishing" | "time" | "arrow"
+        verb => "fly" | "flies" | "like"
+        determiner => "an" | "the"
+    };
+
+    let left_corner_parser = LeftCornerParser::of(cfg.clone());
+    let earley_parser = EarleyParser::of(cfg);
+
+    for (sentence, expectation) in [
+        ("fly fishing flies", true),
+        ("time like an arrow", true),
+        ("time fly away", true),
+        ("time really fly fly fishing", true),
+        ("the fly really like time", true),
+        ("time fly fishing", false),
+        ("time really fly away", false),
+        ("fly fishing really", false),
+    ] {
+        let word: Vec<&str> = sentence.split(' ').collect();
+        let parse_tree = left_corner_parser.parse(word.clone());
+        assert_eq!(parse_tree.is_ok(), expectation, "{}", sentence);
+        assert_eq!(parse_tree, earley_parser.parse(word), "{}", sentence);
+    }
+
+    // After the noun phrase, "fly" may only begin a verb phrase, so the rule of the noun beginning
+    // with it is not started, and "fishing" is not expected next.
+    let error = left_corner_parser
+        .parse(vec!["time", "fly", "fishing"])
+        .expect_err("word is not in the language");
+    assert_eq!(error.position(), 2);
+    assert!(!error.expected().contains(&Some("fishing")));
+    assert!(error.expected().contains(&Some("away")));
+    assert!(error.expected().contains(&Some("fly")));
+    assert!(error.vars().all(|(_, var_name)| var_name != "noun"));
+}
+
+#[test]
+fn left_corner_parser_parses_left_recursion() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | sum '+' product { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | product '*' term { format!("(* {} {})", _1, _3) }
+        term => number { _1 } | '(' expr ')' { _2 }
+        number => digit { _1 } | number digit { format!("{}{}", _1, _2) }
+        digit => '0' { "0".to_string() } | '1' { "1".to_string() } | '2' { "2".to_string() }
+    );
+
+    let left_corner_parser = LeftCornerParser::of(cfg);
+
+    for (word, expected_result) in [
+        ("1", Some("1")),
+        ("102", Some("102")),
+        ("1+2+0", Some("(+ (+ 1 2) 0)")),
+        ("1*2*10+(2+0)", Some("(+ (* (* 1 2) 10) (+ 2 0))")),
+        ("((1))", Some("1")),
+        ("1*", None),
+        ("1+", None),
+        ("", None),
+    ] {
+        let result = left_corner_parser
+            .parse(word.chars().collect())
+            .map(|parse_tree| interpreter.interpret(parse_tree));
+        assert_eq!(result.as_deref().ok(), expected_result);
+    }
+}
+
+#[test]
+fn left_corner_parser_parses_like_unger_parser() {
+    let grammars = [
+        // Ambiguous.
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        // Hidden left recursion.
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        // Nullable variables everywhere.
+        grammar!(
+            start => a b a | 'x' start
+            a => | 'x' | a 'y'
+            b => | a 'x'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ];
+
+    for cfg in grammars {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        let unger_parser = UngerParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 5) {
+            let parse_trees = unger_parser.parse_trees(word.clone());
+            match left_corner_parser.parse(word.clone()) {
+                Ok(parse_tree) => assert!(parse_trees.contains(&parse_tree), "{:?}", word),
+                Err(_) => assert!(parse_trees.is_empty(), "{:?}", word),
+            }
+        }
+    }
+}
+
+#[test]
+fn left_corner_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&left_corner_parser, cfg);
+    }
+}
+
+#[test]
+fn left_corner_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&left_corner_parser, cfg);
+    }
+}
+
+#[test]
+fn left_corner_parser_finds_parse_errors() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(
+            &left_corner_parser,
+            cfg,
+            &['x', 'y'],
+        );
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(
+            &left_corner_parser,
+            cfg,
+            alphabet,
+        );
+    }
+}
+
+#[test]
+fn left_corner_parser_parses_longest_prefixes() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(
+            &left_corner_parser,
+            cfg,
+            &['x', 'y'],
+        );
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let left_corner_parser = LeftCornerParser::of(cfg.clone());
+        test_cases::assert_parses_longest_prefixes_like_earley_parser(
+            &left_corner_parser,
+            cfg,
+            alphabet,
+        );
+    }
+}
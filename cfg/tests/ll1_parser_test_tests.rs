@@ -0,0 +1,129 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{EarleyParser, LL1Parser, Parser},
+};
+
+mod test_cases;
+
+#[test]
+fn ll1_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => | '(' start ')' start
+    );
+
+    let ll1_parser = LL1Parser::of(dyck_grammar).expect("grammar is LL(1)");
+
+    for len in 1..12 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(ll1_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn ll1_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 rest1
+        rest1 => | '+' expression1 | '-' expression1
+        expression2 => expression3 rest2
+        rest2 => | '*' expression2 | '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let ll1_parser = LL1Parser::of(cfg).expect("grammar is LL(1)");
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(ll1_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn ll1_parser_parses_like_earley_parser() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        start => opt_sign list opt_dot { format!("{}{}{}", _1, _2, _3) }
+        opt_sign => { "+".to_string() } | '-' { "-".to_string() }
+        list => { "[]".to_string() } | item list { format!("{}:{}", _1, _2) }
+        item => 'a' { "a".to_string() } | '(' start ')' { format!("({})", _2) }
+        opt_dot => { String::new() } | '.' { ".".to_string() }
+    );
+
+    let ll1_parser = LL1Parser::of(cfg.clone()).expect("grammar is LL(1)");
+    let earley_parser = EarleyParser::of(cfg);
+
+    for word in test_cases::all_words(&['-', 'a', '(', ')', '.', 'b'], 5) {
+        let parse_tree = ll1_parser.parse(word.clone());
+        assert_eq!(parse_tree, earley_parser.parse(word.clone()), "{:?}", word);
+    }
+
+    let parse_tree = ll1_parser
+        .parse("-a(.)a.".chars().collect())
+        .expect("word is correctly parsed");
+    assert_eq!(interpreter.interpret(parse_tree), "-a:(+[].):a:[].");
+}
+
+#[test]
+fn ll1_parser_reports_conflicts() {
+    let cfg = grammar!(
+        stmt => 'i' stmt opt_else | 'x'
+        opt_else => | 'e' stmt
+        list => list 'x' | 'y'
+    );
+
+    let Err(conflicts) = LL1Parser::of(cfg) else {
+        panic!("grammar is not LL(1)");
+    };
+
+    let mut conflicts: Vec<_> = conflicts
+        .conflicts()
+        .iter()
+        .map(|conflict| {
+            let var_name = conflicts.var_name(conflict.var()).to_string();
+            let rule_idxs: Vec<_> = conflict.rules().iter().map(|(idx, _)| *idx).collect();
+            (var_name, conflict.lookahead().copied(), rule_idxs)
+        })
+        .collect();
+    conflicts.sort();
+    assert_eq!(
+        conflicts,
+        [
+            ("list".to_string(), Some('y'), vec![4, 5]),
+            ("opt_else".to_string(), Some('e'), vec![2, 3]),
+        ]
+    );
+}
+
+#[test]
+fn ll1_parser_formats_conflicts() {
+    let cfg = grammar!(
+        start => a 'x' | 'x'
+        a =>
+    );
+
+    let Err(conflicts) = LL1Parser::of(cfg) else {
+        panic!("grammar is not LL(1)");
+    };
+
+    assert_eq!(
+        conflicts.to_string(),
+        "the grammar is not LL(1):\n  `start` on 'x' may be derived by `start => a 'x'` or `start => 'x'`"
+    );
+}
+
+#[test]
+fn ll1_parser_parses_all_parse_trees() {
+    let dyck_grammar = grammar!(
+        start => | '(' start ')' start
+    );
+
+    let ll1_parser = LL1Parser::of(dyck_grammar).expect("grammar is LL(1)");
+
+    for len in 0..8 {
+        for (brkt_seq, _) in test_cases::brkt_seq_test_cases(len) {
+            let parse_trees: Vec<_> = ll1_parser.parse_all(brkt_seq.clone()).collect();
+            assert_eq!(parse_trees, Vec::from_iter(ll1_parser.parse(brkt_seq).ok()));
+        }
+    }
+}
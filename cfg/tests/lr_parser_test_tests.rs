@@ -0,0 +1,193 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{EarleyParser, LrAction, LrParser, LrTableKind, Parser},
+};
+
+mod test_cases;
+
+const KINDS: [LrTableKind; 4] = [
+    LrTableKind::Lr0,
+    LrTableKind::Slr1,
+    LrTableKind::Lalr1,
+    LrTableKind::Lr1,
+];
+
+#[test]
+fn lr_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => '(' list ')'
+        list => | list start
+    );
+
+    for kind in KINDS {
+        let lr_parser = LrParser::of(dyck_grammar.clone(), kind).expect("grammar is LR(0)");
+
+        for len in 1..12 {
+            for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+                let mut word = vec!['('];
+                word.extend(brkt_seq);
+                word.push(')');
+                assert_eq!(lr_parser.test(word), expectation);
+            }
+        }
+    }
+}
+
+#[test]
+fn lr_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression1 '+' expression2 | expression1 '-' expression2
+        expression2 => expression3 | expression2 '*' expression3 | expression2 '/' expression3
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    assert!(LrParser::of(cfg.clone(), LrTableKind::Lr0).is_err());
+
+    for kind in [LrTableKind::Slr1, LrTableKind::Lalr1, LrTableKind::Lr1] {
+        let lr_parser = LrParser::of(cfg.clone(), kind).expect("grammar is SLR(1)");
+
+        for (word, expected_result) in test_cases::expression_test_cases() {
+            let word = word.chars().collect();
+            assert_eq!(lr_parser.test(word), *expected_result);
+        }
+    }
+}
+
+#[test]
+fn lr_parser_distinguishes_table_kinds() {
+    // LALR(1) but not SLR(1), as `l` may be followed by '=' but not where it is reduced to `r`.
+    let cfg = grammar!(
+        s => l '=' r | r
+        l => '*' r | 'x'
+        r => l
+    );
+    assert!(LrParser::of(cfg.clone(), LrTableKind::Slr1).is_err());
+    let lr_parser = LrParser::of(cfg.clone(), LrTableKind::Lalr1).expect("grammar is LALR(1)");
+    assert!(lr_parser.test("*x=**x".chars().collect()));
+    assert!(!lr_parser.test("x=x=x".chars().collect()));
+
+    // LR(1) but not LALR(1), as merging the states after 'c' mixes up the lookaheads of `a` and
+    // `b`.
+    let cfg = grammar!(
+        s => 'x' a 'y' | 'x' b 'z' | a 'z' | b 'y'
+        a => 'c'
+        b => 'c'
+    );
+    let Err(conflicts) = LrParser::of(cfg.clone(), LrTableKind::Lalr1) else {
+        panic!("grammar is not LALR(1)");
+    };
+    assert!(conflicts
+        .conflicts()
+        .iter()
+        .all(|conflict| !conflict.is_shift_reduce()));
+    let lr_parser = LrParser::of(cfg, LrTableKind::Lr1).expect("grammar is LR(1)");
+    for (word, expectation) in [("xcy", true), ("xcz", true), ("cz", true), ("cy", true)] {
+        assert_eq!(lr_parser.test(word.chars().collect()), expectation);
+    }
+    assert!(!lr_parser.test("xc".chars().collect()));
+}
+
+#[test]
+fn lr_parser_parses_like_earley_parser() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        expr => sum { _1 }
+        sum => product { _1 } | sum '+' product { format!("(+ {} {})", _1, _3) }
+        product => term { _1 } | product '*' term { format!("(* {} {})", _1, _3) }
+        term => 'a' { "a".to_string() } | '(' opt_expr ')' { _2 }
+        opt_expr => { "()".to_string() } | expr { _1 }
+    );
+
+    let earley_parser = EarleyParser::of(cfg.clone());
+    for kind in [LrTableKind::Slr1, LrTableKind::Lalr1, LrTableKind::Lr1] {
+        let lr_parser = LrParser::of(cfg.clone(), kind).expect("grammar is SLR(1)");
+        for word in test_cases::all_words(&['a', '+', '*', '(', ')', 'b'], 5) {
+            let parse_tree = lr_parser.parse(word.clone());
+            assert_eq!(parse_tree, earley_parser.parse(word.clone()), "{:?}", word);
+        }
+    }
+
+    let lr_parser = LrParser::of(cfg, LrTableKind::Lalr1).expect("grammar is LALR(1)");
+
+    let parse_tree = lr_parser
+        .parse("a+a*(a+())".chars().collect())
+        .expect("word is correctly parsed");
+    assert_eq!(interpreter.interpret(parse_tree), "(+ a (* a (+ a ())))");
+}
+
+#[test]
+fn lr_parser_reports_conflicts_with_counterexamples() {
+    let cfg = grammar!(
+        expr => expr '+' expr | 'a'
+    );
+
+    let Err(conflicts) = LrParser::of(cfg, LrTableKind::Lr1) else {
+        panic!("grammar is ambiguous");
+    };
+
+    let [conflict] = conflicts.conflicts() else {
+        panic!("expected a single conflict");
+    };
+    assert_eq!(conflict.lookahead(), Some(&'+'));
+    assert_eq!(conflict.actions(), [LrAction::Shift, LrAction::Reduce(0)]);
+    assert_eq!(
+        conflict.derivations(),
+        [vec![(0, 2), (0, 1)], vec![(0, 0), (0, 3)]]
+    );
+    assert_eq!(
+        conflicts.to_string(),
+        format!(
+            "the grammar is not LR(1):\n  shift/reduce conflict in state {} on '+':\n    example: expr '+' expr • '+'\n    shift derivation:\n      expr => expr '+' expr\n                       ↳ expr => expr • '+' expr\n    reduce derivation (rule 0):\n      expr => expr '+' expr\n              ↳ expr => expr '+' expr •\n",
+            conflict.state()
+        )
+    );
+}
+
+#[test]
+fn lr_parser_reports_reduce_reduce_conflicts() {
+    let cfg = grammar!(
+        start => a 'x' | b 'x'
+        a => 'y'
+        b => 'y'
+    );
+
+    let Err(conflicts) = LrParser::of(cfg, LrTableKind::Lr1) else {
+        panic!("grammar is not LR(1)");
+    };
+
+    let [conflict] = conflicts.conflicts() else {
+        panic!("expected a single conflict");
+    };
+    assert!(!conflict.is_shift_reduce());
+    assert_eq!(
+        conflict.actions(),
+        [LrAction::Reduce(2), LrAction::Reduce(3)]
+    );
+    assert_eq!(
+        conflict.derivations(),
+        [vec![(0, 0), (2, 1)], vec![(1, 0), (3, 1)]]
+    );
+}
+
+#[test]
+fn lr_parser_parses_all_parse_trees() {
+    let dyck_grammar = grammar!(
+        start => '(' list ')'
+        list => | list start
+    );
+
+    for kind in KINDS {
+        let lr_parser = LrParser::of(dyck_grammar.clone(), kind).expect("grammar is LR(0)");
+
+        for len in 0..8 {
+            for (brkt_seq, _) in test_cases::brkt_seq_test_cases(len) {
+                let mut word = vec!['('];
+                word.extend(brkt_seq);
+                word.push(')');
+                let parse_trees: Vec<_> = lr_parser.parse_all(word.clone()).collect();
+                assert_eq!(parse_trees, Vec::from_iter(lr_parser.parse(word).ok()));
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeSet;
+
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{
+        BestDerivation, Boolean, Counting, CykParser, Derivations, EarleyParser, LogSumExp, Parser,
+        SemiringParser, Tropical, UngerParser, Viterbi,
+    },
+};
+
+mod test_cases;
+
+#[test]
+fn semiring_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => | '(' start ')' start
+    );
+
+    let semiring_parser = SemiringParser::of(dyck_grammar);
+
+    for len in 1..10 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(
+                semiring_parser.weight(brkt_seq.clone(), |_| Boolean(true)),
+                Some(Boolean(expectation))
+            );
+            assert_eq!(semiring_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn semiring_parser_tests_like_cyk_and_earley_parsers() {
+    let grammars = [
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ];
+
+    for cfg in grammars {
+        let semiring_parser = SemiringParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg.clone());
+        let earley_parser = EarleyParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 6) {
+            let expectation = cyk_parser.test(word.clone());
+            assert_eq!(earley_parser.test(word.clone()), expectation);
+            assert_eq!(
+                semiring_parser.weight(word.clone(), |_| Boolean(true)),
+                Some(Boolean(expectation))
+            );
+            assert_eq!(semiring_parser.test(word), expectation);
+        }
+    }
+}
+
+#[test]
+fn semiring_parser_counts_and_lists_parse_trees() {
+    let grammars = [
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        grammar!(
+            start => a b a | 'x' start
+            a => | 'x' | a 'y'
+            b => | a 'x' 'y' 'x'
+        ),
+    ];
+
+    for cfg in grammars {
+        let semiring_parser = SemiringParser::of(cfg.clone());
+        let unger_parser = UngerParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 6) {
+            let parse_trees = unger_parser.parse_trees(word.clone());
+
+            let count = semiring_parser.weight(word.clone(), |_| Counting(1));
+            assert_eq!(
+                count,
+                Some(Counting(parse_trees.len() as u64)),
+                "{:?}",
+                word
+            );
+
+            let derivations = semiring_parser
+                .weight(word.clone(), Derivations::of_rule)
+                .unwrap();
+            let derivation_trees: Vec<_> = derivations
+                .0
+                .iter()
+                .map(|derivation| semiring_parser.derivation_tree(derivation))
+                .collect();
+            assert_eq!(derivation_trees.len(), parse_trees.len());
+            for parse_tree in derivation_trees.iter() {
+                assert!(parse_trees.contains(parse_tree), "{:?}", word);
+            }
+
+            match semiring_parser.parse(word.clone()) {
+                Ok(parse_tree) => assert!(parse_trees.contains(&parse_tree)),
+                Err(_) => assert!(parse_trees.is_empty()),
+            }
+        }
+    }
+
+    let catalan_numbers = [1, 1, 2, 5, 14, 42, 132, 429, 1430, 4862];
+    let semiring_parser = SemiringParser::of(grammar!(
+        expr => expr '+' expr | 'a'
+    ));
+    for (n_ops, catalan_number) in catalan_numbers.into_iter().enumerate() {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+        assert_eq!(
+            semiring_parser.weight(word, |_| Counting(1)),
+            Some(Counting(catalan_number))
+        );
+    }
+}
+
+#[test]
+fn semiring_parser_finds_best_parse_trees() {
+    // Attaching the prepositional phrase to the verb is more likely than to the noun.
+    let (cfg, interpreter) = interpreted_grammar!(
+        &'static str, String,
+        s => np vp { format!("(s {} {})", _1, _2) }
+        vp => v np { format!("(vp {} {})", _1, _2) } | vp pp { format!("(vp {} {})", _1, _2) }
+        np => n { _1 } | np pp { format!("(np {} {})", _1, _2) }
+        pp => p np { format!("(pp {} {})", _1, _2) }
+        n => "I" { "I".to_string() } | "stars" { "stars".to_string() } | "telescopes" { "telescopes".to_string() }
+        v => "saw" { "saw".to_string() }
+        p => "with" { "with".to_string() }
+    );
+    let probabilities = [1.0, 0.7, 0.3, 0.8, 0.2, 1.0, 0.4, 0.3, 0.3, 1.0, 1.0];
+
+    let semiring_parser = SemiringParser::of(cfg);
+    let word = vec!["I", "saw", "stars", "with", "telescopes"];
+
+    let best = semiring_parser
+        .weight(word.clone(), |rule_idx| {
+            BestDerivation::of_rule(Viterbi(probabilities[rule_idx]), rule_idx)
+        })
+        .unwrap();
+    let parse_tree = semiring_parser.derivation_tree(&best.derivation.unwrap());
+    assert_eq!(
+        interpreter.interpret(parse_tree),
+        "(s I (vp (vp saw stars) (pp with telescopes)))"
+    );
+    let noun_phrases = (0.8 * 0.4) * (0.8 * 0.3) * (0.8 * 0.3);
+    let verb_attachment = noun_phrases * 0.3 * 0.7;
+    let noun_attachment = noun_phrases * 0.7 * 0.2;
+    assert!((best.weight.0 - verb_attachment).abs() < 1e-12);
+
+    let viterbi = semiring_parser.weight(word.clone(), |rule_idx| Viterbi(probabilities[rule_idx]));
+    assert_eq!(viterbi, Some(best.weight));
+
+    let total = semiring_parser
+        .weight(word.clone(), |rule_idx| {
+            LogSumExp(probabilities[rule_idx].ln())
+        })
+        .unwrap();
+    assert!((total.0 - (verb_attachment + noun_attachment).ln()).abs() < 1e-9);
+
+    // With costs, the cheapest parse tree is the most likely one.
+    let best = semiring_parser
+        .weight(word, |rule_idx| {
+            BestDerivation::of_rule(Tropical(-probabilities[rule_idx].ln()), rule_idx)
+        })
+        .unwrap();
+    let parse_tree = semiring_parser.derivation_tree(&best.derivation.unwrap());
+    assert_eq!(
+        interpreter.interpret(parse_tree),
+        "(s I (vp (vp saw stars) (pp with telescopes)))"
+    );
+    assert!((best.weight.0 + verb_attachment.ln()).abs() < 1e-9);
+}
+
+#[test]
+fn semiring_parser_weighs_empty_words() {
+    let cfg = grammar!(
+        start => a a | 'x'
+        a => | b
+        b => | 'y'
+    );
+
+    let semiring_parser = SemiringParser::of(cfg);
+
+    assert_eq!(
+        semiring_parser.weight(vec![], |_| Counting(1)),
+        Some(Counting(4))
+    );
+    let derivations = semiring_parser
+        .weight(vec![], Derivations::of_rule)
+        .unwrap();
+    assert_eq!(
+        derivations.0,
+        BTreeSet::from([
+            vec![0, 2, 2],
+            vec![0, 2, 3, 4],
+            vec![0, 3, 4, 2],
+            vec![0, 3, 4, 3, 4],
+        ])
+    );
+}
+
+#[test]
+fn semiring_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let semiring_parser = SemiringParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&semiring_parser, cfg);
+    }
+}
+
+#[test]
+fn semiring_parser_weighs_cycles_only_when_they_settle() {
+    let cfg = grammar!(
+        start => a a
+        a => | b | 'x' a
+        b => a 'y' a | start
+    );
+
+    let semiring_parser = SemiringParser::of(cfg.clone());
+    let unger_parser = UngerParser::of(cfg);
+
+    for word in test_cases::all_words(&['x', 'y'], 4) {
+        let count = semiring_parser.weight(word.clone(), |_| Counting(1));
+        let total = semiring_parser.weight(word.clone(), |_| LogSumExp(0.5f64.ln()));
+        let cheapest = semiring_parser.weight(word.clone(), |_| Tropical(1.0));
+        let parse_trees = unger_parser.parse_trees(word.clone());
+        if parse_trees.is_empty() {
+            assert_eq!(count, Some(Counting(0)));
+            assert_eq!(total, Some(LogSumExp(f64::NEG_INFINITY)));
+            assert_eq!(cheapest, Some(Tropical(f64::INFINITY)));
+        } else {
+            // A word with a parse tree has infinitely many, since `start` derives itself.
+            assert_eq!(count, None, "{:?}", word);
+            assert_eq!(total, None, "{:?}", word);
+            let fewest_rules = parse_trees
+                .iter()
+                .map(|parse_tree| parse_tree.n_rules_applied())
+                .min()
+                .unwrap();
+            assert_eq!(cheapest, Some(Tropical(fewest_rules as f64)), "{:?}", word);
+        }
+    }
+}
@@ -1,3 +1,13 @@
+// Shared by several test crates, each of which only uses some of the test cases.
+#![allow(dead_code)]
+
+use cfg::{
+    grammar,
+    parse::{EarleyParser, ParseTree, ParsedSymbol, Parser},
+    Cfg, Rule, Symbol, Var,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
 pub fn brkt_seq_test_cases(len: usize) -> Vec<(Vec<char>, bool)> {
     let mut brkt_seqs = Vec::new();
     for mask in 0..1 << len {
@@ -49,3 +59,251 @@ fn is_balanced_brkt_seq(brkt_seq: &Vec<char>) -> bool {
     is_balanced &= balance == 0;
     is_balanced
 }
+
+pub fn all_words(alphabet: &[char], max_len: usize) -> Vec<Vec<char>> {
+    let mut words = vec![Vec::new()];
+    let mut last_words = vec![Vec::new()];
+    for _ in 0..max_len {
+        last_words = last_words
+            .iter()
+            .flat_map(|word: &Vec<char>| {
+                alphabet.iter().map(move |ch| {
+                    let mut word = word.clone();
+                    word.push(*ch);
+                    word
+                })
+            })
+            .collect();
+        words.extend(last_words.iter().cloned());
+    }
+    words
+}
+
+pub fn parse_tree_yield(parse_tree: &ParseTree<char>) -> Vec<char> {
+    let mut word = Vec::new();
+    for child in parse_tree.children() {
+        match child {
+            ParsedSymbol::Term(term) => word.push(*term),
+            ParsedSymbol::Var(parse_tree) => word.extend(parse_tree_yield(parse_tree)),
+            ParsedSymbol::Error(terms) => word.extend(terms),
+        }
+    }
+    word
+}
+
+/// Grammars with ambiguous words, also with hidden left recursion, nullable variables and cycles.
+pub fn ambiguous_grammars() -> Vec<Cfg<char>> {
+    vec![
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        grammar!(
+            start => start e 'x' | 'y' e start | e
+            e => | 'y'
+        ),
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ]
+}
+
+/// Random grammars over `x` and `y`, each with a cycle of unit rules and a variable deriving no
+/// word, which random rules may refer to. The random rules often make more variables nullable,
+/// cyclic or derive no word. Grammars with words of up to five terminals with more than a hundred
+/// parse trees are left out, so that the trees can be compared one by one.
+pub fn random_grammars(n_grammars: usize) -> Vec<Cfg<char>> {
+    let mut rng = StdRng::seed_from_u64(17);
+    let words = all_words(&['x', 'y'], 5);
+    std::iter::repeat_with(|| {
+        let mut cfg_builder = Cfg::builder();
+        let n_vars = rng.gen_range(2..=4);
+        let vars: Vec<Var> = (0..n_vars)
+            .map(|i| cfg_builder.add_var(format!("v{}", i).into()))
+            .collect();
+        let dead = cfg_builder.add_var("dead".into());
+
+        let (a, b) = (
+            vars[rng.gen_range(0..n_vars)],
+            vars[rng.gen_range(0..n_vars)],
+        );
+        cfg_builder.add_rules([
+            Rule::new(a, vec![Symbol::Var(b)]),
+            Rule::new(b, vec![Symbol::Var(a)]),
+            Rule::new(dead, vec![Symbol::Term('x'), Symbol::Var(dead)]),
+        ]);
+        for _ in 0..rng.gen_range(3..=7) {
+            let head = vars[rng.gen_range(0..n_vars)];
+            let body = (0..rng.gen_range(0..=3))
+                .map(|_| match rng.gen_range(0..8) {
+                    0 => Symbol::Var(dead),
+                    1..=4 => Symbol::Var(vars[rng.gen_range(0..n_vars)]),
+                    _ => Symbol::Term(['x', 'y'][rng.gen_range(0..2)]),
+                })
+                .collect();
+            cfg_builder.add_rule(Rule::new(head, body));
+        }
+        cfg_builder.build(vars[0])
+    })
+    .filter(|cfg| {
+        let earley_parser = EarleyParser::of(cfg.clone());
+        words
+            .iter()
+            .all(|word| earley_parser.parse_all(word.clone()).nth(100).is_none())
+    })
+    .take(n_grammars)
+    .collect()
+}
+
+/// Checks that the parser finds the same parse trees of every word of up to five terminals as the
+/// Earley parser, in order of the number of rules applied in them, and counts them the same.
+pub fn assert_finds_parse_trees_like_earley_parser(
+    parser: &impl Parser<Vec<char>, TermType = char>,
+    cfg: Cfg<char>,
+) {
+    let earley_parser = EarleyParser::of(cfg);
+    for word in all_words(&['x', 'y'], 5) {
+        let parse_trees: Vec<_> = parser.parse_all(word.clone()).collect();
+        let earley_parse_trees: Vec<_> = earley_parser.parse_all(word.clone()).collect();
+        assert_eq!(parse_trees.len(), earley_parse_trees.len(), "{:?}", word);
+        assert_eq!(
+            parser.count_parses(word.clone()),
+            earley_parser.count_parses(word.clone()),
+            "{:?}",
+            word
+        );
+        let rule_cost = |rule_idx| (rule_idx % 3) as f64;
+        assert_eq!(
+            parser
+                .parse_k_best(word.clone(), 3, rule_cost)
+                .into_iter()
+                .map(|(_, cost)| cost)
+                .collect::<Vec<_>>(),
+            earley_parser
+                .parse_k_best(word.clone(), 3, rule_cost)
+                .into_iter()
+                .map(|(_, cost)| cost)
+                .collect::<Vec<_>>(),
+            "{:?}",
+            word
+        );
+        for (parse_tree, earley_parse_tree) in parse_trees.iter().zip(earley_parse_trees.iter()) {
+            assert_eq!(parse_tree_yield(parse_tree), word);
+            assert!(earley_parse_trees.contains(parse_tree), "{:?}", word);
+            assert_eq!(
+                parse_tree.n_rules_applied(),
+                earley_parse_tree.n_rules_applied()
+            );
+        }
+    }
+}
+
+/// Grammars in whose languages many words go wrong, along with their terminals.
+pub fn error_grammars() -> Vec<(Cfg<char>, &'static [char])> {
+    vec![
+        (
+            grammar!(
+                expr => sum
+                sum => product | sum '+' product
+                product => term | product '*' term
+                term => 'x' | '(' opt_expr ')'
+                opt_expr => | expr
+            ),
+            &['x', '+', '*', '(', ')'],
+        ),
+        (
+            grammar!(
+                start => a b c 'x' c b a
+                a => b | 'a' |
+                b => c c | 'b'
+                c => a |
+            ),
+            &['a', 'b', 'x'],
+        ),
+        (
+            grammar!(
+                start => list
+                list => | item opt_sep list
+                item => 'a' | '[' list ']'
+                opt_sep => | ','
+            ),
+            &['a', '[', ']', ','],
+        ),
+        (rule_less_var_grammar(), &['x', 'y']),
+        (
+            grammar!(
+                start => c b | 'x' start 'y' | 'x'
+                b => 'y' b | b 'x'
+                c => 'y'
+            ),
+            &['x', 'y'],
+        ),
+        (
+            grammar!(
+                start => 'x' start | start 'y'
+            ),
+            &['x', 'y'],
+        ),
+    ]
+}
+
+/// The grammar `start => 'y' 'x' b | ε`, where `b` has no rules at all and so derives no word.
+fn rule_less_var_grammar() -> Cfg<char> {
+    let mut cfg_builder = Cfg::builder();
+    let start = cfg_builder.add_var("start".into());
+    let b = cfg_builder.add_var("b".into());
+    cfg_builder
+        .add_rule(Rule::new(
+            start,
+            vec![Symbol::Term('y'), Symbol::Term('x'), Symbol::Var(b)],
+        ))
+        .add_rule(Rule::new(start, vec![]));
+    cfg_builder.build(start)
+}
+
+/// Checks that the parser finds the same errors as the Earley parser in every word of up to four
+/// terminals, some of which are not in the grammar.
+pub fn assert_finds_parse_errors_like_earley_parser(
+    parser: &impl Parser<Vec<char>, TermType = char>,
+    cfg: Cfg<char>,
+    alphabet: &[char],
+) {
+    let earley_parser = EarleyParser::of(cfg);
+    let alphabet = [alphabet, &['z']].concat();
+    for word in all_words(&alphabet, 4) {
+        assert_eq!(
+            parser.parse(word.clone()).err(),
+            earley_parser.parse(word.clone()).err(),
+            "{:?}",
+            word
+        );
+    }
+}
+
+/// Checks that the parser finds the same longest prefixes in the language as the Earley parser of
+/// every word of up to four terminals, some of which are not in the grammar.
+pub fn assert_parses_longest_prefixes_like_earley_parser(
+    parser: &impl Parser<Vec<char>, TermType = char>,
+    cfg: Cfg<char>,
+    alphabet: &[char],
+) {
+    let earley_parser = EarleyParser::of(cfg);
+    let alphabet = [alphabet, &['z']].concat();
+    for word in all_words(&alphabet, 4) {
+        let prefix = parser.parse_prefix(word.clone());
+        assert_eq!(
+            prefix.as_ref().map(|(_, end)| *end),
+            earley_parser.parse_prefix(word.clone()).map(|(_, end)| end),
+            "{:?}",
+            word
+        );
+        if let Some((parse_tree, end)) = prefix {
+            assert_eq!(parse_tree_yield(&parse_tree), word[..end]);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use cfg::{
+    grammar,
+    parse::{CykParser, EarleyParser, ParseCount, ParseTree, ParsedSymbol, Parser, UngerParser},
+    Var,
+};
+
+mod test_cases;
+
+#[test]
+fn unger_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => | '(' start ')' start
+    );
+
+    let unger_parser = UngerParser::of(dyck_grammar);
+
+    for len in 1..10 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(unger_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn unger_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression2 '+' expression1 | expression2 '-' expression1
+        expression2 => expression3 | expression3 '*' expression2 | expression3 '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let unger_parser = UngerParser::of(cfg);
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(unger_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn unger_parser_finds_all_parse_trees() {
+    let cfg = grammar!(
+        expr => expr '+' expr | 'a'
+    );
+
+    let unger_parser = UngerParser::of(cfg);
+
+    let catalan_numbers = [1, 1, 2, 5, 14, 42, 132];
+    for (n_ops, catalan_number) in catalan_numbers.into_iter().enumerate() {
+        let mut word = vec!['a'];
+        for _ in 0..n_ops {
+            word.extend(['+', 'a']);
+        }
+        assert_eq!(unger_parser.parse_trees(word).len(), catalan_number);
+    }
+
+    assert!(unger_parser.parse_trees(vec!['a', '+']).is_empty());
+}
+
+#[test]
+fn unger_parser_blocks_cycles_in_parse_trees() {
+    let cfg = grammar!(
+        start => v1 | v2 | v3
+        v1 => v2 | v3 | 'x'
+        v2 => v1 | v3 | 'x'
+        v3 => v1 | v2 | 'x'
+    );
+
+    let unger_parser = UngerParser::of(cfg.clone());
+    let earley_parser = EarleyParser::of(cfg);
+
+    assert_eq!(
+        earley_parser
+            .parse_forest(vec!['x'])
+            .expect("word is correctly parsed")
+            .count_parse_trees(),
+        ParseCount::Infinite
+    );
+
+    // The trees are the chains of distinct variables going down from `start` to `'x'`: 3 of one
+    // variable, 6 of two and 6 of three. The trees of a variable found while another one is being
+    // parsed lack the chains through that other one, so they must not be reused elsewhere.
+    let parse_trees = unger_parser.parse_trees(vec!['x']);
+    assert_eq!(parse_trees.len(), 15);
+    for (i, parse_tree) in parse_trees.iter().enumerate() {
+        assert!(!parse_trees[..i].contains(parse_tree));
+        assert_goes_around_no_cycle(parse_tree, 0, &mut Vec::new());
+    }
+}
+
+#[test]
+fn unger_parser_blocks_cycles_through_nullable_variables() {
+    let cfg = grammar!(
+        start => a a
+        a => | b | 'x' a
+        b => a 'y' a | start
+    );
+
+    let unger_parser = UngerParser::of(cfg);
+
+    for word in test_cases::all_words(&['x', 'y'], 4) {
+        let parse_trees = unger_parser.parse_trees(word.clone());
+        assert_eq!(!parse_trees.is_empty(), unger_parser.test(word.clone()));
+        for (i, parse_tree) in parse_trees.iter().enumerate() {
+            assert!(!parse_trees[..i].contains(parse_tree), "{:?}", word);
+            assert_eq!(test_cases::parse_tree_yield(parse_tree), word);
+            assert_goes_around_no_cycle(parse_tree, 0, &mut Vec::new());
+        }
+    }
+}
+
+/// Asserts that no variable of the tree derives the same part of the word as one of its
+/// ancestors, given the position where the tree starts and the variables and parts of the word of
+/// its ancestors, and returns the position where the tree ends.
+fn assert_goes_around_no_cycle(
+    parse_tree: &ParseTree<char>,
+    start: usize,
+    path: &mut Vec<(Var, usize, usize)>,
+) -> usize {
+    let end = start + test_cases::parse_tree_yield(parse_tree).len();
+    let key = (parse_tree.root_var(), start, end);
+    assert!(!path.contains(&key), "{:?} goes around a cycle", key);
+
+    path.push(key);
+    let mut i = start;
+    for child in parse_tree.children() {
+        match child {
+            ParsedSymbol::Term(_) => i += 1,
+            ParsedSymbol::Var(child) => i = assert_goes_around_no_cycle(child, i, path),
+            ParsedSymbol::Error(_) => unreachable!(),
+        }
+    }
+    path.pop();
+    end
+}
+
+#[test]
+fn unger_parser_checks_earley_and_cyk_parsers() {
+    let grammars = [
+        // Ambiguous.
+        grammar!(
+            start => start start | 'x' | 'y' start 'x'
+        ),
+        // Hidden left recursion.
+        grammar!(
+            start => e start 'x' | 'y'
+            e => | 'y'
+        ),
+        // Nullable variables everywhere.
+        grammar!(
+            start => a b a | 'x' start
+            a => | 'x' | a 'y'
+            b => | a 'x'
+        ),
+        // Cycles, also through nullable variables.
+        grammar!(
+            start => a a
+            a => | b | 'x' a
+            b => a 'y' a | start
+        ),
+    ];
+
+    for cfg in grammars {
+        let unger_parser = UngerParser::of(cfg.clone());
+        let earley_parser = EarleyParser::of(cfg.clone());
+        let cyk_parser = CykParser::of(cfg);
+
+        for word in test_cases::all_words(&['x', 'y'], 5) {
+            let parse_trees = unger_parser.parse_trees(word.clone());
+            let earley_parse_trees = earley_parser
+                .parse_forest(word.clone())
+                .map_or(Vec::new(), |parse_forest| parse_forest.parse_trees());
+            assert_eq!(parse_trees.len(), earley_parse_trees.len(), "{:?}", word);
+            for parse_tree in earley_parse_trees.iter() {
+                assert!(parse_trees.contains(parse_tree), "{:?}", word);
+            }
+
+            match cyk_parser.parse(word.clone()) {
+                Ok(parse_tree) => assert!(parse_trees.contains(&parse_tree), "{:?}", word),
+                Err(_) => assert!(parse_trees.is_empty(), "{:?}", word),
+            }
+        }
+    }
+}
+
+#[test]
+fn unger_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let unger_parser = UngerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&unger_parser, cfg);
+    }
+}
+
+#[test]
+fn unger_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let unger_parser = UngerParser::of(cfg.clone());
+        let earley_parser = EarleyParser::of(cfg.clone());
+        for word in test_cases::all_words(&['x', 'y'], 4) {
+            let parse_trees = unger_parser.parse_trees(word.clone());
+            let earley_parse_trees = earley_parser
+                .parse_forest(word.clone())
+                .map_or(Vec::new(), |parse_forest| parse_forest.parse_trees());
+            assert_eq!(parse_trees.len(), earley_parse_trees.len(), "{:?}", word);
+            for parse_tree in parse_trees.iter() {
+                assert!(earley_parse_trees.contains(parse_tree), "{:?}", word);
+            }
+        }
+        test_cases::assert_finds_parse_trees_like_earley_parser(&unger_parser, cfg);
+    }
+}
+
+#[test]
+fn unger_parser_finds_parse_errors() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let unger_parser = UngerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&unger_parser, cfg, &['x', 'y']);
+    }
+    for (cfg, alphabet) in test_cases::error_grammars() {
+        let unger_parser = UngerParser::of(cfg.clone());
+        test_cases::assert_finds_parse_errors_like_earley_parser(&unger_parser, cfg, alphabet);
+    }
+}
@@ -0,0 +1,193 @@
+use cfg::{
+    grammar, interpreted_grammar,
+    parse::{CykParser, Parser, ValiantParser},
+    Cfg, Rule, Symbol, Var,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+mod test_cases;
+
+#[test]
+fn valiant_parser_tests_brkt_seqs() {
+    let dyck_grammar = grammar!(
+        start => '(' ')'
+        start => '(' start ')'
+        start => start start
+    );
+
+    let valiant_parser = ValiantParser::of(dyck_grammar);
+
+    for len in 1..12 {
+        for (brkt_seq, expectation) in test_cases::brkt_seq_test_cases(len) {
+            assert_eq!(valiant_parser.test(brkt_seq), expectation);
+        }
+    }
+}
+
+#[test]
+fn valiant_parser_tests_expressions() {
+    let cfg = grammar!(
+        expression1 => expression2 | expression2 '+' expression1 | expression2 '-' expression1
+        expression2 => expression3 | expression3 '*' expression2 | expression3 '/' expression2
+        expression3 => term | '(' expression1 ')'
+        term => 'A' | 'B' | 'C' | 'D'
+    );
+
+    let valiant_parser = ValiantParser::of(cfg);
+
+    for (word, expected_result) in test_cases::expression_test_cases() {
+        let word = word.chars().collect();
+        assert_eq!(valiant_parser.test(word), *expected_result);
+    }
+}
+
+#[test]
+fn valiant_parser_tests_long_words() {
+    let dyck_grammar = grammar!(
+        start => '(' ')'
+        start => '(' start ')'
+        start => start start
+    );
+
+    let valiant_parser = ValiantParser::of(dyck_grammar);
+
+    // Lengths around multiples of 64 exercise blocks spanning several words of the bit matrices.
+    for len in [31, 32, 63, 64, 65, 100] {
+        let nested: Vec<char> = "("
+            .repeat(len)
+            .chars()
+            .chain(")".repeat(len).chars())
+            .collect();
+        let flat: Vec<char> = "()".repeat(len).chars().collect();
+        let mixed: Vec<char> = "(()())".repeat(len / 3).chars().collect();
+        for word in [nested, flat, mixed] {
+            assert!(valiant_parser.test(word.clone()));
+            assert!(!valiant_parser.test(word[1..].to_vec()));
+            assert!(!valiant_parser.test([&word[..], &[')', '(']].concat()));
+        }
+    }
+}
+
+#[test]
+fn valiant_parser_tests_random_long_words_like_cyk_parser() {
+    // Words of blocks `a^p b^q` with `p > q`, where only one of the many splits of a block between
+    // `a_seq` and `a_b_seq` applies.
+    let cfg = grammar!(
+        start => a_seq a_b_seq | start start
+        a_seq => 'a' a_seq | 'a'
+        a_b_seq => 'a' a_b_seq 'b' | 'a' 'b'
+    );
+
+    let cyk_parser = CykParser::of(cfg.clone());
+    let valiant_parser = ValiantParser::of(cfg);
+
+    // Words of more than 63 letters have their products taken with tables, with chunks of every
+    // length up to words of more than 255 letters. Every other word has a block with `p = q`,
+    // which makes it underivable.
+    let mut rng = StdRng::seed_from_u64(8);
+    for len in [70, 128, 200, 300] {
+        for trial in 0..4 {
+            let mut blocks = Vec::new();
+            while blocks.iter().map(|(p, q)| p + q).sum::<usize>() < len {
+                let q = rng.gen_range(1..20);
+                blocks.push((q + rng.gen_range(1..20), q));
+            }
+            if trial % 2 == 1 {
+                let i = rng.gen_range(0..blocks.len());
+                blocks[i].0 = blocks[i].1;
+            }
+            let word: Vec<char> = blocks
+                .iter()
+                .flat_map(|&(p, q)| {
+                    "a".repeat(p)
+                        .chars()
+                        .chain("b".repeat(q).chars())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            assert_eq!(
+                valiant_parser.test(word.clone()),
+                cyk_parser.test(word.clone()),
+                "{:?}",
+                word
+            );
+            assert_eq!(valiant_parser.test(word), trial % 2 == 0);
+        }
+    }
+}
+
+#[test]
+fn valiant_parser_parses_like_cyk_parser() {
+    let (cfg, interpreter) = interpreted_grammar!(
+        char, String,
+        start => a a { format!("[{}{}]", _1, _2) }
+        a => { String::new() } | b { _1 } | 'x' a { format!("x{}", _2) }
+        b => a 'y' a { format!("({}y{})", _1, _3) } | start { _1 }
+    );
+
+    let cyk_parser = CykParser::of(cfg.clone());
+    let valiant_parser = ValiantParser::of(cfg);
+
+    for word in test_cases::all_words(&['x', 'y', 'z'], 6) {
+        let parse_tree = valiant_parser.parse(word.clone());
+        assert_eq!(parse_tree, cyk_parser.parse(word.clone()), "{:?}", word);
+        if let Ok(parse_tree) = parse_tree {
+            let result = interpreter.interpret(parse_tree);
+            assert_eq!(
+                result.replace(['[', ']', '(', ')'], ""),
+                word.iter().collect::<String>()
+            );
+        }
+    }
+}
+
+#[test]
+fn valiant_parser_tests_grammars_with_many_vars() {
+    let n_vars = 150;
+    let mut cfg_builder = Cfg::builder();
+    let vars: Vec<Var> = (0..n_vars)
+        .map(|i| cfg_builder.add_var(format!("v{}", i).into()))
+        .collect();
+    for i in 0..n_vars {
+        cfg_builder.add_rules([
+            Rule::new(
+                vars[i],
+                vec![Symbol::Var(vars[(i + 1) % n_vars]), Symbol::Term('a')],
+            ),
+            Rule::new(
+                vars[i],
+                vec![Symbol::Term('b'), Symbol::Var(vars[(i * 7) % n_vars])],
+            ),
+            Rule::new(vars[i], vec![Symbol::Term(['a', 'b', 'c'][i % 3])]),
+        ]);
+    }
+    let cfg = cfg_builder.build(vars[0]);
+
+    let cyk_parser = CykParser::of(cfg.clone());
+    let valiant_parser = ValiantParser::of(cfg);
+
+    for word in test_cases::all_words(&['a', 'b', 'c'], 6) {
+        assert_eq!(
+            valiant_parser.test(word.clone()),
+            cyk_parser.test(word.clone()),
+            "{:?}",
+            word
+        );
+    }
+}
+
+#[test]
+fn valiant_parser_parses_all_parse_trees() {
+    for cfg in test_cases::ambiguous_grammars() {
+        let valiant_parser = ValiantParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&valiant_parser, cfg);
+    }
+}
+
+#[test]
+fn valiant_parser_parses_random_grammars() {
+    for cfg in test_cases::random_grammars(100) {
+        let valiant_parser = ValiantParser::of(cfg.clone());
+        test_cases::assert_finds_parse_trees_like_earley_parser(&valiant_parser, cfg);
+    }
+}